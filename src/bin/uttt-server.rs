@@ -0,0 +1,49 @@
+extern crate uttt;
+
+use std::env;
+use std::process;
+use uttt::cli::*;
+use uttt::error::UtttResult;
+use uttt::output::*;
+use uttt::server::*;
+use uttt::signals::*;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let opts = parse_options(Command::Serve, &args).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, SERVER_USAGE);
+        process::exit(2);
+    });
+    if opts.command == Command::Help {
+        println!("{}", SERVER_USAGE);
+        return;
+    }
+    if let Err(e) = run(&opts) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+    if stop_requested() {
+        process::exit(stop_exit_status());
+    }
+}
+
+fn run(opts: &Options) -> UtttResult<()> {
+    let mut out = stdout_sink(opts.format);
+    let out = &mut *out;
+    let mut stack = server_stack(opts, out)?;
+    install_stop_handlers()?;
+    // Solves stop on their own flag; the server stops on a signal.
+    stack.stop = Some(&SOLVE_STOP);
+    let listener = bind(&opts.addr)?;
+    out.value("listening", &format!("http://{}", opts.addr))?;
+    out.separator()?;
+    let result = Api::new(&stack, opts.depth)
+        .and_then(|mut api| serve(&mut api, &listener, out, opts.verbose));
+    // As in 'uttt', persist what can still be persisted, even after an error.
+    match stack.flush() {
+        Ok(count) => out.value("flushed", &count.to_string())?,
+        Err(flush_e) => eprintln!("Error while flushing: {}", flush_e),
+    }
+    if opts.verbose { out.metrics(&stack.metrics())?; }
+    result
+}
@@ -0,0 +1,488 @@
+/// Command-line interface.
+///
+/// Parses the arguments of the 'uttt' binary into `Options`; see `USAGE`. The
+/// binary (main.rs) runs the matching `run_*` function from the 'runners'
+/// module. The 'uttt-server' binary parses its options in the same way; see
+/// the 'server' module.
+
+use data::*;
+use error::*;
+use output::OutputFormat;
+use runners::RunConfig;
+use solver::*;
+use solver::export::ExportFormat;
+use solver::file::file_open;
+use solver::verify::VerifyConfig;
+use tournament::Engine;
+use training::TrainingFormat;
+use std::env;
+
+#[cfg(test)]
+mod tests;
+
+/// The environment variable read when `--db` is not given.
+pub const DB_ENV_VAR: &'static str = "UTTT_DATABASE_URL";
+
+/// The address that the server listens on when `--addr` is not given.
+pub const DEFAULT_ADDR: &'static str = "127.0.0.1:8080";
+
+pub const USAGE: &'static str = "\
+Usage: uttt <command> [options]
+
+Commands:
+  random           play random games and summarize the results
+  solve            solve a game a few plays back from the end of random games
+  backwards        solve random games from the end, back --back plays
+  full-backwards   fully solve random games from the end to the start
+  ongoing          solve random games from the end, forever
+  play             let the solver play against itself
+  interactive      play against the solver in the terminal
+  tournament       play the --engines against each other, and rate them
+  engine           speak a UCI-like engine protocol on stdin and stdout
+  training         write solved positions as training data to --output
+  replay           repeat one trial of a run, from its --manifest
+  db init          create or migrate the database schema
+  db stats         print the number of stored solutions
+  db export        write the stored solutions to --output
+  db import        load stored solutions from a binary export, --input
+  db freeze        write the stored solutions to a solution file, --output,
+                   for the mmap stack
+  db verify        check the stored solutions, and optionally --repair them
+  help             print this message
+
+Options:
+  --trials <n>     number of trials, or of games per pairing (default: 1)
+  --depth <n>      solver depth, at most 81 (default: 16)
+  --back <n>       plays back from the end, at most 81 (default: 18)
+  --seed <a,b,c,d> random number seed (default: random)
+  --manifest <f>   run manifest to write (or, for 'replay', to read)
+  --trial <n>      trial to replay (default: 1)
+  --stack <kind>   cpu, ram, ssd or mmap (default: ssd)
+  --file <path>    solution file, for the mmap stack
+  --db <url>       PostgreSQL connection string (default: $UTTT_DATABASE_URL)
+  --tablespace <t> tablespace, for 'db init' (default: none)
+  --cache-1 <n>    capacity of the ssd stack's write-delaying RAM cache
+                   (default: 5000)
+  --cache-2 <n>    capacity of the ssd stack's general RAM cache
+                   (default: 50000000)
+  --queue <n>      capacity of the ssd stack's write queue (default: 20000)
+  --batch <n>      writes per database transaction (default: 1000)
+  --admission <p>  which solutions the ssd stack persists: always, decided,
+                   balanced[:<low mark>:<multiplier>] or sample:<rate>
+                   (default: balanced:200:20000)
+  --side <x|o>     your side, for 'interactive' (default: x)
+  --time <ms>      time budget per solver play, for 'interactive' and
+                   'engine'; deepens up to --depth until it runs out
+                   (default: none)
+  --engines <e,..> engines for 'tournament': random, solver:<depth> or
+                   deepening:<depth>:<ms> (default: random,solver:4)
+  --opening <n>    random opening plays, for 'tournament' (default: 4)
+  --sample <rate>  fraction of rows to check, for 'db verify', from 0 to 1
+                   (default: 1)
+  --sample-seed <n>
+                   picks which rows are sampled (default: 0)
+  --repair         delete the rows that 'db verify' finds problems with
+  --output <path>  training data, export or solution file to write
+  --input <path>   export file to read, for 'db import'
+  --export <f>     export format: binary, csv or json (default: binary;
+                   only binary files can be imported)
+  --data <f>       training data format: tensor or csv (default: tensor)
+  --augment        also write the 7 other symmetries of each position
+  --format <f>     text or json (one JSON object per line; default: text)
+  -v, --verbose    print games and solutions as they are solved
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Random,
+    Solve,
+    Backwards,
+    FullBackwards,
+    Ongoing,
+    Play,
+    Interactive,
+    Tournament,
+    Engine,
+    Training,
+    Replay,
+    DbInit,
+    DbStats,
+    DbExport,
+    DbImport,
+    DbFreeze,
+    DbVerify,
+    Serve,
+    Help,
+}
+
+/// Which devices to solve with; see the 'solver/stacks' module.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StackKind {
+    Cpu,
+    Ram,
+    Ssd,
+    Mmap,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub trials: u16,
+    pub depth: Count,
+    pub back: Count,
+    pub seed: Option<[u32; 4]>,
+    pub manifest: Option<String>,
+    pub trial: u32,
+    pub stack: StackKind,
+    pub file: Option<String>,
+    pub db: Option<String>,
+    pub tablespace: Option<String>,
+    /// The SSD device's configuration, for the ssd stack.
+    pub ssd: SsdConfig,
+    /// The verifier's configuration, for 'db verify'.
+    pub verify: VerifyConfig,
+    pub side: Player,
+    pub time: Option<u64>,
+    pub engines: Vec<Engine>,
+    pub opening: usize,
+    pub output: Option<String>,
+    pub input: Option<String>,
+    pub export: ExportFormat,
+    pub data: TrainingFormat,
+    pub augment: bool,
+    pub format: OutputFormat,
+    pub addr: String,
+    pub verbose: bool,
+}
+
+impl Command {
+    /// Returns the command's name, as typed on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Random => "random",
+            Command::Solve => "solve",
+            Command::Backwards => "backwards",
+            Command::FullBackwards => "full-backwards",
+            Command::Ongoing => "ongoing",
+            Command::Play => "play",
+            Command::Interactive => "interactive",
+            Command::Tournament => "tournament",
+            Command::Engine => "engine",
+            Command::Training => "training",
+            Command::Replay => "replay",
+            Command::DbInit => "db init",
+            Command::DbStats => "db stats",
+            Command::DbExport => "db export",
+            Command::DbImport => "db import",
+            Command::DbFreeze => "db freeze",
+            Command::DbVerify => "db verify",
+            Command::Serve => "serve",
+            Command::Help => "help",
+        }
+    }
+}
+
+impl Options {
+    /// Returns the default options for a command.
+    pub fn new(command: Command) -> Options {
+        Options {
+            command: command,
+            trials: 1,
+            depth: 16,
+            back: 18,
+            seed: None,
+            manifest: None,
+            trial: 1,
+            stack: StackKind::Ssd,
+            file: None,
+            db: None,
+            tablespace: None,
+            ssd: SsdConfig::new(),
+            verify: VerifyConfig::new(),
+            side: Player::X,
+            time: None,
+            engines: vec![Engine::Random, Engine::Solver { depth: 4 }],
+            opening: 4,
+            output: None,
+            input: None,
+            export: ExportFormat::Binary,
+            data: TrainingFormat::Tensor,
+            augment: false,
+            format: OutputFormat::Text,
+            addr: DEFAULT_ADDR.to_string(),
+            verbose: false,
+        }
+    }
+
+    /// Returns the connection string: the `--db` option, or else the
+    /// `DB_ENV_VAR` environment variable.
+    pub fn db_params(&self) -> UtttResult<String> {
+        match self.db {
+            Some(ref db) => Ok(db.clone()),
+            None => env::var(DB_ENV_VAR).map_err(|_| UtttError::usage(
+                "E4101", &format!("--db is not given and ${} is not set",
+                                  DB_ENV_VAR))),
+        }
+    }
+
+    /// Returns the settings of a solver run.
+    pub fn run_config(&self) -> RunConfig {
+        RunConfig {
+            trials: self.trials,
+            depth: self.depth,
+            back: self.back,
+            data: self.data,
+            augment: self.augment,
+            verbose: self.verbose,
+        }
+    }
+
+    /// Returns the stack chosen by `--stack`.
+    pub fn open_stack(&self) -> UtttResult<Stack> {
+        match self.stack {
+            StackKind::Cpu => Ok(CPU_Stack::new()),
+            StackKind::Ram => Ok(RAM_CPU_Stack::new()),
+            StackKind::Ssd => SSD_CPU_Stack::with_config(
+                self.db_params()?.as_str(), &self.ssd),
+            StackKind::Mmap => {
+                let path = self.file.as_ref().ok_or_else(|| {
+                    usage("E4120", "the mmap stack needs --file")
+                })?;
+                Ok(MMAP_CPU_Stack::new(file_open(path)?))
+            },
+        }
+    }
+}
+
+// == parsing ==================================================================
+
+/// Parses the arguments (without the program name).
+pub fn parse_args(args: &[String]) -> UtttResult<Options> {
+    let mut iter = args.iter();
+    let command = match iter.next().map(|s| s.as_str()) {
+        None | Some("help") | Some("-h") | Some("--help") => Command::Help,
+        Some("random") => Command::Random,
+        Some("solve") => Command::Solve,
+        Some("backwards") => Command::Backwards,
+        Some("full-backwards") => Command::FullBackwards,
+        Some("ongoing") => Command::Ongoing,
+        Some("play") => Command::Play,
+        Some("interactive") => Command::Interactive,
+        Some("tournament") => Command::Tournament,
+        Some("engine") => Command::Engine,
+        Some("training") => Command::Training,
+        Some("replay") => Command::Replay,
+        Some("db") => match iter.next().map(|s| s.as_str()) {
+            Some("init") => Command::DbInit,
+            Some("stats") => Command::DbStats,
+            Some("export") => Command::DbExport,
+            Some("import") => Command::DbImport,
+            Some("freeze") => Command::DbFreeze,
+            Some("verify") => Command::DbVerify,
+            _ => return Err(usage(
+                "E4102", "expected 'db init', 'db stats', 'db export', \
+                          'db import', 'db freeze' or 'db verify'")),
+        },
+        Some(other) => return Err(usage(
+            "E4103", &format!("unknown command '{}'", other))),
+    };
+    parse_options(command, iter.as_slice())
+}
+
+/// Parses the options of a command.
+pub fn parse_options(command: Command, args: &[String])
+                     -> UtttResult<Options> {
+    let mut iter = args.iter();
+    let mut opts = Options::new(command);
+    while let Some(arg) = iter.next() {
+        // Accept both "--name value" and "--name=value".
+        let (name, inline) = match arg.find('=') {
+            Some(i) if arg.starts_with("--") =>
+                (&arg[.. i], Some(arg[i + 1 ..].to_string())),
+            _ => (arg.as_str(), None),
+        };
+        if name == "-v" || name == "--verbose" {
+            opts.verbose = true;
+            continue;
+        }
+        if name == "--augment" {
+            opts.augment = true;
+            continue;
+        }
+        if name == "--repair" {
+            opts.verify.repair = true;
+            continue;
+        }
+        if name == "-h" || name == "--help" {
+            opts.command = Command::Help;
+            continue;
+        }
+        let value = match inline.or_else(|| iter.next().cloned()) {
+            Some(value) => value,
+            None => return Err(usage(
+                "E4104", &format!("{} needs a value", name))),
+        };
+        match name {
+            "--trials" => opts.trials = number(name, &value)?,
+            "--depth" => opts.depth = number(name, &value)?,
+            "--back" => opts.back = number(name, &value)?,
+            "--seed" => opts.seed = Some(seed(&value)?),
+            "--manifest" => opts.manifest = Some(value),
+            "--trial" => opts.trial = number(name, &value)?,
+            "--stack" => opts.stack = stack_kind(&value)?,
+            "--file" => opts.file = Some(value),
+            "--db" => opts.db = Some(value),
+            "--tablespace" => opts.tablespace = Some(value),
+            "--cache-1" => opts.ssd.cache_1_cap = number(name, &value)?,
+            "--cache-2" => opts.ssd.cache_2_cap = number(name, &value)?,
+            "--queue" => opts.ssd.write_queue_cap = number(name, &value)?,
+            "--batch" => opts.ssd.write_batch_size = number(name, &value)?,
+            "--admission" => opts.ssd.admission = admission(&value)?,
+            "--sample" => opts.verify.sample_rate = sample_rate(&value)?,
+            "--sample-seed" => opts.verify.seed = number(name, &value)?,
+            "--side" => opts.side = side(&value)?,
+            "--time" => opts.time = Some(number(name, &value)?),
+            "--engines" => opts.engines = value.split(',')
+                .map(Engine::parse)
+                .collect::<UtttResult<Vec<Engine>>>()?,
+            "--opening" => opts.opening = number(name, &value)?,
+            "--output" => opts.output = Some(value),
+            "--input" => opts.input = Some(value),
+            "--export" => opts.export = export_format(&value)?,
+            "--data" => opts.data = data_format(&value)?,
+            "--format" => opts.format = format(&value)?,
+            "--addr" => opts.addr = value,
+            _ => return Err(usage(
+                "E4105", &format!("unknown option '{}'", name))),
+        }
+    }
+    if opts.stack == StackKind::Mmap && opts.file.is_none() {
+        return Err(usage("E4106", "the mmap stack needs --file"));
+    }
+    // A game has at most 81 plays, so neither can be more.
+    if opts.depth > 81 {
+        return Err(usage("E4134", "--depth must be at most 81"));
+    }
+    if opts.back > 81 {
+        return Err(usage("E4135", "--back must be at most 81"));
+    }
+    if opts.ssd.cache_1_cap == 0 || opts.ssd.write_batch_size == 0 {
+        return Err(usage("E4124", "--cache-1 and --batch must be at least 1"));
+    }
+    if opts.command == Command::Tournament && opts.engines.len() < 2 {
+        return Err(usage("E4115", "a tournament needs two or more engines"));
+    }
+    if opts.command == Command::Training && opts.output.is_none() {
+        return Err(usage("E4116", "training needs --output"));
+    }
+    if opts.command == Command::DbExport && opts.output.is_none() {
+        return Err(usage("E4129", "db export needs --output"));
+    }
+    if opts.command == Command::DbImport && opts.input.is_none() {
+        return Err(usage("E4130", "db import needs --input"));
+    }
+    if opts.command == Command::DbFreeze && opts.output.is_none() {
+        return Err(usage("E4136", "db freeze needs --output"));
+    }
+    if opts.command == Command::Replay && opts.manifest.is_none() {
+        return Err(usage("E4110", "replay needs --manifest"));
+    }
+    Ok(opts)
+}
+
+// == helpers ==================================================================
+
+fn number<T: ::std::str::FromStr>(name: &str, value: &str) -> UtttResult<T> {
+    value.parse::<T>().map_err(|_| usage(
+        "E4107", &format!("{} expects a number, not '{}'", name, value)))
+}
+
+/// Parses a seed as four comma-separated numbers. Brackets and spaces are
+/// ignored, so the seed printed by a run ("[1, 2, 3, 4]") can be pasted back.
+fn seed(value: &str) -> UtttResult<[u32; 4]> {
+    let parts = value.trim_matches(|c| c == '[' || c == ']')
+        .split(',')
+        .map(|part| number::<u32>("--seed", part.trim()))
+        .collect::<UtttResult<Vec<u32>>>()?;
+    if parts.len() != 4 {
+        return Err(usage("E4108", "--seed expects four numbers"));
+    }
+    Ok([parts[0], parts[1], parts[2], parts[3]])
+}
+
+fn stack_kind(value: &str) -> UtttResult<StackKind> {
+    match value {
+        "cpu" => Ok(StackKind::Cpu),
+        "ram" => Ok(StackKind::Ram),
+        "ssd" => Ok(StackKind::Ssd),
+        "mmap" => Ok(StackKind::Mmap),
+        _ => Err(usage("E4109", &format!("unknown stack '{}'", value))),
+    }
+}
+
+/// Parses an admission policy; see `AdmissionPolicy`.
+fn admission(value: &str) -> UtttResult<AdmissionPolicy> {
+    let parts = value.split(':').collect::<Vec<&str>>();
+    let name = "--admission";
+    match parts.as_slice() {
+        ["always"] => Ok(AdmissionPolicy::Always),
+        ["decided"] => Ok(AdmissionPolicy::DecidedOnly),
+        ["balanced"] => Ok(AdmissionPolicy::default_policy()),
+        ["balanced", low_mark, multiplier] => Ok(
+            AdmissionPolicy::DepthBalanced {
+                low_mark: number(name, low_mark)?,
+                multiplier: number(name, multiplier)?,
+            }),
+        ["sample", rate] => match number::<f64>(name, rate)? {
+            rate if rate >= 0.0 && rate <= 1.0 =>
+                Ok(AdmissionPolicy::Sampling { rate: rate }),
+            _ => Err(usage("E4125", "the sample rate must be from 0 to 1")),
+        },
+        _ => Err(usage(
+            "E4126", &format!("unknown admission policy '{}'", value))),
+    }
+}
+
+fn sample_rate(value: &str) -> UtttResult<f64> {
+    match number::<f64>("--sample", value)? {
+        rate if rate >= 0.0 && rate <= 1.0 => Ok(rate),
+        _ => Err(usage("E4137", "--sample must be from 0 to 1")),
+    }
+}
+
+fn side(value: &str) -> UtttResult<Player> {
+    match value {
+        "x" | "X" => Ok(Player::X),
+        "o" | "O" => Ok(Player::O),
+        _ => Err(usage("E4113", &format!("unknown side '{}'", value))),
+    }
+}
+
+fn format(value: &str) -> UtttResult<OutputFormat> {
+    match value {
+        "text" => Ok(OutputFormat::Text),
+        "json" => Ok(OutputFormat::JsonLines),
+        _ => Err(usage("E4112", &format!("unknown format '{}'", value))),
+    }
+}
+
+fn export_format(value: &str) -> UtttResult<ExportFormat> {
+    match value {
+        "binary" => Ok(ExportFormat::Binary),
+        "csv" => Ok(ExportFormat::Csv),
+        "json" => Ok(ExportFormat::JsonLines),
+        _ => Err(usage("E4131", &format!("unknown export format '{}'", value))),
+    }
+}
+
+fn data_format(value: &str) -> UtttResult<TrainingFormat> {
+    match value {
+        "tensor" => Ok(TrainingFormat::Tensor),
+        "csv" => Ok(TrainingFormat::Csv),
+        _ => Err(usage("E4117", &format!("unknown data format '{}'", value))),
+    }
+}
+
+fn usage(code: &'static str, message: &str) -> UtttError {
+    UtttError::usage(code, message)
+}
@@ -0,0 +1,125 @@
+use cli::*;
+use solver::db::Backoff;
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_parse_args() {
+    assert!(parse_args(&[]).map(|o| o.command) == Ok(Command::Help));
+    let mut expected = Options::new(Command::Backwards);
+    expected.trials = 3;
+    expected.depth = 10;
+    expected.verbose = true;
+    expected.seed = Some([1, 2, 3, 4]);
+    expected.stack = StackKind::Cpu;
+    expected.format = OutputFormat::JsonLines;
+    let opts = parse_args(&args("backwards --trials 3 --depth=10 -v \
+                                 --seed=[1,2,3,4] --stack cpu --format json"));
+    assert!(opts == Ok(expected));
+    let opts = parse_args(&args("db init --db postgres://a@b --tablespace t"))
+        .expect("E4111");
+    assert!(opts.command == Command::DbInit);
+    assert!(opts.db_params() == Ok("postgres://a@b".to_string()));
+    assert!(opts.tablespace == Some("t".to_string()));
+    let opts = parse_args(&args("interactive --side o --time 500"))
+        .expect("E4114");
+    assert!(opts.command == Command::Interactive);
+    assert!(opts.side == Player::O);
+    assert!(opts.time == Some(500));
+    let opts = parse_args(&args("tournament --engines \
+                                 random,solver:3,deepening:8:100"))
+        .expect("E4118");
+    assert!(opts.engines == vec![Engine::Random, Engine::Solver { depth: 3 },
+                                 Engine::Deepening { depth: 8,
+                                                     budget_ms: 100 }]);
+    let opts = parse_args(&args("training --output a.csv --data csv --augment"))
+        .expect("E4119");
+    assert!(opts.output == Some("a.csv".to_string()));
+    assert!(opts.data == TrainingFormat::Csv && opts.augment);
+    let opts = parse_args(&args("db export --output a.csv --export csv"))
+        .expect("E4132");
+    assert!(opts.command == Command::DbExport);
+    assert!(opts.export == ExportFormat::Csv);
+    let opts = parse_args(&args("db import --input a.bin")).expect("E4133");
+    assert!(opts.command == Command::DbImport);
+    assert!(opts.input == Some("a.bin".to_string()));
+    let opts = parse_args(&args("db verify --sample 0.5 --sample-seed 7 \
+                                 --repair"))
+        .expect("E4138");
+    assert!(opts.command == Command::DbVerify);
+    assert!(opts.verify == VerifyConfig {
+        sample_rate: 0.5,
+        seed: 7,
+        repair: true,
+    });
+    assert!(parse_args(&args("db verify")).map(|o| o.verify) ==
+            Ok(VerifyConfig::new()));
+}
+
+#[test]
+fn test_parse_options() {
+    let opts = parse_options(Command::Serve,
+                             &args("--addr 0.0.0.0:9000 --stack ram"))
+        .expect("E4122");
+    assert!(opts.command == Command::Serve);
+    assert!(opts.addr == "0.0.0.0:9000" && opts.stack == StackKind::Ram);
+    assert!(parse_options(Command::Serve, &[]).map(|o| o.addr) ==
+            Ok(DEFAULT_ADDR.to_string()));
+    let opts = parse_args(&args("engine --time 250")).expect("E4123");
+    assert!(opts.command == Command::Engine && opts.time == Some(250));
+    let opts = parse_args(&args("ongoing --cache-1 10 --cache-2 100 --queue 5 \
+                                 --batch 2 --admission sample:0.25"))
+        .expect("E4127");
+    assert!(opts.ssd == SsdConfig {
+        cache_1_cap: 10,
+        cache_2_cap: 100,
+        write_queue_cap: 5,
+        write_batch_size: 2,
+        admission: AdmissionPolicy::Sampling { rate: 0.25 },
+        backoff: Backoff::new(),
+    });
+    let opts = parse_args(&args("ongoing --admission balanced:1:2"))
+        .expect("E4128");
+    assert!(opts.ssd.admission == AdmissionPolicy::DepthBalanced {
+        low_mark: 1,
+        multiplier: 2,
+    });
+}
+
+#[test]
+fn test_parse_args_errors() {
+    let cases = [
+        ("db drop", "E4102"),
+        ("fly", "E4103"),
+        ("solve --depth", "E4104"),
+        ("solve --speed 3", "E4105"),
+        ("solve --stack mmap", "E4106"),
+        ("solve --trials many", "E4107"),
+        ("solve --seed 1,2,3", "E4108"),
+        ("solve --stack gpu", "E4109"),
+        ("solve --format xml", "E4112"),
+        ("solve --depth 82", "E4134"),
+        ("solve --back 90", "E4135"),
+        ("solve --cache-1 0", "E4124"),
+        ("db export", "E4129"),
+        ("db import", "E4130"),
+        ("db freeze", "E4136"),
+        ("db verify --sample 1.5", "E4137"),
+        ("db export --output a --export xml", "E4131"),
+        ("solve --admission sample:2", "E4125"),
+        ("solve --admission never", "E4126"),
+        ("interactive --side z", "E4113"),
+        ("tournament --engines random", "E4115"),
+        ("tournament --engines random,solver", "E4701"),
+        ("training", "E4116"),
+        ("training --output a --data npy", "E4117"),
+    ];
+    for &(line, code) in cases.iter() {
+        match parse_args(&args(line)) {
+            Err(UtttError::Usage { code: c, .. }) if c == code => {},
+            other => panic!("unexpected {:?} for '{}'", other, line),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+/// Errors.
+///
+/// Failures that a caller can act on (retry, reconnect, or flush and exit) are
+/// returned as a `UtttError` instead of a panic. Each error keeps its "E...."
+/// code, so that it can still be found in the source.
+
+use postgres;
+use std::error;
+use std::fmt;
+use std::io;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UtttError {
+    /// Could not connect to the database, or the connection was lost.
+    Connection { code: &'static str, message: String },
+    /// The database rejected a command.
+    Query { code: &'static str, message: String },
+    /// A stored value could not be decoded.
+    Decode { code: &'static str, message: String },
+    /// Something that should always hold did not; a bug or corrupt data.
+    Invariant { code: &'static str, message: String },
+    /// A device was asked for an operation it does not support.
+    Capability { code: &'static str, operation: &'static str },
+    /// Reading or writing a file failed.
+    Io { code: &'static str, message: String },
+    /// The program was invoked with invalid arguments.
+    Usage { code: &'static str, message: String },
+    /// The work was stopped on request (for example, by Ctrl-C).
+    Interrupted { code: &'static str },
+}
+
+pub type UtttResult<T> = Result<T, UtttError>;
+
+impl UtttError {
+    /// Returns an error for a failed PostgreSQL command. I/O failures, and
+    /// server errors in the "connection exception" (08) and "operator
+    /// intervention" (57P) classes, are classified as connection errors, since
+    /// they mean that the connection is unusable. Conversion failures are
+    /// classified as decode errors.
+    pub fn from_postgres(code: &'static str, err: postgres::error::Error)
+                         -> UtttError {
+        let message = err.to_string();
+        match err {
+            postgres::error::Error::Io(_) =>
+                UtttError::Connection { code: code, message: message },
+            postgres::error::Error::Conversion(_) =>
+                UtttError::Decode { code: code, message: message },
+            postgres::error::Error::Db(ref db_err)
+                if is_connection_state(db_err.code.code()) =>
+                UtttError::Connection { code: code, message: message },
+            postgres::error::Error::Db(_) =>
+                UtttError::Query { code: code, message: message },
+        }
+    }
+
+    /// Returns an error for a failed attempt to connect.
+    pub fn from_connect(code: &'static str,
+                        err: postgres::error::ConnectError) -> UtttError {
+        UtttError::Connection { code: code, message: err.to_string() }
+    }
+
+    /// Returns an error for a failed file operation.
+    pub fn from_io(code: &'static str, err: io::Error) -> UtttError {
+        UtttError::Io { code: code, message: err.to_string() }
+    }
+
+    pub fn decode(code: &'static str, message: &str) -> UtttError {
+        UtttError::Decode { code: code, message: message.to_string() }
+    }
+
+    pub fn invariant(code: &'static str, message: &str) -> UtttError {
+        UtttError::Invariant { code: code, message: message.to_string() }
+    }
+
+    pub fn usage(code: &'static str, message: &str) -> UtttError {
+        UtttError::Usage { code: code, message: message.to_string() }
+    }
+
+    pub fn interrupted(code: &'static str) -> UtttError {
+        UtttError::Interrupted { code: code }
+    }
+
+    pub fn capability(code: &'static str, operation: &'static str)
+                      -> UtttError {
+        UtttError::Capability { code: code, operation: operation }
+    }
+
+    /// Returns the "E...." code.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            UtttError::Connection { code, .. } => code,
+            UtttError::Query { code, .. } => code,
+            UtttError::Decode { code, .. } => code,
+            UtttError::Invariant { code, .. } => code,
+            UtttError::Capability { code, .. } => code,
+            UtttError::Io { code, .. } => code,
+            UtttError::Usage { code, .. } => code,
+            UtttError::Interrupted { code } => code,
+        }
+    }
+
+    /// Returns true if the error means the database connection is unusable,
+    /// in which case reconnecting (and retrying) may help.
+    pub fn is_connection(&self) -> bool {
+        match *self {
+            UtttError::Connection { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the work was stopped on request, rather than failing.
+    pub fn is_interrupted(&self) -> bool {
+        match *self {
+            UtttError::Interrupted { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for UtttError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UtttError::Connection { code, ref message } =>
+                write!(f, "{}: connection error: {}", code, message),
+            UtttError::Query { code, ref message } =>
+                write!(f, "{}: query error: {}", code, message),
+            UtttError::Decode { code, ref message } =>
+                write!(f, "{}: decode error: {}", code, message),
+            UtttError::Invariant { code, ref message } =>
+                write!(f, "{}: invariant violated: {}", code, message),
+            UtttError::Capability { code, operation } =>
+                write!(f, "{}: device does not support {}", code, operation),
+            UtttError::Io { code, ref message } =>
+                write!(f, "{}: I/O error: {}", code, message),
+            UtttError::Usage { code, ref message } =>
+                write!(f, "{}: usage error: {}", code, message),
+            UtttError::Interrupted { code } =>
+                write!(f, "{}: interrupted", code),
+        }
+    }
+}
+
+impl error::Error for UtttError {
+    fn description(&self) -> &str {
+        match *self {
+            UtttError::Connection { .. } => "connection error",
+            UtttError::Query { .. } => "query error",
+            UtttError::Decode { .. } => "decode error",
+            UtttError::Invariant { .. } => "invariant violated",
+            UtttError::Capability { .. } => "unsupported device operation",
+            UtttError::Io { .. } => "I/O error",
+            UtttError::Usage { .. } => "usage error",
+            UtttError::Interrupted { .. } => "interrupted",
+        }
+    }
+}
+
+/// Returns true for SQLSTATE codes that mean the connection is unusable.
+fn is_connection_state(sqlstate: &str) -> bool {
+    sqlstate.starts_with("08") || sqlstate.starts_with("57P")
+}
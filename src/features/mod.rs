@@ -0,0 +1,106 @@
+/// Features.
+///
+/// Encodes a `Game` as fixed-shape planes, the input of a neural network, and
+/// maps between an 81-way policy index and a `Play` (its output).
+///
+/// Every plane has 81 values, one per location, at index `row * 9 + column`:
+/// the same scheme as the solution encoding, so `policy_index` and
+/// `loc_from_index` are re-exported from 'solver/codec'. "Own" is the side to
+/// move (if the game is over, the side that would have moved next), so that a
+/// network sees every position from the mover's point of view.
+///
+/// plane       value at a location
+/// -----       -------------------
+/// OWN         1 if the side to move has played there
+/// OPPONENT    1 if the other side has played there
+/// LEGAL       1 if the side to move may play there
+/// OWN_WON     1 if its sub-board is won by the side to move
+/// OPP_WON     1 if its sub-board is won by the other side
+/// TIED        1 if its sub-board is filled without a winner
+/// X_TO_MOVE   1 everywhere if the side to move is X
+///
+/// All other values are 0.
+
+use data::*;
+pub use solver::codec::{loc_from_index, policy_index};
+
+#[cfg(test)]
+mod tests;
+
+pub const OWN: usize = 0;
+pub const OPPONENT: usize = 1;
+pub const LEGAL: usize = 2;
+pub const OWN_WON: usize = 3;
+pub const OPP_WON: usize = 4;
+pub const TIED: usize = 5;
+pub const X_TO_MOVE: usize = 6;
+
+/// The number of planes.
+pub const PLANES: usize = 7;
+
+/// The number of policy indexes (and of values in a plane).
+pub const POLICY_SIZE: usize = 81;
+
+pub type Plane = [f32; POLICY_SIZE];
+
+/// Returns the planes of a game; see the module documentation.
+pub fn planes(game: &Game) -> [Plane; PLANES] {
+    let mut planes = [[0.0; POLICY_SIZE]; PLANES];
+    let own = side_to_move(game);
+    for i in 0 .. POLICY_SIZE {
+        let loc = loc_from_index(i).expect("E4901");
+        match game.board.player_at_loc(loc) {
+            Some(player) if player == own => planes[OWN][i] = 1.0,
+            Some(_) => planes[OPPONENT][i] = 1.0,
+            None => {},
+        }
+        let sboard = game.board.sboard_at_idx(BI::from_loc(loc));
+        match sboard.winner() {
+            Some(player) if player == own => planes[OWN_WON][i] = 1.0,
+            Some(_) => planes[OPP_WON][i] = 1.0,
+            None if sboard.is_filled() => planes[TIED][i] = 1.0,
+            None => {},
+        }
+        if own == Player::X { planes[X_TO_MOVE][i] = 1.0; }
+    }
+    for (i, &legal) in legal_mask(game).iter().enumerate() {
+        if legal { planes[LEGAL][i] = 1.0; }
+    }
+    planes
+}
+
+/// Returns the side to move, or (if the game is over) the side that would
+/// have moved next.
+pub fn side_to_move(game: &Game) -> Player {
+    game.next_player().unwrap_or_else(|| match game.last_player() {
+        Some(player) => player.opponent(),
+        None => Player::X,
+    })
+}
+
+/// Returns the valid plays of a game, as a mask over policy indexes.
+pub fn legal_mask(game: &Game) -> [bool; POLICY_SIZE] {
+    let mut mask = [false; POLICY_SIZE];
+    for play in game.valid_plays().iter() {
+        mask[policy_index(play.loc)] = true;
+    }
+    mask
+}
+
+// == policy indexes ===========================================================
+
+/// Returns the play of the side to move at a policy index, or None if the
+/// game is over or the index is out of range. The play may still be invalid;
+/// see `legal_mask`.
+pub fn play_from_index(game: &Game, i: usize) -> Option<Play> {
+    match (game.next_player(), loc_from_index(i)) {
+        (Some(player), Some(loc)) => Some(Play { loc: loc, player: player }),
+        _ => None,
+    }
+}
+
+/// Returns the index of the sub-board (`sub-board row * 3 + sub-board
+/// column`) of a policy index.
+pub fn sboard_index(i: usize) -> usize {
+    (i / 27) * 3 + (i % 9) / 3
+}
@@ -0,0 +1,76 @@
+use constants::*;
+use features::*;
+use solver::*;
+use solver::codec::*;
+
+fn play_all(game: &mut Game, locs: &[(u8, u8)]) {
+    for &(row, col) in locs.iter() {
+        let player = game.next_player().expect("E4911");
+        let loc = Loc::from_row_col(RI::from_u8(row), CI::from_u8(col));
+        assert!(game.play(Play { loc: loc, player: player }));
+    }
+}
+
+fn count(plane: &Plane) -> usize {
+    plane.iter().filter(|&&v| v == 1.0).count()
+}
+
+#[test]
+fn test_policy_index_round_trip() {
+    for i in 0 .. POLICY_SIZE {
+        let loc = loc_from_index(i).expect("E4912");
+        assert_eq!(policy_index(loc), i);
+        assert_eq!(loc.row().as_u8() as usize, i / 9);
+        assert_eq!(loc.col().as_u8() as usize, i % 9);
+    }
+    assert_eq!(loc_from_index(POLICY_SIZE), None);
+    assert_eq!(play_from_index(&EMPTY_GAME, 81), None);
+    let play = play_from_index(&EMPTY_GAME, 40).expect("E4913");
+    assert!(play.player == Player::X && EMPTY_GAME.is_valid_play(play));
+}
+
+#[test]
+fn test_policy_index_matches_the_codec() {
+    // A stored solution's location decodes to the play at its policy index.
+    for i in 0 .. POLICY_SIZE {
+        let play = play_from_index(&EMPTY_GAME, i).expect("E4914");
+        let solution = Solution {
+            opt_play: Some(play),
+            outcome: Outcome::Unknown { turns: 1 },
+        };
+        let x = sol_i16(solution) as u16;
+        assert_eq!((x >> 7 & 0x7F) as usize, i);
+        assert_eq!(solution_from(x as i16, Some(Player::X)), Ok(solution));
+    }
+}
+
+#[test]
+fn test_planes() {
+    let empty = planes(&EMPTY_GAME);
+    assert_eq!(count(&empty[LEGAL]), 81);
+    assert_eq!(count(&empty[X_TO_MOVE]), 81);
+    assert_eq!(count(&empty[OWN]) + count(&empty[OPPONENT]), 0);
+
+    // X wins the top-left sub-board on its diagonal; O's plays in the other
+    // sub-boards' top-left corners send X back to it.
+    let mut game = EMPTY_GAME;
+    play_all(&mut game, &[(1, 1), (3, 3), (2, 2), (6, 6), (0, 0)]);
+    let planes = planes(&game);
+    assert_eq!(side_to_move(&game), Player::O);
+    assert_eq!(count(&planes[OWN]), 2);
+    assert_eq!(count(&planes[OPPONENT]), 3);
+    assert_eq!(count(&planes[OWN_WON]), 0);
+    assert_eq!(count(&planes[OPP_WON]), 9);
+    assert!((0 .. 9).all(|i| planes[OPP_WON][sboard_cell(0, i)] == 1.0));
+    assert_eq!(count(&planes[TIED]), 0);
+    assert_eq!(count(&planes[X_TO_MOVE]), 0);
+    // The won sub-board is closed, so O may play in any other empty slot.
+    assert_eq!(count(&planes[LEGAL]), 81 - 9 - 2);
+    assert!(legal_mask(&game).iter().enumerate()
+            .all(|(i, &legal)| legal == (planes[LEGAL][i] == 1.0)));
+}
+
+/// Returns the policy index of the i-th slot of a sub-board.
+fn sboard_cell(sboard: usize, i: usize) -> usize {
+    (sboard / 3 * 3 + i / 3) * 9 + sboard % 3 * 3 + i % 3
+}
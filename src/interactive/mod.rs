@@ -0,0 +1,333 @@
+/// Interactive play: a human versus the solver, in the terminal.
+///
+/// The board is shown with `show_playable`, so the empty slots of the legal
+/// sub-boards are marked. A play is a row and a column, as in `R4C6`, `4 6`
+/// or `46`. The other commands are:
+///
+/// command      effect
+/// ----------   ------
+/// undo         take back plays until it is your turn again
+/// hint         show the solver's solutions for your position
+/// side x|o     play the other side (the solver plays if it is its turn)
+/// save <path>  save the game record (see `Session::record`)
+/// new          start a new game
+/// board        show the board again
+/// help         list the commands
+/// quit         stop (so does the end of the input)
+///
+/// The solver replies with the best play it finds at the session's depth. If
+/// the session has a time budget, it deepens one play at a time (up to the
+/// depth) until the budget is used up or the outcome is decided. A timer (see
+/// `signals::start_timer`) stops the solve when the budget is used up, even
+/// within a depth, and the reply comes from the deepest finished depth. The
+/// timer also stops it on Ctrl-C, which ends the session.
+
+use constants::*;
+use data::*;
+use error::*;
+use show::*;
+use signals::*;
+use solver::*;
+use std::fs::File;
+use std::io::{BufRead, Write};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests;
+
+pub const HELP: &'static str = "\
+Plays:    R4C6, 4 6 or 46 (row, then column)
+Commands: undo, hint, side x|o, save <path>, new, board, help, quit";
+
+/// The flag that stops a reply; the stack of the 'interactive' command watches
+/// it (see `Stack::stop`).
+pub static REPLY_STOP: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Input {
+    Play(Loc),
+    Undo,
+    Hint,
+    Side(Player),
+    Save(String),
+    New,
+    Board,
+    Help,
+    Quit,
+}
+
+pub struct Session {
+    pub game: Game,
+    /// The games before each play, for `undo`.
+    history: Vec<Game>,
+    /// The human's side.
+    pub human: Player,
+    pub depth: Count,
+    pub budget: Option<Duration>,
+}
+
+/// The solver's choice of play.
+pub struct Reply {
+    pub play: Play,
+    /// The depth of the last (deepest) solve.
+    pub depth: Count,
+    pub solutions: Vec<Solution>,
+}
+
+impl Session {
+    pub fn new(human: Player, depth: Count, budget: Option<Duration>)
+               -> Session {
+        Session {
+            game: EMPTY_GAME,
+            history: vec![],
+            human: human,
+            depth: depth,
+            budget: budget,
+        }
+    }
+
+    /// Is it the solver's turn?
+    pub fn is_solver_turn(&self) -> bool {
+        match self.game.next_player() {
+            Some(player) => player != self.human,
+            None => false,
+        }
+    }
+
+    /// Plays for the human, if the play is valid.
+    pub fn play_human(&mut self, loc: Loc) -> UtttResult<()> {
+        let play = Play { loc: loc, player: self.human };
+        if self.is_solver_turn() || !self.game.is_valid_play(play) {
+            return Err(UtttError::usage(
+                "E4601", &format!("{} is not a valid play", loc_name(loc))));
+        }
+        self.play(play);
+        Ok(())
+    }
+
+    /// Plays the solver's best play.
+    pub fn play_solver(&mut self, stack: &Stack) -> UtttResult<Reply> {
+        let reply = self.reply(stack)?;
+        self.play(reply.play);
+        Ok(reply)
+    }
+
+    /// Returns the best play for the game, under a timer on the stack's flag
+    /// (if it has one). The timer clears the flag when it is done, so it must
+    /// be the session's own, such as `REPLY_STOP`.
+    pub fn reply(&self, stack: &Stack) -> UtttResult<Reply> {
+        let timer = stack.stop.map(|stop| start_timer(stop, self.budget));
+        let reply = best_play(stack, &self.game, self.depth, self.budget);
+        if let Some(timer) = timer { timer.finish(); }
+        reply
+    }
+
+    /// Takes back plays until it is the human's turn again (at least one).
+    /// Returns false if there is nothing to take back.
+    pub fn undo(&mut self) -> bool {
+        if self.history.is_empty() { return false; }
+        while let Some(game) = self.history.pop() {
+            self.game = game;
+            if game.next_player() == Some(self.human) { break; }
+        }
+        true
+    }
+
+    pub fn new_game(&mut self) {
+        self.game = EMPTY_GAME;
+        self.history.clear();
+    }
+
+    /// Returns the game record: each play, as a row digit and a column digit
+    /// (as in run manifests, so `manifest::games_from_plays` can read it).
+    pub fn record(&self) -> String {
+        self.history.iter().chain(Some(&self.game))
+            .filter_map(|game| game.last_loc)
+            .map(loc_digits)
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Writes the game record to a file. A file that cannot be written is a
+    /// usage error, so that the session goes on.
+    pub fn save(&self, path: &str) -> UtttResult<()> {
+        File::create(path)
+            .and_then(|mut file| writeln!(file, "{}", self.record()))
+            .map_err(|e| UtttError::usage(
+                "E4602", &format!("cannot write '{}': {}", path, e)))
+    }
+
+    fn play(&mut self, play: Play) {
+        self.history.push(self.game);
+        self.game.play(play);
+    }
+}
+
+// == solver ===================================================================
+
+/// Returns the best play for a game. Without a budget, solves at `depth`;
+/// with one, deepens from depth 1 (see the module documentation). If the
+/// stack is stopped after a depth has finished, answers from that depth.
+pub fn best_play(stack: &Stack, game: &Game, depth: Count,
+                 budget: Option<Duration>) -> UtttResult<Reply> {
+    let start = Instant::now();
+    let first = if budget.is_some() { 1 } else { depth };
+    let mut reply: Option<(Count, Vec<Solution>)> = None;
+    for d in first .. (depth + 1) {
+        let solutions = match stack.get_and_put(game, d) {
+            Ok(solutions) => solutions,
+            Err(ref e) if e.is_interrupted() && reply.is_some() => break,
+            Err(e) => return Err(e),
+        };
+        let decided = solutions.iter().all(|sol| match sol.outcome {
+            Outcome::Unknown { .. } => false,
+            _ => true,
+        });
+        reply = Some((d, solutions));
+        if decided || budget.map_or(true, |b| start.elapsed() >= b) { break; }
+    }
+    let (depth, solutions) = reply.unwrap_or((depth, vec![]));
+    // A dominant solution found at a lower depth may have no play.
+    let play = solutions.iter()
+        .filter_map(|sol| sol.opt_play)
+        .find(|&play| game.is_valid_play(play))
+        .or_else(|| game.valid_plays().first().cloned())
+        .ok_or_else(|| UtttError::invariant("E4603", "no play to make"))?;
+    Ok(Reply { play: play, depth: depth, solutions: solutions })
+}
+
+// == input ====================================================================
+
+pub fn parse_input(line: &str) -> UtttResult<Input> {
+    let words = line.split_whitespace().collect::<Vec<&str>>();
+    match words.as_slice() {
+        ["undo"] => Ok(Input::Undo),
+        ["hint"] => Ok(Input::Hint),
+        ["side", "x"] | ["side", "X"] => Ok(Input::Side(Player::X)),
+        ["side", "o"] | ["side", "O"] => Ok(Input::Side(Player::O)),
+        ["save", path] => Ok(Input::Save(path.to_string())),
+        ["new"] => Ok(Input::New),
+        ["board"] | [] => Ok(Input::Board),
+        ["help"] | ["?"] => Ok(Input::Help),
+        ["quit"] | ["exit"] => Ok(Input::Quit),
+        _ => parse_loc(line).map(Input::Play).ok_or_else(|| {
+            UtttError::usage("E4604", &format!(
+                "'{}' is not a play or a command (try 'help')", line.trim()))
+        }),
+    }
+}
+
+/// Returns a location as two digits, row then column, as in run manifests;
+/// the inverse of `parse_loc`.
+pub fn loc_digits(loc: Loc) -> String {
+    format!("{}{}", loc.row().as_u8(), loc.col().as_u8())
+}
+
+/// Parses a location as `R4C6` (in any case), or as two digits, optionally
+/// separated by a space or a comma.
+pub fn parse_loc(s: &str) -> Option<Loc> {
+    let s = s.trim().to_uppercase();
+    let digits = if s.starts_with('R') {
+        match s[1 ..].find('C') {
+            Some(i) => format!("{}{}", &s[1 .. i + 1], &s[i + 2 ..]),
+            None => return None,
+        }
+    } else {
+        s.replace(|c: char| c == ' ' || c == ',', "")
+    };
+    match digits.as_bytes() {
+        &[row, col] if b'0' <= row && row <= b'8' &&
+            b'0' <= col && col <= b'8' =>
+            Some(Loc::from_row_col(RI::from_u8(row - b'0'),
+                                   CI::from_u8(col - b'0'))),
+        _ => None,
+    }
+}
+
+/// Returns a location as `R4C6`.
+pub fn loc_name(loc: Loc) -> String {
+    format!("R{}C{}", loc.row().as_u8(), loc.col().as_u8())
+}
+
+// == loop =====================================================================
+
+/// Runs a session until the human quits or the input ends. Invalid input is
+/// reported, and the session goes on; other errors end it. Returns the game.
+pub fn play_session<R: BufRead, W: Write>(stack: &Stack, session: &mut Session,
+                                          input: R, out: &mut W)
+                                          -> UtttResult<Game> {
+    let mut lines = input.lines();
+    say(out, &format!("You play {}. {}\n", session.human.show(), HELP))?;
+    loop {
+        if stop_requested() { break; }
+        if session.is_solver_turn() {
+            let reply = session.play_solver(stack)?;
+            if stop_requested() { break; }
+            say(out, &format!("Solver plays {} (depth {})\n",
+                              loc_name(reply.play.loc), reply.depth))?;
+        }
+        say(out, &show_playable(&session.game))?;
+        if session.game.is_over() {
+            say(out, &format!("Game over: {}\n", result(&session.game)))?;
+        }
+        write!(out, "{}> ", session.human.show())
+            .and_then(|_| out.flush())
+            .map_err(|e| UtttError::from_io("E4605", e))?;
+        let line = match lines.next() {
+            Some(line) => line.map_err(|e| UtttError::from_io("E4606", e))?,
+            None => break,
+        };
+        let done = match parse_input(&line)
+            .and_then(|input| command(stack, session, input, out)) {
+            Ok(done) => done,
+            Err(e @ UtttError::Usage { .. }) => {
+                say(out, &format!("{}\n", e))?;
+                false
+            },
+            Err(e) => return Err(e),
+        };
+        if done { break; }
+    }
+    say(out, "")?;
+    Ok(session.game)
+}
+
+/// Carries out one input. Returns true to quit.
+fn command<W: Write>(stack: &Stack, session: &mut Session, input: Input,
+                     out: &mut W) -> UtttResult<bool> {
+    match input {
+        Input::Play(loc) => session.play_human(loc)?,
+        Input::Undo => if !session.undo() {
+            say(out, "Nothing to undo.\n")?;
+        },
+        Input::Hint => {
+            if session.game.is_over() {
+                return Err(UtttError::usage("E4607", "the game is over"));
+            }
+            let reply = session.reply(stack)?;
+            say(out, &show_solutions("hint", reply.depth, &reply.solutions))?;
+        },
+        Input::Side(player) => session.human = player,
+        Input::Save(path) => {
+            session.save(&path)?;
+            say(out, &format!("Saved to {}.\n", path))?;
+        },
+        Input::New => session.new_game(),
+        Input::Board => {},
+        Input::Help => say(out, &format!("{}\n", HELP))?,
+        Input::Quit => return Ok(true),
+    }
+    Ok(false)
+}
+
+fn result(game: &Game) -> String {
+    match game.winner() {
+        Some(player) => format!("{} wins", player.show()),
+        None => "tie".to_string(),
+    }
+}
+
+fn say<W: Write>(out: &mut W, text: &str) -> UtttResult<()> {
+    writeln!(out, "{}", text).map_err(|e| UtttError::from_io("E4605", e))
+}
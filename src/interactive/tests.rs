@@ -0,0 +1,111 @@
+use interactive::*;
+use manifest::games_from_plays;
+use rand::{SeedableRng, XorShiftRng};
+use random::random_games;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+fn loc(row: u8, col: u8) -> Loc {
+    Loc::from_row_col(RI::from_u8(row), CI::from_u8(col))
+}
+
+#[test]
+fn test_parse_loc() {
+    for s in ["R4C6", "r4c6", "4 6", "46", " 4,6 "].iter() {
+        assert_eq!(parse_loc(s), Some(loc(4, 6)), "{}", s);
+    }
+    for s in ["R9C6", "4", "4 6 1", "RC", "R4", "x6", ""].iter() {
+        assert_eq!(parse_loc(s), None, "{}", s);
+    }
+}
+
+#[test]
+fn test_parse_input() {
+    assert_eq!(parse_input("side o"), Ok(Input::Side(Player::O)));
+    assert_eq!(parse_input("save a.txt"), Ok(Input::Save("a.txt".into())));
+    assert_eq!(parse_input("R0C8"), Ok(Input::Play(loc(0, 8))));
+    match parse_input("castle") {
+        Err(UtttError::Usage { code: "E4604", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_session_plays_and_undoes() {
+    let stack = CPU_Stack::new();
+    let mut session = Session::new(Player::X, 2, None);
+    session.play_human(loc(4, 4)).expect("E4611");
+    // X played in the middle of the center sub-board; O must play there too.
+    match session.play_human(loc(0, 0)) {
+        Err(UtttError::Usage { code: "E4601", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(session.is_solver_turn());
+    let reply = session.play_solver(&stack).expect("E4612");
+    assert!(reply.play.player == Player::O);
+    assert!(BI::from_loc(reply.play.loc) == BI::from_loc(loc(4, 4)));
+    let record = session.record();
+    let games = games_from_plays(&record.split(' ').collect::<Vec<&str>>())
+        .expect("E4613");
+    assert!(games.back() == Some(&session.game));
+    assert!(session.undo());
+    assert!(session.game == EMPTY_GAME);
+    assert!(!session.undo());
+}
+
+#[test]
+fn test_best_play_with_budget() {
+    let stack = CPU_Stack::new();
+    let budget = Some(Duration::from_millis(0));
+    let reply = best_play(&stack, &EMPTY_GAME, 5, budget).expect("E4614");
+    // A zero budget stops after the first depth.
+    assert!(reply.depth == 1);
+    assert!(EMPTY_GAME.is_valid_play(reply.play));
+}
+
+#[test]
+fn test_reply_deadline() {
+    // Too deep to finish in time: the timer stops the solve within a depth.
+    let mut stack = CPU_Stack::new();
+    stack.stop = Some(&STOP);
+    let session = Session::new(Player::O, 81,
+                               Some(Duration::from_millis(100)));
+    let start = Instant::now();
+    let reply = session.reply(&stack).expect("E4620");
+    assert!(start.elapsed().as_secs() < 5);
+    assert!(EMPTY_GAME.is_valid_play(reply.play));
+    assert!(!stack.is_stopping());
+}
+
+#[test]
+fn test_play_session() {
+    let stack = CPU_Stack::new();
+    let mut session = Session::new(Player::O, 1, None);
+    let mut out: Vec<u8> = Vec::new();
+    let input = "hint\nbogus\nundo\nquit\n".as_bytes();
+    let game = play_session(&stack, &mut session, input, &mut out)
+        .expect("E4615");
+    let text = String::from_utf8(out).expect("E4616");
+    assert!(text.contains("Solver plays"));
+    assert!(text.contains("- hint depth=1"));
+    assert!(text.contains("E4604"));
+    // The undo took back the solver's first play; it then played again.
+    assert!(game.board.play_count() == 1);
+}
+
+#[test]
+fn test_play_session_reports_usage_errors() {
+    let stack = CPU_Stack::new();
+    let mut session = Session::new(Player::X, 1, None);
+    let mut rng: XorShiftRng = SeedableRng::from_seed([1, 2, 3, 4]);
+    session.game = *random_games(&mut rng).back().expect("E4619");
+    let mut out: Vec<u8> = Vec::new();
+    let input = "hint\nsave /nonexistent/game.txt\nquit\n".as_bytes();
+    play_session(&stack, &mut session, input, &mut out).expect("E4617");
+    let text = String::from_utf8(out).expect("E4618");
+    assert!(text.contains("Game over"));
+    assert!(text.contains("E4607"));
+    assert!(text.contains("E4602"));
+}
@@ -2,16 +2,29 @@
 #![cfg_attr(test, feature(test))]
 
 pub mod accessors;
+pub mod cli;
 pub mod constants;
 pub mod constructors;
 pub mod data;
+pub mod error;
+pub mod features;
+pub mod interactive;
 pub mod logic;
+pub mod manifest;
+pub mod output;
+pub mod protocol;
 pub mod random;
 pub mod runners;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod show;
+pub mod signals;
 pub mod solver;
+pub mod tournament;
+pub mod training;
 pub mod utility;
 
+extern crate libc;
 extern crate lru_cache;
 extern crate postgres;
 extern crate rand;
@@ -0,0 +1,193 @@
+/// Run manifests.
+///
+/// A run manifest records what is needed to repeat a run: the seed, the
+/// command and its options, and the plays of each trial's random game. Each
+/// line is written (and flushed) as soon as it is known, so a manifest
+/// survives a panic hours into a run; `replay` then re-executes one trial
+/// without replaying the ones before it.
+///
+/// The format is plain text, one record per line:
+///
+/// ```text
+/// uttt-manifest 1
+/// seed 1950144991,3815769152,584337888,1474954538
+/// command backwards
+/// depth 16
+/// back 18
+/// trial 1 44 35 ...
+/// ```
+///
+/// A trial line has the trial number, then each play of the game as a row
+/// digit and a column digit. X plays first, and players alternate.
+
+use constants::*;
+use data::*;
+use error::*;
+use std::collections::LinkedList;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+#[cfg(test)]
+mod tests;
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// What a manifest records about the run itself (before its trials).
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunInfo {
+    pub seed: [u32; 4],
+    /// The command's name; see `Command::name`.
+    pub command: String,
+    pub depth: Count,
+    pub back: Count,
+}
+
+/// A manifest being written. `Manifest::none()` records nothing, for runs
+/// without one.
+pub struct Manifest {
+    file: Option<File>,
+}
+
+impl Manifest {
+    /// Creates (or truncates) a manifest file and writes the run's details.
+    pub fn create<P: AsRef<Path>>(path: P, info: &RunInfo)
+                                  -> UtttResult<Manifest> {
+        let file = File::create(path)
+            .map_err(|e| UtttError::from_io("E4401", e))?;
+        let mut manifest = Manifest { file: Some(file) };
+        let seed = info.seed.iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>();
+        manifest.line(&format!("uttt-manifest {}", MANIFEST_VERSION))?;
+        manifest.line(&format!("seed {}", seed.join(",")))?;
+        manifest.line(&format!("command {}", info.command))?;
+        manifest.line(&format!("depth {}", info.depth))?;
+        manifest.line(&format!("back {}", info.back))?;
+        Ok(manifest)
+    }
+
+    pub fn none() -> Manifest {
+        Manifest { file: None }
+    }
+
+    /// Records the games of a trial (from the empty game to the last one).
+    pub fn record_trial(&mut self, trial: u32, games: &LinkedList<Game>)
+                        -> UtttResult<()> {
+        if self.file.is_none() { return Ok(()); }
+        let plays = games.iter()
+            .filter_map(|game| game.last_loc)
+            .map(|loc| format!("{}{}", loc.row().as_u8(), loc.col().as_u8()))
+            .collect::<Vec<String>>();
+        self.line(&format!("trial {} {}", trial, plays.join(" ")))
+    }
+
+    fn line(&mut self, line: &str) -> UtttResult<()> {
+        match self.file {
+            Some(ref mut file) => writeln!(file, "{}", line)
+                .and_then(|_| file.flush())
+                .map_err(|e| UtttError::from_io("E4402", e)),
+            None => Ok(()),
+        }
+    }
+}
+
+// == reading ==================================================================
+
+/// Reads a manifest. Returns the run's details, and the games of the given
+/// trial (see `Manifest::record_trial`).
+pub fn read_trial<P: AsRef<Path>>(path: P, trial: u32)
+                                  -> UtttResult<(RunInfo, LinkedList<Game>)> {
+    let file = File::open(path).map_err(|e| UtttError::from_io("E4403", e))?;
+    let mut seed: Option<[u32; 4]> = None;
+    let mut command: Option<String> = None;
+    let (mut depth, mut back): (Count, Count) = (0, 0);
+    let mut games: Option<LinkedList<Game>> = None;
+    let trial_str = trial.to_string();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| UtttError::from_io("E4403", e))?;
+        let mut words = line.split_whitespace();
+        let key = words.next().unwrap_or("");
+        let rest = words.collect::<Vec<&str>>();
+        if i == 0 {
+            if key != "uttt-manifest" ||
+                rest != [MANIFEST_VERSION.to_string().as_str()] {
+                return Err(corrupt("not a version 1 manifest"));
+            }
+            continue;
+        }
+        match (key, rest.as_slice()) {
+            ("seed", [value]) => seed = Some(parse_seed(value)?),
+            ("command", [value]) => command = Some(value.to_string()),
+            ("depth", [value]) => depth = parse_plays(value)?,
+            ("back", [value]) => back = parse_plays(value)?,
+            ("trial", _) if rest.first() == Some(&trial_str.as_str()) =>
+                games = Some(games_from_plays(&rest[1 ..])?),
+            ("trial", _) => {},
+            _ => return Err(corrupt(&format!("bad line {}", i + 1))),
+        }
+    }
+    match (seed, command, games) {
+        (Some(seed), Some(command), Some(games)) => Ok((RunInfo {
+            seed: seed,
+            command: command,
+            depth: depth,
+            back: back,
+        }, games)),
+        (Some(_), Some(_), None) => Err(UtttError::usage(
+            "E4404", &format!("the manifest has no trial {}", trial))),
+        _ => Err(corrupt("missing seed or command")),
+    }
+}
+
+/// Replays plays (each a row digit and a column digit) from the empty game.
+/// Returns every game along the way, starting with the empty game.
+pub fn games_from_plays(plays: &[&str]) -> UtttResult<LinkedList<Game>> {
+    let mut games: LinkedList<Game> = LinkedList::new();
+    let mut game = EMPTY_GAME;
+    games.push_back(game);
+    for play in plays.iter() {
+        let digits = play.bytes().map(|b| b.wrapping_sub(b'0'))
+            .collect::<Vec<u8>>();
+        let loc = match digits.as_slice() {
+            &[row, col] if row < 9 && col < 9 =>
+                Loc::from_row_col(RI::from_u8(row), CI::from_u8(col)),
+            _ => return Err(corrupt(&format!("bad play '{}'", play))),
+        };
+        let player = game.next_player()
+            .ok_or_else(|| corrupt("play after the end of the game"))?;
+        if !game.play(Play { loc: loc, player: player }) {
+            return Err(corrupt(&format!("invalid play '{}'", play)));
+        }
+        games.push_back(game);
+    }
+    Ok(games)
+}
+
+// == helpers ==================================================================
+
+fn parse<T: ::std::str::FromStr>(value: &str) -> UtttResult<T> {
+    value.parse::<T>()
+        .map_err(|_| corrupt(&format!("bad number '{}'", value)))
+}
+
+/// Parses a depth or a number of plays back, which is at most 81 (as for
+/// `parse_options`).
+fn parse_plays(value: &str) -> UtttResult<Count> {
+    match parse::<Count>(value)? {
+        n if n <= 81 => Ok(n),
+        n => Err(corrupt(&format!("{} is more than 81 plays", n))),
+    }
+}
+
+fn parse_seed(value: &str) -> UtttResult<[u32; 4]> {
+    let parts = value.split(',')
+        .map(parse::<u32>)
+        .collect::<UtttResult<Vec<u32>>>()?;
+    if parts.len() != 4 { return Err(corrupt("bad seed")); }
+    Ok([parts[0], parts[1], parts[2], parts[3]])
+}
+
+fn corrupt(message: &str) -> UtttError {
+    UtttError::decode("E4405", &format!("manifest: {}", message))
+}
@@ -0,0 +1,62 @@
+use manifest::*;
+use rand::{SeedableRng, XorShiftRng};
+use random::random_games;
+use std::env;
+use std::fs;
+
+#[test]
+fn test_manifest_round_trip() {
+    let path = env::temp_dir().join("uttt_test_manifest_round_trip.txt");
+    let info = RunInfo {
+        seed: [1, 2, 3, 4],
+        command: "backwards".to_string(),
+        depth: 16,
+        back: 18,
+    };
+    let mut rng: XorShiftRng = SeedableRng::from_seed(info.seed);
+    let trials = (0 .. 3).map(|_| random_games(&mut rng)).collect::<Vec<_>>();
+    {
+        let mut manifest = Manifest::create(&path, &info).expect("E4411");
+        for (i, games) in trials.iter().enumerate() {
+            manifest.record_trial(i as u32 + 1, games).expect("E4412");
+        }
+    }
+    for (i, games) in trials.iter().enumerate() {
+        let (read_info, read_games) = read_trial(&path, i as u32 + 1)
+            .expect("E4413");
+        assert!(read_info == info);
+        assert!(read_games == *games);
+    }
+    match read_trial(&path, 4) {
+        Err(UtttError::Usage { code: "E4404", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+    fs::remove_file(&path).expect("E4414");
+}
+
+#[test]
+fn test_games_from_plays_rejects_bad_plays() {
+    assert!(games_from_plays(&[]).map(|games| games.len()) == Ok(1));
+    for plays in [vec!["44", "44"], vec!["4"], vec!["49"], vec!["x4"]].iter() {
+        match games_from_plays(plays) {
+            Err(UtttError::Decode { code: "E4405", .. }) => {},
+            other => panic!("unexpected {:?} for {:?}", other, plays),
+        }
+    }
+}
+
+#[test]
+fn test_read_trial_rejects_bad_run_info() {
+    let path = env::temp_dir().join("uttt_test_read_trial_run_info.txt");
+    for &(key, value) in [("depth", "82"), ("back", "255"), ("back", "-1")]
+        .iter() {
+        let lines = format!("uttt-manifest 1\nseed 1,2,3,4\ncommand solve\n\
+                             {} {}\ntrial 1 44\n", key, value);
+        fs::write(&path, lines).expect("E4415");
+        match read_trial(&path, 1) {
+            Err(UtttError::Decode { code: "E4405", .. }) => {},
+            other => panic!("unexpected {:?} for {} {}", other, key, value),
+        }
+    }
+    fs::remove_file(&path).expect("E4416");
+}
@@ -0,0 +1,367 @@
+/// Output.
+///
+/// Runners report what they do to a `Sink`, instead of printing directly. A
+/// `TextSink` prints the human-readable format (headings, boards and
+/// solutions); a `JsonSink` writes one JSON object per line, for programs.
+///
+/// Each JSON line has a "type" field, and then:
+///
+/// type        fields
+/// ---------   ------
+/// heading     level, title
+/// game        trial, label, ply, game, last, state
+/// solutions   trial, label, ply, game, depth, elapsed_ms, solutions, devices
+/// metrics     devices
+/// value       name, value
+/// row         (one field per column)
+///
+/// A "game" field is in `game_notation`; a location ("last", "play") is a row
+/// digit and a column digit, as in run manifests.
+
+use data::*;
+use error::*;
+use solver::*;
+use std::io::{self, Write};
+use std::time::Duration;
+use utility::{h, pln};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    JsonLines,
+}
+
+/// The solutions found for a game.
+pub struct Solved<'a> {
+    pub trial: u32,
+    pub label: &'a str,
+    pub game: &'a Game,
+    pub depth: Count,
+    pub solutions: &'a Vec<Solution>,
+    pub elapsed: Duration,
+    /// The stack's metrics, after solving.
+    pub metrics: &'a [DeviceMetrics],
+}
+
+pub trait Sink {
+    /// A section heading; level 0 is the top.
+    fn heading(&mut self, level: u8, title: &str) -> UtttResult<()>;
+
+    /// A game, about to be solved (or just played).
+    fn game(&mut self, trial: u32, label: &str, game: &Game)
+            -> UtttResult<()>;
+
+    fn solutions(&mut self, solved: &Solved) -> UtttResult<()>;
+
+    fn metrics(&mut self, metrics: &[DeviceMetrics]) -> UtttResult<()>;
+
+    /// A named value, such as a seed or a total.
+    fn value(&mut self, name: &str, value: &str) -> UtttResult<()>;
+
+    /// A row of a table of numbers.
+    fn row(&mut self, fields: &[(&str, i64)]) -> UtttResult<()>;
+
+    /// The end of a group of values or rows; only affects the text format.
+    fn separator(&mut self) -> UtttResult<()>;
+}
+
+/// Returns a sink for the format, writing to standard output.
+pub fn stdout_sink(format: OutputFormat) -> Box<dyn Sink> {
+    match format {
+        OutputFormat::Text => Box::new(TextSink::new()),
+        OutputFormat::JsonLines => Box::new(JsonSink::new(io::stdout())),
+    }
+}
+
+// == text =====================================================================
+
+/// Prints the human-readable format to standard output.
+pub struct TextSink {
+    /// The column names of the table being printed, if any.
+    columns: Vec<String>,
+}
+
+impl TextSink {
+    pub fn new() -> TextSink {
+        TextSink { columns: vec![] }
+    }
+}
+
+impl Sink for TextSink {
+    fn heading(&mut self, level: u8, title: &str) -> UtttResult<()> {
+        self.columns.clear();
+        h(level, title);
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    fn game(&mut self, trial: u32, label: &str, game: &Game)
+            -> UtttResult<()> {
+        pln(game);
+        Ok(())
+    }
+
+    fn solutions(&mut self, solved: &Solved) -> UtttResult<()> {
+        p_solutions(solved.label, solved.depth, solved.solutions);
+        Ok(())
+    }
+
+    fn metrics(&mut self, metrics: &[DeviceMetrics]) -> UtttResult<()> {
+        for m in metrics.iter() {
+            let c = &m.counters;
+            println!("{} reads    : {} ({} hits, {} misses) in {:?}", m.name,
+                     c.reads, c.total_hits(), c.total_misses(), c.read_time);
+            println!("{} misses   : {}", m.name, by_depth(&c.misses));
+            println!("{} writes   : {} in {:?}", m.name, c.writes,
+                     c.write_time);
+            println!("{} evictions: {}", m.name, c.evictions);
+            println!("{} flushes  : {} ({} solutions) in {:?}", m.name,
+                     c.flushes, c.flushed, c.flush_time);
+            if let Some(len) = m.cache_1_len {
+                println!("{} cache_1  : {}", m.name, len);
+            }
+            if let Some(len) = m.cache_2_len {
+                println!("{} cache_2  : {}", m.name, len);
+            }
+            if let Some((accepted, rejected)) = m.admission {
+                println!("{} admitted : {} (rejected {})", m.name, accepted,
+                         rejected);
+            }
+            if let Some(reconnects) = m.reconnects {
+                println!("{} reconnects: {}", m.name, reconnects);
+            }
+            println!("");
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, name: &str, value: &str) -> UtttResult<()> {
+        println!("{}: {}", name, value);
+        Ok(())
+    }
+
+    /// Prints the column names before the first row of a table.
+    fn row(&mut self, fields: &[(&str, i64)]) -> UtttResult<()> {
+        let names = fields.iter()
+            .map(|&(name, _)| name.to_string())
+            .collect::<Vec<String>>();
+        if names != self.columns {
+            let header = names.iter()
+                .map(|name| format!("{:>1$}", name, column_width(name)))
+                .collect::<Vec<String>>();
+            println!("{}", header.join("  "));
+            self.columns = names;
+        }
+        let cells = fields.iter()
+            .map(|&(name, x)| format!("{:>1$}", x, column_width(name)))
+            .collect::<Vec<String>>();
+        println!("{}", cells.join("  "));
+        Ok(())
+    }
+
+    fn separator(&mut self) -> UtttResult<()> {
+        self.columns.clear();
+        println!("");
+        Ok(())
+    }
+}
+
+fn column_width(name: &str) -> usize {
+    if name.len() > 5 { 10 } else { 5 }
+}
+
+/// Formats the nonzero counts of a by-depth array as "depth:count" pairs.
+fn by_depth(counts: &[u64; MAX_DEPTH]) -> String {
+    let pairs = counts.iter().enumerate()
+        .filter(|&(_, &n)| n > 0)
+        .map(|(depth, n)| format!("{}:{}", depth, n))
+        .collect::<Vec<String>>();
+    if pairs.is_empty() { "-".to_string() } else { pairs.join(" ") }
+}
+
+// == JSON lines ===============================================================
+
+/// Writes one JSON object per line.
+pub struct JsonSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(writer: W) -> JsonSink<W> {
+        JsonSink { writer: writer }
+    }
+
+    /// Returns the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn line(&mut self, kind: &str, fields: &str) -> UtttResult<()> {
+        writeln!(self.writer, "{{\"type\":\"{}\",{}}}", kind, fields)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| UtttError::from_io("E4501", e))
+    }
+}
+
+impl<W: Write> Sink for JsonSink<W> {
+    fn heading(&mut self, level: u8, title: &str) -> UtttResult<()> {
+        self.line("heading", &format!("\"level\":{},\"title\":{}", level,
+                                      json_str(title)))
+    }
+
+    fn game(&mut self, trial: u32, label: &str, game: &Game)
+            -> UtttResult<()> {
+        let state = match game.state() {
+            GameState::Won(player) => format!("{:?} won", player),
+            GameState::Tied => "tie".to_string(),
+            GameState::Ongoing => "ongoing".to_string(),
+        };
+        self.line("game", &format!(
+            "\"trial\":{},\"label\":{},\"ply\":{},\"game\":\"{}\",\
+             \"last\":{},\"state\":\"{}\"",
+            trial, json_str(label), game.board.play_count(),
+            game_notation(game), json_loc(game.last_loc), state))
+    }
+
+    fn solutions(&mut self, solved: &Solved) -> UtttResult<()> {
+        self.line("solutions", &format!(
+            "\"trial\":{},\"label\":{},\"ply\":{},\"game\":\"{}\",\
+             \"depth\":{},\"elapsed_ms\":{},\"solutions\":{},\
+             \"devices\":{}",
+            solved.trial, json_str(solved.label),
+            solved.game.board.play_count(), game_notation(solved.game),
+            solved.depth, millis(solved.elapsed),
+            json_solutions(solved.solutions), json_devices(solved.metrics)))
+    }
+
+    fn metrics(&mut self, metrics: &[DeviceMetrics]) -> UtttResult<()> {
+        self.line("metrics", &format!("\"devices\":{}", json_devices(metrics)))
+    }
+
+    /// Trims the name and value, which may be padded for the text format.
+    fn value(&mut self, name: &str, value: &str) -> UtttResult<()> {
+        self.line("value", &format!("\"name\":{},\"value\":{}",
+                                    json_str(name.trim()),
+                                    json_str(value.trim())))
+    }
+
+    fn row(&mut self, fields: &[(&str, i64)]) -> UtttResult<()> {
+        let fields = fields.iter()
+            .map(|&(name, x)| format!("{}:{}", json_str(name), x))
+            .collect::<Vec<String>>();
+        self.line("row", &fields.join(","))
+    }
+
+    fn separator(&mut self) -> UtttResult<()> {
+        Ok(())
+    }
+}
+
+// == helpers ==================================================================
+
+/// Returns a game's board as 81 characters, row by row: 'X', 'O', or '.' for
+/// an empty slot.
+pub fn game_notation(game: &Game) -> String {
+    let mut s = String::with_capacity(81);
+    for row in 0 .. 9 {
+        for col in 0 .. 9 {
+            s.push(match game.board.player_at_row_col(RI::from_u8(row),
+                                                      CI::from_u8(col)) {
+                Some(Player::X) => 'X',
+                Some(Player::O) => 'O',
+                None => '.',
+            });
+        }
+    }
+    s
+}
+
+/// Returns a JSON array of solutions, each an object with a "play" (a
+/// location, or null), an "outcome" ("X" or "O" for the winner, "tie" or
+/// "unknown") and "turns".
+pub fn json_solutions(solutions: &Vec<Solution>) -> String {
+    let solutions = solutions.iter()
+        .map(|sol| {
+            let (winner, turns) = match sol.outcome {
+                Outcome::Win { player, turns } =>
+                    (format!("\"{:?}\"", player), turns),
+                Outcome::Tie { turns } => ("\"tie\"".to_string(), turns),
+                Outcome::Unknown { turns } =>
+                    ("\"unknown\"".to_string(), turns),
+            };
+            format!("{{\"play\":{},\"outcome\":{},\"turns\":{}}}",
+                    json_loc(sol.opt_play.map(|play| play.loc)), winner, turns)
+        })
+        .collect::<Vec<String>>();
+    format!("[{}]", solutions.join(","))
+}
+
+/// Returns a location as a JSON string of a row digit and a column digit, or
+/// null.
+pub fn json_loc(opt_loc: Option<Loc>) -> String {
+    match opt_loc {
+        None => "null".to_string(),
+        Some(loc) => format!("\"{}{}\"", loc.row().as_u8(), loc.col().as_u8()),
+    }
+}
+
+/// Returns a JSON array of device metrics.
+pub fn json_devices(metrics: &[DeviceMetrics]) -> String {
+    let devices = metrics.iter()
+        .map(|m| {
+            let c = &m.counters;
+            let (accepted, rejected) = match m.admission {
+                Some((a, r)) => (a.to_string(), r.to_string()),
+                None => ("null".to_string(), "null".to_string()),
+            };
+            format!("{{\"name\":{},\"reads\":{},\"hits\":{},\"misses\":{},\
+                     \"writes\":{},\"evictions\":{},\"flushes\":{},\
+                     \"flushed\":{},\"read_ms\":{},\"write_ms\":{},\
+                     \"flush_ms\":{},\"cache_1\":{},\"cache_2\":{},\
+                     \"accepted\":{},\"rejected\":{},\"reconnects\":{}}}",
+                    json_str(m.name), c.reads, c.total_hits(),
+                    c.total_misses(), c.writes, c.evictions, c.flushes,
+                    c.flushed, millis(c.read_time), millis(c.write_time),
+                    millis(c.flush_time), json_opt(m.cache_1_len),
+                    json_opt(m.cache_2_len), accepted, rejected,
+                    json_opt(m.reconnects))
+        })
+        .collect::<Vec<String>>();
+    format!("[{}]", devices.join(","))
+}
+
+/// Returns a value as JSON, or null.
+pub fn json_opt<T: ToString>(x: Option<T>) -> String {
+    x.map_or("null".to_string(), |x| x.to_string())
+}
+
+/// Returns a JSON string literal.
+pub fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 =>
+                out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Returns a duration in milliseconds.
+pub fn millis(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1_000_000.0
+}
+
+/// Returns a duration in whole milliseconds, rounded down.
+pub fn whole_millis(d: Duration) -> u64 {
+    d.as_secs() * 1000 + d.subsec_nanos() as u64 / 1_000_000
+}
@@ -0,0 +1,65 @@
+use constants::*;
+use output::*;
+use std::time::Duration;
+
+fn json_lines<F>(emit: F) -> Vec<String>
+    where F: Fn(&mut dyn Sink) -> UtttResult<()> {
+    let mut sink = JsonSink::new(Vec::new());
+    emit(&mut sink).expect("E4511");
+    let bytes = sink.into_inner();
+    String::from_utf8(bytes).expect("E4512")
+        .lines().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_json_str() {
+    assert_eq!(json_str("N-1"), "\"N-1\"");
+    assert_eq!(json_str("a \"b\" \\ c\n\t"),
+               "\"a \\\"b\\\" \\\\ c\\n\\u0009\"");
+}
+
+#[test]
+fn test_game_notation() {
+    let mut game = EMPTY_GAME;
+    assert_eq!(game_notation(&game), ".".repeat(81));
+    let loc = Loc::from_row_col(RI::from_u8(1), CI::from_u8(2));
+    assert!(game.play(Play { loc: loc, player: Player::X }));
+    let notation = game_notation(&game);
+    assert_eq!(notation.find('X'), Some(9 + 2));
+    assert_eq!(notation.matches('.').count(), 80);
+}
+
+#[test]
+fn test_json_sink_lines() {
+    let mut game = EMPTY_GAME;
+    let loc = Loc::from_row_col(RI::from_u8(4), CI::from_u8(4));
+    assert!(game.play(Play { loc: loc, player: Player::X }));
+    let solutions = vec![];
+    let lines = json_lines(|out| {
+        out.heading(1, "Trial #1")?;
+        out.game(1, "Game N", &game)?;
+        out.separator()?;
+        out.value(" ties", "3")?;
+        out.row(&[("plays", 60), ("unknown", 2)])?;
+        out.solutions(&Solved {
+            trial: 1,
+            label: "N-1",
+            game: &game,
+            depth: 3,
+            solutions: &solutions,
+            elapsed: Duration::from_millis(1500),
+            metrics: &[],
+        })
+    });
+    assert_eq!(lines, vec![
+        "{\"type\":\"heading\",\"level\":1,\"title\":\"Trial #1\"}".to_string(),
+        format!("{{\"type\":\"game\",\"trial\":1,\"label\":\"Game N\",\
+                 \"ply\":1,\"game\":\"{}\",\"last\":\"44\",\
+                 \"state\":\"ongoing\"}}", game_notation(&game)),
+        "{\"type\":\"value\",\"name\":\"ties\",\"value\":\"3\"}".to_string(),
+        "{\"type\":\"row\",\"plays\":60,\"unknown\":2}".to_string(),
+        format!("{{\"type\":\"solutions\",\"trial\":1,\"label\":\"N-1\",\
+                 \"ply\":1,\"game\":\"{}\",\"depth\":3,\"elapsed_ms\":1500,\
+                 \"solutions\":[],\"devices\":[]}}", game_notation(&game)),
+    ]);
+}
@@ -0,0 +1,442 @@
+/// Engine protocol.
+///
+/// A line protocol on stdin and stdout, modelled on UCI (the Universal Chess
+/// Interface), so that the solver can play in bot arenas and against other
+/// engines. Each line from the server is a message; the engine answers only
+/// the messages that ask for a reply.
+///
+/// message                            reply
+/// -------                            -----
+/// uti (or uci)                       'id' lines, an 'option' line per
+///                                    option, then 'utiok' (or 'uciok')
+/// isready                            readyok
+/// setoption name <n> value <v>       -
+/// newgame (or utinewgame, ...)       -
+/// position startpos [moves <l>..]    -
+/// play <l>..                         -
+/// go [<parameter>..]                 'info' lines, then 'bestmove <l>'
+/// stop                               - (ends a search)
+/// show                               the board
+/// quit                               -
+///
+/// A location <l> is a row digit and a column digit, as in run manifests, or
+/// `R3C5`. `position` sets the plays from the empty game; `play` adds plays to
+/// the current position, for arenas that send only the opponent's play. (As in
+/// UCI, the engine does not add its own 'bestmove'.)
+///
+/// The parameters of 'go' are `movetime <ms>`, `depth <n>` (at least 1),
+/// `wtime`, `btime`, `winc` and `binc` (X's and O's clocks and increments, in
+/// ms), `movestogo <n>`, `infinite`, and `searchmoves <l>..` (the valid
+/// actions an arena offers; the reply is one of them). The search deepens one
+/// play at a time, up to the depth, until the outcome is decided, the time
+/// runs out, or a 'stop' (or a signal; see the 'signals' module) arrives; its
+/// deadline is kept even within a depth. It then answers the best play of the
+/// deepest finished depth.
+///
+/// After each depth, an 'info' line gives the depth, the time used in ms, the
+/// score and the best play: `info depth 4 time 35 score unknown pv 16`. The
+/// score is `win <n>`, `loss <n>` or `tie <n>` for a decided outcome in n
+/// turns, from the side to move's point of view, or `unknown`. Errors are
+/// reported as `info string <error>`, and the engine carries on.
+
+use constants::*;
+use data::*;
+use error::*;
+use interactive::{loc_digits, parse_loc};
+use output::whole_millis;
+use show::*;
+use signals::*;
+use solver::*;
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests;
+
+/// The flag that stops a search; the stack of the 'engine' command watches it
+/// (see `Stack::stop`).
+pub static SEARCH_STOP: AtomicBool = AtomicBool::new(false);
+
+/// The default "MoveOverhead", in ms.
+pub const DEFAULT_OVERHEAD_MS: u64 = 20;
+
+/// The number of plays to spread the remaining clock time over, when
+/// `movestogo` is not given.
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+// == messages =================================================================
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// The handshake, with the word that started it ("uti" or "uci").
+    Hello(String),
+    IsReady,
+    SetOption { name: String, value: String },
+    NewGame,
+    Position(Vec<Loc>),
+    Play(Vec<Loc>),
+    Go(Go),
+    Stop,
+    Show,
+    Quit,
+}
+
+/// The parameters of a search; see the module documentation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Go {
+    pub move_time: Option<u64>,
+    pub depth: Option<Count>,
+    /// X's and O's remaining time, in ms.
+    pub clocks: [Option<u64>; 2],
+    /// X's and O's increments, in ms.
+    pub increments: [u64; 2],
+    pub moves_to_go: Option<u64>,
+    pub infinite: bool,
+    pub search_plays: Vec<Loc>,
+}
+
+pub fn parse_message(line: &str) -> UtttResult<Message> {
+    let words = line.split_whitespace().collect::<Vec<&str>>();
+    match words.split_first() {
+        Some((&"uti", _)) | Some((&"uci", _)) =>
+            Ok(Message::Hello(words[0].to_string())),
+        Some((&"isready", _)) => Ok(Message::IsReady),
+        Some((&"setoption", rest)) => parse_option(rest),
+        Some((&"newgame", _)) | Some((&"utinewgame", _)) |
+        Some((&"ucinewgame", _)) => Ok(Message::NewGame),
+        Some((&"position", rest)) => match rest.split_first() {
+            Some((&"startpos", &[])) => Ok(Message::Position(vec![])),
+            Some((&"startpos", moves)) if moves[0] == "moves" =>
+                locs(&moves[1 ..]).map(Message::Position),
+            _ => Err(usage("E5101", "expected 'position startpos [moves ..]'")),
+        },
+        Some((&"play", rest)) => locs(rest).map(Message::Play),
+        Some((&"go", rest)) => parse_go(rest).map(Message::Go),
+        Some((&"stop", _)) => Ok(Message::Stop),
+        Some((&"show", _)) | Some((&"d", _)) => Ok(Message::Show),
+        Some((&"quit", _)) => Ok(Message::Quit),
+        _ => Err(usage("E5102", &format!("unknown message '{}'", line.trim()))),
+    }
+}
+
+fn parse_option(words: &[&str]) -> UtttResult<Message> {
+    let value_at = words.iter().position(|&w| w == "value");
+    match (words.first(), value_at) {
+        (Some(&"name"), Some(i)) if i > 1 => Ok(Message::SetOption {
+            name: words[1 .. i].join(" "),
+            value: words[i + 1 ..].join(" "),
+        }),
+        _ => Err(usage("E5103", "expected 'setoption name <n> value <v>'")),
+    }
+}
+
+fn parse_go(words: &[&str]) -> UtttResult<Go> {
+    let mut go = Go::default();
+    let mut iter = words.iter();
+    while let Some(&word) = iter.next() {
+        if word == "infinite" {
+            go.infinite = true;
+            continue;
+        }
+        if word == "searchmoves" {
+            let rest = iter.by_ref().cloned().collect::<Vec<&str>>();
+            go.search_plays = locs(&rest)?;
+            break;
+        }
+        let value = iter.next().ok_or_else(|| usage(
+            "E5104", &format!("'{}' needs a value", word)))?;
+        let n = value.parse::<u64>().map_err(|_| usage(
+            "E5105", &format!("'{}' expects a number, not '{}'", word,
+                              value)))?;
+        match word {
+            "movetime" => go.move_time = Some(n),
+            "depth" if n == 0 => return Err(usage(
+                "E5115", "'depth' must be at least 1")),
+            "depth" => go.depth = Some(n.min(81) as Count),
+            "wtime" => go.clocks[0] = Some(n),
+            "btime" => go.clocks[1] = Some(n),
+            "winc" => go.increments[0] = n,
+            "binc" => go.increments[1] = n,
+            "movestogo" => go.moves_to_go = Some(n),
+            _ => return Err(usage(
+                "E5106", &format!("unknown 'go' parameter '{}'", word))),
+        }
+    }
+    Ok(go)
+}
+
+fn locs(words: &[&str]) -> UtttResult<Vec<Loc>> {
+    words.iter()
+        .map(|word| parse_loc(word).ok_or_else(|| usage(
+            "E5107", &format!("'{}' is not a location", word))))
+        .collect()
+}
+
+// == the engine ===============================================================
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BotOptions {
+    /// The maximum depth of a search ("Depth").
+    pub depth: Count,
+    /// The time for a search without a time limit of its own, in ms; 0 for
+    /// none ("MoveTime").
+    pub move_time: u64,
+    /// The time kept back from every time limit, for the latency of the
+    /// server, in ms ("MoveOverhead").
+    pub overhead: u64,
+}
+
+/// The position and the options of the engine.
+pub struct Bot<'a> {
+    stack: &'a Stack,
+    stop: &'static AtomicBool,
+    pub game: Game,
+    pub options: BotOptions,
+}
+
+/// The result of a search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Search {
+    /// The play to make; None if the game is over.
+    pub play: Option<Play>,
+    /// The deepest finished depth (0 if none finished).
+    pub depth: Count,
+    pub solutions: Vec<Solution>,
+}
+
+impl<'a> Bot<'a> {
+    /// Returns an engine at the start of a game. The stack needs a stop flag,
+    /// which the engine sets to end a search. A depth over 81 is taken as 81.
+    pub fn new(stack: &'a Stack, options: BotOptions) -> UtttResult<Bot<'a>> {
+        let stop = stack.stop.ok_or_else(|| UtttError::invariant(
+            "E5108", "the engine's stack has no stop flag"))?;
+        let options = BotOptions { depth: options.depth.min(81), ..options };
+        Ok(Bot { stack: stack, stop: stop, game: EMPTY_GAME,
+                 options: options })
+    }
+
+    /// Handles a message other than 'quit'.
+    pub fn handle<W: Write>(&mut self, message: Message, out: &mut W)
+                            -> UtttResult<()> {
+        match message {
+            Message::Hello(word) => self.hello(&word, out),
+            Message::IsReady => say(out, "readyok"),
+            Message::SetOption { name, value } =>
+                self.set_option(&name, &value),
+            Message::NewGame => {
+                self.game = EMPTY_GAME;
+                Ok(())
+            },
+            Message::Position(locs) => {
+                self.game = play_all(EMPTY_GAME, &locs)?;
+                Ok(())
+            },
+            Message::Play(locs) => {
+                self.game = play_all(self.game, &locs)?;
+                Ok(())
+            },
+            Message::Go(go) => {
+                let search = self.search(&go, out)?;
+                say(out, &format!("bestmove {}", search.play.map_or(
+                    "none".to_string(), |play| loc_digits(play.loc))))
+            },
+            // A 'stop' that ends a search sets the flag as it is read (see
+            // `run_protocol`); by now that search is over.
+            Message::Stop => {
+                self.stop.store(false, Ordering::SeqCst);
+                Ok(())
+            },
+            Message::Show => say(out, self.game.show().trim_end()),
+            Message::Quit => Ok(()),
+        }
+    }
+
+    fn hello<W: Write>(&self, word: &str, out: &mut W) -> UtttResult<()> {
+        let author = env!("CARGO_PKG_AUTHORS").split(" <").next()
+            .unwrap_or("");
+        say(out, &format!("id name uttt {}", env!("CARGO_PKG_VERSION")))?;
+        say(out, &format!("id author {}", author))?;
+        say(out, &format!("option name Depth type spin default {} min 1 \
+                           max 81", self.options.depth))?;
+        say(out, &format!("option name MoveTime type spin default {} min 0 \
+                           max 3600000", self.options.move_time))?;
+        say(out, &format!("option name MoveOverhead type spin default {} \
+                           min 0 max 60000", self.options.overhead))?;
+        say(out, &format!("{}ok", word))
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> UtttResult<()> {
+        let n = value.parse::<u64>().map_err(|_| usage(
+            "E5109", &format!("option '{}' expects a number, not '{}'", name,
+                              value)))?;
+        match name.to_lowercase().as_str() {
+            "depth" if 1 <= n && n <= 81 => self.options.depth = n as Count,
+            "movetime" => self.options.move_time = n,
+            "moveoverhead" => self.options.overhead = n,
+            _ => return Err(usage("E5110", &format!(
+                "unknown option '{}', or bad value '{}'", name, value))),
+        }
+        Ok(())
+    }
+
+    /// Searches the current position, writing an 'info' line per depth.
+    pub fn search<W: Write>(&mut self, go: &Go, out: &mut W)
+                            -> UtttResult<Search> {
+        let start = Instant::now();
+        let player = match self.game.next_player() {
+            Some(player) => player,
+            None => return Ok(Search { play: None, depth: 0,
+                                       solutions: vec![] }),
+        };
+        let allowed = self.game.valid_plays().into_iter()
+            .filter(|play| go.search_plays.is_empty() ||
+                    go.search_plays.contains(&play.loc))
+            .collect::<Vec<Play>>();
+        if allowed.is_empty() {
+            return Err(usage("E5111", "none of the searchmoves is valid"));
+        }
+        let max_depth = go.depth.unwrap_or(self.options.depth);
+        // The timer also passes on a stop request (Ctrl-C), which would not
+        // otherwise end a search without a budget.
+        let timer = start_timer(self.stop, self.budget(go, player));
+        let mut deepest: Option<(Count, Vec<Solution>)> = None;
+        let mut result = Ok(());
+        // With a single choice, there is nothing to search.
+        let first = if allowed.len() == 1 { max_depth + 1 } else { 1 };
+        for depth in first .. (max_depth + 1) {
+            let solutions = match self.stack.get_and_put(&self.game, depth) {
+                Ok(solutions) => solutions,
+                Err(ref e) if e.is_interrupted() => break,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                },
+            };
+            let decided = solutions.iter().all(|sol| match sol.outcome {
+                Outcome::Unknown { .. } => false,
+                _ => true,
+            });
+            result = say(out, &format!(
+                "info depth {} time {} score {} pv {}", depth,
+                whole_millis(start.elapsed()), score(&solutions, player),
+                solutions.first().and_then(|sol| sol.opt_play)
+                    .map_or("none".to_string(), |play| loc_digits(play.loc))));
+            deepest = Some((depth, solutions));
+            if decided || result.is_err() { break; }
+        }
+        // This clears the flag if the timer set it; a 'stop' clears its own.
+        timer.finish();
+        result?;
+        let (depth, solutions) = deepest.unwrap_or((0, vec![]));
+        // A dominant solution found at a lower depth may have no play.
+        let play = solutions.iter()
+            .filter_map(|sol| sol.opt_play)
+            .find(|play| allowed.contains(play))
+            .unwrap_or(allowed[0]);
+        Ok(Search { play: Some(play), depth: depth, solutions: solutions })
+    }
+
+    /// Returns the time limit of a search, if it has one.
+    pub fn budget(&self, go: &Go, player: Player) -> Option<Duration> {
+        let side = match player { Player::X => 0, Player::O => 1 };
+        let overhead = self.options.overhead;
+        let ms = if go.infinite {
+            None
+        } else if let Some(ms) = go.move_time {
+            Some(ms.saturating_sub(overhead))
+        } else if let Some(clock) = go.clocks[side] {
+            let moves = go.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+            let ms = clock / moves + go.increments[side];
+            Some(ms.min(clock.saturating_sub(overhead)))
+        } else if self.options.move_time > 0 {
+            Some(self.options.move_time.saturating_sub(overhead))
+        } else {
+            None
+        };
+        ms.map(|ms| Duration::from_millis(ms.max(1)))
+    }
+}
+
+/// Speaks the protocol until 'quit', the end of the input, or a stop request
+/// (see the 'signals' module).
+///
+/// The input is read on its own thread, so that a 'stop' (or 'quit') can end
+/// a search: it sets the stop flag as soon as it is read.
+pub fn run_protocol<R, W>(bot: &mut Bot, input: R, out: &mut W)
+                          -> UtttResult<()>
+    where R: BufRead + Send + 'static, W: Write {
+    let (tx, rx) = mpsc::channel::<String>();
+    let stop = bot.stop;
+    thread::spawn(move || {
+        for line in input.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            match line.trim() {
+                "stop" | "quit" => stop.store(true, Ordering::SeqCst),
+                _ => {},
+            }
+            if tx.send(line).is_err() { break; }
+        }
+    });
+    while !stop_requested() {
+        let line = match rx.recv_timeout(Duration::from_millis(POLL_MS)) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if stop_requested() { break; }
+        if line.trim().is_empty() { continue; }
+        let result = match parse_message(&line) {
+            Ok(Message::Quit) => break,
+            Ok(message) => bot.handle(message, out),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {},
+            Err(e @ UtttError::Usage { .. }) =>
+                say(out, &format!("info string {}", e))?,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// == helpers ==================================================================
+
+fn play_all(mut game: Game, locs: &[Loc]) -> UtttResult<Game> {
+    for &loc in locs.iter() {
+        let player = game.next_player()
+            .ok_or_else(|| usage("E5112", "the game is over"))?;
+        if !game.play(Play { loc: loc, player: player }) {
+            return Err(usage("E5113", &format!(
+                "{} is not a valid play", loc_digits(loc))));
+        }
+    }
+    Ok(game)
+}
+
+/// Returns the score of the best solution, from a player's point of view.
+fn score(solutions: &Vec<Solution>, player: Player) -> String {
+    match solutions.first().map(|sol| sol.outcome) {
+        Some(Outcome::Win { player: winner, turns }) if winner == player =>
+            format!("win {}", turns),
+        Some(Outcome::Win { turns, .. }) => format!("loss {}", turns),
+        Some(Outcome::Tie { turns }) => format!("tie {}", turns),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn say<W: Write>(out: &mut W, line: &str) -> UtttResult<()> {
+    writeln!(out, "{}", line)
+        .and_then(|_| out.flush())
+        .map_err(|e| UtttError::from_io("E5114", e))
+}
+
+fn usage(code: &'static str, message: &str) -> UtttError {
+    UtttError::usage(code, message)
+}
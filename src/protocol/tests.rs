@@ -0,0 +1,138 @@
+use interactive::parse_loc;
+use output::whole_millis;
+use protocol::*;
+use std::io::Cursor;
+use std::sync::atomic::AtomicBool;
+
+// A flag per test, since tests run in parallel.
+static STOP_1: AtomicBool = AtomicBool::new(false);
+static STOP_2: AtomicBool = AtomicBool::new(false);
+static STOP_3: AtomicBool = AtomicBool::new(false);
+static STOP_4: AtomicBool = AtomicBool::new(false);
+
+const OPTIONS: BotOptions = BotOptions { depth: 2, move_time: 0, overhead: 20 };
+
+fn loc(s: &str) -> Loc {
+    parse_loc(s).expect("E5121")
+}
+
+fn stack(stop: &'static AtomicBool) -> Stack {
+    let mut stack = CPU_Stack::new();
+    stack.stop = Some(stop);
+    stack
+}
+
+#[test]
+fn test_parse_message() {
+    assert_eq!(parse_message("uci"), Ok(Message::Hello("uci".to_string())));
+    assert_eq!(parse_message(" position startpos "),
+               Ok(Message::Position(vec![])));
+    assert_eq!(parse_message("position startpos moves 44 R3C5"),
+               Ok(Message::Position(vec![loc("44"), loc("35")])));
+    assert_eq!(parse_message("setoption name Move Overhead value 50"),
+               Ok(Message::SetOption { name: "Move Overhead".to_string(),
+                                       value: "50".to_string() }));
+    let go = Go {
+        move_time: Some(500),
+        depth: Some(81),
+        infinite: true,
+        search_plays: vec![loc("13"), loc("14")],
+        ..Go::default()
+    };
+    assert_eq!(parse_message("go movetime 500 depth 90 infinite \
+                              searchmoves 13 14"),
+               Ok(Message::Go(go)));
+    let cases = [
+        ("position 44", "E5101"),
+        ("castle", "E5102"),
+        ("setoption name Depth", "E5103"),
+        ("go movetime", "E5104"),
+        ("go wtime soon", "E5105"),
+        ("go nodes 10", "E5106"),
+        ("play 99", "E5107"),
+        ("go depth 0", "E5115"),
+    ];
+    for &(line, code) in cases.iter() {
+        match parse_message(line) {
+            Err(UtttError::Usage { code: c, .. }) if c == code => {},
+            other => panic!("unexpected {:?} for '{}'", other, line),
+        }
+    }
+}
+
+#[test]
+fn test_budget() {
+    let stack = stack(&STOP_1);
+    let bot = Bot::new(&stack, OPTIONS).expect("E5122");
+    let ms = |go: &Go, player| bot.budget(go, player).map(whole_millis);
+    assert_eq!(ms(&Go::default(), Player::X), None);
+    let go = Go { move_time: Some(500), ..Go::default() };
+    assert_eq!(ms(&go, Player::X), Some(480));
+    let go = Go { clocks: [Some(60000), Some(3000)], increments: [0, 100],
+                  ..Go::default() };
+    assert_eq!(ms(&go, Player::X), Some(2000));
+    assert_eq!(ms(&go, Player::O), Some(200));
+    let go = Go { clocks: [Some(10), None], ..Go::default() };
+    assert_eq!(ms(&go, Player::X), Some(1));
+    let go = Go { infinite: true, move_time: Some(500), ..Go::default() };
+    assert_eq!(bot.budget(&go, Player::X), None);
+}
+
+#[test]
+fn test_search() {
+    let stack = stack(&STOP_2);
+    let mut bot = Bot::new(&stack, OPTIONS).expect("E5123");
+    let mut out: Vec<u8> = Vec::new();
+    bot.handle(Message::Position(vec![loc("44"), loc("35")]), &mut out)
+        .expect("E5124");
+    let search = bot.search(&Go::default(), &mut out).expect("E5125");
+    assert_eq!(search.depth, 2);
+    let play = search.play.expect("E5126");
+    assert!(bot.game.is_valid_play(play));
+    let text = String::from_utf8(out).expect("E5127");
+    assert_eq!(text.lines().count(), 2);
+    assert!(text.starts_with("info depth 1 time "), "{}", text);
+    // The reply is one of the searchmoves, even if it is not the best.
+    let go = Go { search_plays: vec![loc("28")], ..Go::default() };
+    let search = bot.search(&go, &mut Vec::new()).expect("E5128");
+    assert_eq!(search.play.map(|play| play.loc), Some(loc("28")));
+    let go = Go { search_plays: vec![loc("44")], ..Go::default() };
+    assert!(bot.search(&go, &mut Vec::new()).is_err());
+}
+
+#[test]
+fn test_search_keeps_its_deadline() {
+    let stack = stack(&STOP_3);
+    let options = BotOptions { depth: 255, move_time: 0, overhead: 0 };
+    let mut bot = Bot::new(&stack, options).expect("E5129");
+    assert_eq!(bot.options.depth, 81);
+    let go = Go { move_time: Some(200), ..Go::default() };
+    let search = bot.search(&go, &mut Vec::new()).expect("E5130");
+    assert!(search.depth >= 1 && search.depth < 81);
+    assert!(search.play.is_some());
+    // The timer's stop was cleared for the next search.
+    assert!(!stack.is_stopping());
+}
+
+#[test]
+fn test_run_protocol() {
+    let stack = stack(&STOP_4);
+    let mut bot = Bot::new(&stack, OPTIONS).expect("E5131");
+    let input = "uti\nisready\nsetoption name Depth value 1\n\
+                 position startpos moves 44\ngo\nplay 35 00\nshow\n\
+                 quit\nisready\n";
+    let mut out: Vec<u8> = Vec::new();
+    run_protocol(&mut bot, Cursor::new(input.as_bytes().to_vec()), &mut out)
+        .expect("E5132");
+    let text = String::from_utf8(out).expect("E5133");
+    let lines = text.lines().collect::<Vec<&str>>();
+    assert!(lines[0].starts_with("id name uttt "));
+    assert!(lines.contains(&"utiok") && lines.contains(&"readyok"));
+    assert!(lines.iter().any(|l| l.starts_with("bestmove ")), "{}", text);
+    assert!(lines.iter().any(|l| l.starts_with("info string E5113")));
+    // A bad 'play' leaves the position as it was, and 'quit' ends the loop.
+    assert_eq!(bot.game.board.play_count(), 1);
+    assert_eq!(lines.iter().filter(|&&l| l == "readyok").count(), 1);
+    assert_eq!(bot.options.depth, 1);
+    assert!(Bot::new(&CPU_Stack::new(), OPTIONS).is_err());
+}
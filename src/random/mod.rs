@@ -3,7 +3,11 @@
 use constants::*;
 use data::*;
 use rand::{Rand, Rng, thread_rng};
+#[cfg(test)]
+use solver::{Outcome, Solution};
 use std::collections::LinkedList;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
 // -- games --------------------------------------------------------------------
 
@@ -216,6 +220,37 @@ impl Rand for Player {
     }
 }
 
+// -- solution -----------------------------------------------------------------
+
+#[cfg(test)]
+impl Rand for Solution {
+    /// Returns a random solution. It need not be a solution to any particular
+    /// game. Only for tests.
+    fn rand<R: Rng>(rng: &mut R) -> Self {
+        let opt_play = if rng.gen::<bool>() {
+            Some(Play { loc: random_loc(rng), player: rng.gen::<Player>() })
+        } else {
+            None
+        };
+        Solution { opt_play: opt_play, outcome: rng.gen::<Outcome>() }
+    }
+}
+
+// -- outcome ------------------------------------------------------------------
+
+#[cfg(test)]
+impl Rand for Outcome {
+    /// Returns a random outcome.
+    fn rand<R: Rng>(rng: &mut R) -> Self {
+        let turns: Count = rng.gen_range(0, 82);
+        match rng.gen_range(0, 3) {
+            0 => Outcome::Win { player: rng.gen::<Player>(), turns: turns },
+            1 => Outcome::Tie { turns: turns },
+            _ => Outcome::Unknown { turns: turns },
+        }
+    }
+}
+
 // -- [u32; 4] -----------------------------------------------------------------
 
 /// Returns a random seed, intended for XorShiftRng.
@@ -227,6 +262,16 @@ pub fn random_seed() -> [u32; 4] {
         rng.gen::<u32>(),
         rng.gen::<u32>(),
     ];
-    println!("Using random number seed {:?}\n", seed);
     seed
 }
+
+// -- f64 ----------------------------------------------------------------------
+
+/// Returns a number in [0.0, 1.0) derived from hashing a value. Comparing it
+/// with a rate picks a repeatable sample; hash a seed along with the value
+/// (e.g. as a tuple) to pick a different one.
+pub fn hash_fraction<T: Hash>(value: &T) -> f64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -1,13 +1,42 @@
+use constants::*;
 use data::*;
+use error::*;
+use postgres::Connection;
 use rand::{Rng, XorShiftRng, SeedableRng};
+use manifest::*;
+use output::*;
 use random::*;
 use solver::*;
-use solver::db::{turns_and_unknown};
-use utility::{h, p, pln};
+use solver::db::*;
+use solver::export::*;
+use solver::file::file_write_from_db;
+use solver::verify::*;
+use std::collections::LinkedList;
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::{Duration, Instant};
+use tournament::*;
+use training::*;
 
-pub fn run_random_games<R: Rng>(trials: u16, rng: &mut R) {
+/// The settings of a solver run; see `Options::run_config`. When a run is
+/// replayed, `depth` and `back` come from its manifest instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RunConfig {
+    pub trials: u16,
+    pub depth: Count,
+    /// The number of plays back from the end of each random game.
+    pub back: Count,
+    /// The format of the training data, for `run_training`.
+    pub data: TrainingFormat,
+    /// If true, `run_training` writes every symmetry of each example.
+    pub augment: bool,
+    pub verbose: bool,
+}
+
+pub fn run_random_games<R: Rng>(trials: u16, rng: &mut R, out: &mut dyn Sink)
+                               -> UtttResult<()> {
     if trials > 0 {
-        h(0, "random_games()");
+        out.heading(0, "random_games()")?;
         let mut x_wins = 0;
         let mut o_wins = 0;
         let mut ties = 0;
@@ -15,9 +44,10 @@ pub fn run_random_games<R: Rng>(trials: u16, rng: &mut R) {
         for i in 0 .. trials {
             let games = random_games(rng);
             let game_len = games.len();
-            let game = games.iter().last().expect("E99XX");
+            let game = last_game(&games)?;
             let winner = game.winner();
-            println!("Game #{:4}: {} in {}", i, result_str(winner), game_len);
+            out.value(&format!("Game #{:4}", i),
+                      &format!("{} in {}", result_str(winner), game_len))?;
             games_len += game_len;
             match winner {
                 None => ties += 1,
@@ -25,146 +55,468 @@ pub fn run_random_games<R: Rng>(trials: u16, rng: &mut R) {
                 Some(Player::O) => o_wins += 1,
             }
         }
-        println!("");
-        println!("X wins: {:4}", x_wins);
-        println!("O wins: {:4}", o_wins);
-        println!("  ties: {:4}", ties);
-        println!("");
-        println!("average game length: {}",
-            (games_len as f64) / (trials as f64));
+        out.separator()?;
+        out.value("X wins", &format!("{:4}", x_wins))?;
+        out.value("O wins", &format!("{:4}", o_wins))?;
+        out.value("  ties", &format!("{:4}", ties))?;
+        out.separator()?;
+        out.value("average game length",
+                  &((games_len as f64) / (trials as f64)).to_string())?;
     }
+    Ok(())
 }
 
-pub fn run_random_game<R: Rng>(trials: u16, rng: &mut R) {
+pub fn run_random_game<R: Rng>(trials: u16, rng: &mut R, out: &mut dyn Sink)
+                              -> UtttResult<()> {
     if trials > 0 {
-        h(0, "random_game()");
+        out.heading(0, "random_game()")?;
         for i in 0 .. trials {
-            h(1, &format!("Game #{}", i));
+            out.heading(1, &format!("Game #{}", i))?;
             let game = random_game(rng);
-            p(&game);
+            out.game(i as u32, "", &game)?;
         }
     }
+    Ok(())
 }
 
-pub fn run_solve<R: Rng>(trials: u16, stack: &Stack, rng: &mut R,
-                     back: Count, depth: Count, verbose: bool) {
-    if trials > 0 && back > 0 {
-        h(0, "Solve N-4");
-        for i in 0 .. trials {
-            if verbose { h(1, &format!("Trial #{}", i)); }
+pub fn run_solve<R: Rng>(config: &RunConfig, stack: &Stack, rng: &mut R,
+                         manifest: &mut Manifest, out: &mut dyn Sink)
+                         -> UtttResult<()> {
+    if config.trials > 0 && config.back > 0 {
+        out.heading(0, "Solve N-4")?;
+        for trial in 1 .. (config.trials as u32 + 1) {
+            if stack.is_stopping() { break; }
             let games = random_games(rng);
-            let mut games_iter = games.iter();
-            // Get the last move in the sequence of games.
-            let game_n = games_iter.next_back().expect("E99XX");
-            if verbose { h(2, "Game N"); }
-            if verbose { pln(game_n); }
-
-            // TODO: It would be nice to extract out this chunk of code;
-            // however, the types have flummoxed me so far.
-            //
-            // Back up `back - 1` times.
-            for _ in 0 .. (back - 1) {
-                games_iter.next_back().expect("E99XX");
-            }
-            // Back up one more time.
-            let game = games_iter.next_back().expect("E99XX");
-
-            let label = format!("Game N-{}", back);
-            if verbose { h(2, &label); }
-            if verbose { pln(game); }
-            let solutions = solve(stack, &game, depth);
-            if verbose { p_solutions(&label, depth, &solutions); }
+            manifest.record_trial(trial, &games)?;
+            solve_trial(config, stack, out, trial, &games)?;
         }
     }
+    Ok(())
+}
+
+/// Solves the game `back` plays before the end of a trial's games.
+pub fn solve_trial(config: &RunConfig, stack: &Stack, out: &mut dyn Sink,
+                   trial: u32, games: &LinkedList<Game>) -> UtttResult<()> {
+    let (back, depth, verbose) = (config.back, config.depth, config.verbose);
+    if verbose { out.heading(1, &format!("Trial #{}", trial))?; }
+    let game_n = last_game(games)?;
+    if verbose {
+        out.heading(2, "Game N")?;
+        out.game(trial, "Game N", game_n)?;
+    }
+    let game = game_back(games, back)?;
+    let label = format!("Game N-{}", back);
+    if verbose {
+        out.heading(2, &label)?;
+        out.game(trial, &label, game)?;
+    }
+    let (solutions, elapsed) = solve(stack, &game, depth)?;
+    if verbose {
+        emit_solutions(stack, out, trial, &label, game, depth, &solutions,
+                       elapsed)?;
+    }
+    Ok(())
 }
 
-pub fn run_backwards_solve<R: Rng>(trials: u16, stack: &Stack, rng: &mut R,
-                               depth: Count, n: Count, verbose: bool) {
-    if trials > 0 && n > 0 {
-        h(0, "Solving Back to Front");
-        for trial in 1 .. trials + 1 {
-            if verbose { h(1, &format!("Trial #{}", trial)); }
+pub fn run_backwards_solve<R: Rng>(config: &RunConfig, stack: &Stack,
+                                   rng: &mut R, manifest: &mut Manifest,
+                                   out: &mut dyn Sink) -> UtttResult<()> {
+    if config.trials > 0 && config.back > 0 {
+        out.heading(0, "Solving Back to Front")?;
+        for trial in 1 .. (config.trials as u32 + 1) {
+            if stack.is_stopping() { break; }
             let games = random_games(rng);
-            let mut games_iter = games.iter();
-            let game_n = games_iter.next_back().expect("E99XX");
-            if verbose { h(2, "Game N"); }
-            if verbose { pln(game_n); }
-            for i in 1 .. (n + 1) {
-                let label = &format!("N-{}", i);
-                if verbose { h(2, label) }
-                let game = games_iter.next_back().expect("E99XX");
-                if verbose { pln(game); }
-                let solutions = solve(stack, &game, depth + i);
-                if verbose { p_solutions(label, depth + i, &solutions); }
-            }
+            manifest.record_trial(trial, &games)?;
+            backwards_trial(config, stack, out, trial, &games)?;
         }
     }
+    Ok(())
 }
 
-pub fn run_full_backwards_solve<R: Rng>(trials: u16, stack: &Stack,
-    rng: &mut R, verbose: bool) {
-    let depth = 81;
-    if trials > 0 {
-        h(0, "Fully Solving Back to Front");
-        for trial in 1 .. (trials + 1) {
-            if verbose { h(1, &format!("Trial #{}", trial)); }
+/// Solves the last `back` games of a trial, from the end, one play deeper
+/// each time.
+pub fn backwards_trial(config: &RunConfig, stack: &Stack, out: &mut dyn Sink,
+                       trial: u32, games: &LinkedList<Game>)
+                       -> UtttResult<()> {
+    let (depth, n, verbose) = (config.depth, config.back, config.verbose);
+    if verbose { out.heading(1, &format!("Trial #{}", trial))?; }
+    let game_n = last_game(games)?;
+    if verbose {
+        out.heading(2, "Game N")?;
+        out.game(trial, "Game N", game_n)?;
+    }
+    for i in 1 .. (n + 1) {
+        let label = &format!("N-{}", i);
+        if verbose { out.heading(2, label)?; }
+        let game = game_back(games, i)?;
+        if verbose { out.game(trial, label, game)?; }
+        // The solver looks at most 81 plays ahead.
+        let depth = depth.saturating_add(i).min(81);
+        let (solutions, elapsed) = solve(stack, &game, depth)?;
+        if verbose {
+            emit_solutions(stack, out, trial, label, game, depth,
+                           &solutions, elapsed)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run_full_backwards_solve<R: Rng>(config: &RunConfig, stack: &Stack,
+                                        rng: &mut R, manifest: &mut Manifest,
+                                        out: &mut dyn Sink) -> UtttResult<()> {
+    if config.trials > 0 {
+        out.heading(0, "Fully Solving Back to Front")?;
+        for trial in 1 .. (config.trials as u32 + 1) {
+            if stack.is_stopping() { break; }
             let games = random_games(rng);
-            let mut i = 0;
-            for game in games.iter().rev() {
-                let label = &format!("Game N-{}", i);
-                if verbose { h(2, label) }
-                if verbose { pln(game); }
-                let solutions = solve(stack, &game, depth);
-                if verbose { p_cache(stack); }
-                if verbose { p_solutions(label, depth, &solutions); }
-                i = i + 1;
-            }
+            manifest.record_trial(trial, &games)?;
+            full_backwards_trial(config, stack, out, trial, &games)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fully solves every game of a trial, from the end to the start. Ignores
+/// `config.depth` and `config.back`.
+pub fn full_backwards_trial(config: &RunConfig, stack: &Stack,
+                            out: &mut dyn Sink, trial: u32,
+                            games: &LinkedList<Game>) -> UtttResult<()> {
+    let (depth, verbose) = (81, config.verbose);
+    if verbose { out.heading(1, &format!("Trial #{}", trial))?; }
+    let mut i = 0;
+    for game in games.iter().rev() {
+        let label = &format!("Game N-{}", i);
+        if verbose {
+            out.heading(2, label)?;
+            out.game(trial, label, game)?;
         }
+        let (solutions, elapsed) = solve(stack, &game, depth)?;
+        if verbose {
+            emit_solutions(stack, out, trial, label, game, depth, &solutions,
+                           elapsed)?;
+        }
+        i = i + 1;
     }
+    Ok(())
 }
 
-/// If the inner loop finds an unknown solution, skip ahead.
-pub fn run_ongoing_backwards_solve<R: Rng>(active: bool, stack: &Stack,
-    rng: &mut R, depth: Count, n: Count, verbose: bool) {
+/// Runs `ongoing_trial` until the stack's stop flag is set (see
+/// `Stack::stop`).
+pub fn run_ongoing_backwards_solve<R: Rng>(active: bool, config: &RunConfig,
+    stack: &Stack, rng: &mut R, manifest: &mut Manifest, out: &mut dyn Sink)
+    -> UtttResult<()> {
     if active {
         let mut trial: u32 = 0;
-        h(0, "Backwards Solve (Ongoing)");
-        'outer: loop {
+        out.heading(0, "Backwards Solve (Ongoing)")?;
+        loop {
+            if stack.is_stopping() { break; }
             trial += 1;
-            if verbose { h(1, &format!("Trial #{}", trial)); }
             let games = random_games(rng);
-            let mut games_iter = games.iter();
-            let game_n = games_iter.next_back().expect("E99XX");
-            if verbose {
-                h(2, &format!("Trial #{} Game N", trial));
-                p_cache(stack);
-                pln(game_n);
-            }
-            for i in 1 .. (n + 1) {
-                let label = &format!("Trial #{} Game N-{}", trial, i);
-                if verbose {
-                    h(2, label);
-                    p_cache(stack);
-                }
-                let game = games_iter.next_back().expect("E99XX");
-                if verbose { pln(game); }
-                let solutions = solve(stack, &game, depth);
-                let (_, unknown) = turns_and_unknown(&solutions);
-                if unknown == true { break }
-                if verbose { p_solutions(label, depth, &solutions); }
+            manifest.record_trial(trial, &games)?;
+            ongoing_trial(config, stack, out, trial, &games)?;
+        }
+    }
+    Ok(())
+}
+
+/// Solves the last `back` games of a trial, from the end. If it finds an
+/// unknown solution, skips ahead (to the next trial).
+pub fn ongoing_trial(config: &RunConfig, stack: &Stack, out: &mut dyn Sink,
+                     trial: u32, games: &LinkedList<Game>) -> UtttResult<()> {
+    let (depth, n, verbose) = (config.depth, config.back, config.verbose);
+    if verbose { out.heading(1, &format!("Trial #{}", trial))?; }
+    let game_n = last_game(games)?;
+    if verbose {
+        out.heading(2, &format!("Trial #{} Game N", trial))?;
+        out.metrics(&stack.metrics())?;
+        out.game(trial, "Game N", game_n)?;
+    }
+    for i in 1 .. (n + 1) {
+        let label = &format!("Trial #{} Game N-{}", trial, i);
+        if verbose {
+            out.heading(2, label)?;
+            out.metrics(&stack.metrics())?;
+        }
+        let game = game_back(games, i)?;
+        if verbose { out.game(trial, label, game)?; }
+        let (solutions, elapsed) = solve(stack, &game, depth)?;
+        let (_, unknown) = turns_and_unknown(&solutions)?;
+        if unknown == true { break }
+        if verbose {
+            emit_solutions(stack, out, trial, label, game, depth, &solutions,
+                           elapsed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Plays one game from the start, letting the solver choose each play at the
+/// given depth (picking at random among equally good plays). Emits each game.
+pub fn run_play<R: Rng>(stack: &Stack, rng: &mut R, out: &mut dyn Sink,
+                        depth: Count, verbose: bool) -> UtttResult<Game> {
+    out.heading(0, "Solver vs Solver")?;
+    let mut game = EMPTY_GAME;
+    while !game.is_over() {
+        let (solutions, elapsed) = solve(stack, &game, depth)?;
+        let plays = solutions.iter()
+            .filter_map(|sol| sol.opt_play)
+            .collect::<Vec<Play>>();
+        let play = match rng.choose(&plays) {
+            Some(&play) => play,
+            None => random_valid_play(&game, rng).ok_or_else(|| {
+                UtttError::invariant("E4201", "ongoing game without plays")
+            })?,
+        };
+        let label = format!("n={}", game.board.play_count());
+        if verbose {
+            emit_solutions(stack, out, 1, &label, &game, depth, &solutions,
+                           elapsed)?;
+        }
+        game.play(play);
+        out.game(1, &label, &game)?;
+    }
+    out.value("Result", result_str(game.winner()).trim())?;
+    out.separator()?;
+    Ok(game)
+}
+
+/// Plays a round-robin tournament between engines: `games` games per pair,
+/// from `opening` random plays (see the 'tournament' module). Emits each game
+/// if `verbose`, then the score of each pairing and the standings.
+pub fn run_tournament<R: Rng>(engines: &[Engine], games: u16, stack: &Stack,
+                              rng: &mut R, out: &mut dyn Sink,
+                              opening: usize, verbose: bool)
+                              -> UtttResult<Vec<GameRecord>> {
+    out.heading(0, "Tournament")?;
+    for (i, engine) in engines.iter().enumerate() {
+        out.value(&format!("engine {}", i), &engine.name())?;
+    }
+    out.separator()?;
+    let openings = openings(rng, (games as usize + 1) / 2, opening);
+    let mut records: Vec<GameRecord> = vec![];
+    for (n, &(x, o, k)) in pairings(engines.len(), games as usize).iter()
+        .enumerate() {
+        if stack.is_stopping() { break; }
+        let record = play_game(stack, engines, x, o, &openings[k], rng)?;
+        if verbose {
+            let label = format!("{} (X) vs {} (O)", engines[x].name(),
+                                engines[o].name());
+            out.heading(1, &format!("Game #{}: {}", n + 1, label))?;
+            out.game(n as u32 + 1, &label, &record.game())?;
+        }
+        records.push(record);
+    }
+    out.heading(1, "Pairings")?;
+    for i in 0 .. engines.len() {
+        for j in 0 .. engines.len() {
+            if i == j { continue; }
+            let scores = records.iter()
+                .filter(|r| (r.x == i && r.o == j) || (r.x == j && r.o == i))
+                .filter_map(|r| r.score(i))
+                .collect::<Vec<f64>>();
+            let count = |score: f64| {
+                scores.iter().filter(|&&s| s == score).count() as i64
+            };
+            out.row(&[("engine", i as i64), ("opponent", j as i64),
+                      ("games", scores.len() as i64), ("wins", count(1.0)),
+                      ("ties", count(0.5)), ("losses", count(0.0))])?;
+        }
+    }
+    out.separator()?;
+    out.heading(1, "Standings")?;
+    for s in standings(&records, engines.len()).iter() {
+        out.row(&[("engine", s.engine as i64), ("games", s.games as i64),
+                  ("wins", s.wins as i64), ("ties", s.ties as i64),
+                  ("losses", s.losses as i64), ("elo", s.elo.round() as i64),
+                  ("error", s.error.round() as i64)])?;
+    }
+    out.separator()?;
+    Ok(records)
+}
+
+/// Writes training examples to a new file at `path`: for each trial, the
+/// ongoing games among the last `back` plays of a random game, solved at
+/// `depth` (see the 'training' module), in the `data` format. With `augment`,
+/// writes every symmetry of each example.
+pub fn run_training<R: Rng>(config: &RunConfig, stack: &Stack, rng: &mut R,
+                            manifest: &mut Manifest, out: &mut dyn Sink,
+                            path: &str) -> UtttResult<u64> {
+    out.heading(0, "Training Data")?;
+    let file = File::create(path).map_err(|e| UtttError::from_io("E4203", e))?;
+    let mut writer = TrainingWriter::new(BufWriter::new(file), config.data)?;
+    let symmetries = if config.augment { SYMMETRIES } else { 1 };
+    let mut positions: u64 = 0;
+    for trial in 1 .. (config.trials as u32 + 1) {
+        if stack.is_stopping() { break; }
+        let games = random_games(rng);
+        manifest.record_trial(trial, &games)?;
+        for (_, game) in training_games(&games, config.back) {
+            let (solutions, _) = solve(stack, game, config.depth)?;
+            let example = Example::new(game, &solutions);
+            for symmetry in 0 .. symmetries {
+                writer.write(&example.transformed(symmetry))?;
             }
+            positions += 1;
         }
     }
+    let count = writer.finish()?;
+    out.value("positions", &positions.to_string())?;
+    out.value("examples", &count.to_string())?;
+    out.value("file", path)?;
+    out.separator()?;
+    Ok(count)
+}
+
+/// Solves the positions of a trial that `run_training` turns into examples,
+/// without writing them; for replaying a training run.
+pub fn training_trial(config: &RunConfig, stack: &Stack, out: &mut dyn Sink,
+                      trial: u32, games: &LinkedList<Game>) -> UtttResult<()> {
+    let (depth, back, verbose) = (config.depth, config.back, config.verbose);
+    if verbose { out.heading(1, &format!("Trial #{}", trial))?; }
+    for (i, game) in training_games(games, back) {
+        let label = &format!("Game N-{}", i);
+        if verbose {
+            out.heading(2, label)?;
+            out.game(trial, label, game)?;
+        }
+        let (solutions, elapsed) = solve(stack, game, depth)?;
+        if verbose {
+            emit_solutions(stack, out, trial, label, game, depth, &solutions,
+                           elapsed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates (or migrates) the schema, and emits the resulting version.
+pub fn run_db_init(conn: &Connection, config: &SchemaConfig,
+                   out: &mut dyn Sink) -> UtttResult<()> {
+    out.heading(0, "Database Init")?;
+    let version = db_migrate(conn, config)?;
+    out.value("schema version", &version.to_string())?;
+    out.separator()
+}
+
+/// Emits the schema version and the number of decided and unknown rows for
+/// each play count and number of turns.
+pub fn run_db_stats(conn: &Connection, out: &mut dyn Sink) -> UtttResult<()> {
+    out.heading(0, "Database Stats")?;
+    out.value("schema version", &db_schema_version(conn)?.to_string())?;
+    out.separator()?;
+    let (mut decided, mut unknown) = (0, 0);
+    for c in db_counts(conn, &SolutionQuery::new())?.iter() {
+        out.row(&[("plays", c.plays as i64), ("turns", c.sol_turns as i64),
+                  ("decided", c.decided as i64),
+                  ("unknown", c.unknown as i64)])?;
+        decided += c.decided;
+        unknown += c.unknown;
+    }
+    out.separator()?;
+    out.value("total decided", &decided.to_string())?;
+    out.value("total unknown", &unknown.to_string())?;
+    out.separator()
+}
+
+/// Writes every stored solution to an export file, and emits the number of
+/// rows written.
+pub fn run_db_export(conn: &Connection, path: &str, format: ExportFormat,
+                     out: &mut dyn Sink) -> UtttResult<()> {
+    out.heading(0, "Database Export")?;
+    let rows = export_from_db(conn, path, format)?;
+    out.value("rows exported", &rows.to_string())?;
+    out.value("file", path)?;
+    out.separator()
+}
+
+/// Writes the 'solutions' table to a solution file, for the mmap stack, and
+/// emits the number of records written.
+pub fn run_db_freeze(conn: &Connection, path: &str, out: &mut dyn Sink)
+                     -> UtttResult<()> {
+    out.heading(0, "Database Freeze")?;
+    let records = file_write_from_db(path, conn)?;
+    out.value("records written", &records.to_string())?;
+    out.value("file", path)?;
+    out.separator()
+}
+
+/// Loads a binary export file, and emits the number of rows read and written.
+/// Nothing is written unless the whole file checks out.
+pub fn run_db_import(conn: &Connection, path: &str, out: &mut dyn Sink)
+                     -> UtttResult<()> {
+    out.heading(0, "Database Import")?;
+    let (read, written) = import_into_db(conn, path)?;
+    out.value("rows read", &read.to_string())?;
+    out.value("rows written", &written.to_string())?;
+    out.separator()
+}
+
+/// Checks the 'solutions' table and emits each problem found (deleting the
+/// rows with problems if `config.repair` is set).
+pub fn run_verify(conn: &Connection, config: &VerifyConfig,
+                  out: &mut dyn Sink) -> UtttResult<()> {
+    out.heading(0, "Verify")?;
+    let report = verify_db(conn, config)?;
+    for finding in report.findings.iter() {
+        out.value(&format!("{:?}", finding.key),
+                  &format!("{:?}", finding.problem))?;
+    }
+    if !report.findings.is_empty() { out.separator()?; }
+    out.value("rows scanned", &report.scanned.to_string())?;
+    out.value("rows checked", &report.checked.to_string())?;
+    out.value("problems", &report.findings.len().to_string())?;
+    out.value("rows deleted", &report.deleted.to_string())?;
+    out.separator()
 }
 
-pub fn make_rng() -> XorShiftRng {
-    let seed = random_seed();
-    // let seed: [u32; 4] = [1950144991, 3815769152, 584337888, 1474954538];
-    SeedableRng::from_seed(seed)
+/// Returns a random number generator with the given seed, so that a run can
+/// be repeated. The seed must not be all zeros.
+pub fn seeded_rng(seed: [u32; 4]) -> UtttResult<XorShiftRng> {
+    if seed == [0; 4] {
+        return Err(UtttError::usage("E4202", "the seed is all zeros"));
+    }
+    Ok(SeedableRng::from_seed(seed))
 }
 
-fn solve(stack: &Stack, game: &Game, depth: Count) -> Vec<Solution> {
-    stack.get_and_put(game, depth)
+/// Solves a game, and returns its solutions and how long that took.
+fn solve(stack: &Stack, game: &Game, depth: Count)
+         -> UtttResult<(Vec<Solution>, Duration)> {
+    let start = Instant::now();
+    let solutions = stack.get_and_put(game, depth)?;
+    Ok((solutions, start.elapsed()))
+}
+
+/// Returns the last game of a trial.
+fn last_game(games: &LinkedList<Game>) -> UtttResult<&Game> {
+    games.back().ok_or_else(|| UtttError::invariant("E4204", "no games"))
+}
+
+/// Returns the game `back` plays before the end of a trial.
+fn game_back(games: &LinkedList<Game>, back: Count) -> UtttResult<&Game> {
+    games.iter().rev().nth(back as usize).ok_or_else(|| UtttError::usage(
+        "E4205", &format!("the game has fewer than {} plays", back)))
+}
+
+/// Returns the ongoing games among the last `back` plays of a trial, from the
+/// end, each with its number of plays back.
+fn training_games(games: &LinkedList<Game>, back: Count)
+                  -> Vec<(usize, &Game)> {
+    games.iter().rev().take(back as usize + 1).enumerate()
+        .filter(|&(_, game)| !game.is_over())
+        .collect()
+}
+
+fn emit_solutions(stack: &Stack, out: &mut dyn Sink, trial: u32, label: &str,
+                  game: &Game, depth: Count, solutions: &Vec<Solution>,
+                  elapsed: Duration) -> UtttResult<()> {
+    out.solutions(&Solved {
+        trial: trial,
+        label: label,
+        game: game,
+        depth: depth,
+        solutions: solutions,
+        elapsed: elapsed,
+        metrics: &stack.metrics(),
+    })
 }
 
 fn result_str(op: Option<Player>) -> &'static str {
@@ -174,9 +526,3 @@ fn result_str(op: Option<Player>) -> &'static str {
         None => "  tie ",
     }
 }
-
-fn p_cache(stack: &Stack) {
-    let device = stack.devices.get(0).expect("E99XX");
-    println!("SSD RAM cache_1 size : {}", SSD::cache_1_len(&device));
-    println!("SSD RAM cache_2 size : {}\n", SSD::cache_2_len(&device));
-}
@@ -0,0 +1,623 @@
+/// HTTP API.
+///
+/// A small JSON API over HTTP/1.1, for tools that query the solver. The
+/// 'uttt-server' binary (built with the "server" feature) serves it on a local
+/// address, with no other services needed. Requests are handled one at a
+/// time, with one `Stack`, so that solutions found for one request are cached
+/// for the next.
+///
+/// A position is given by its plays from the empty game, each a row digit and
+/// a column digit, separated by spaces, commas or '+'; "44 35" is X at R4C4,
+/// then O at R3C5 (the notation of a run manifest). Locations in responses use
+/// the same notation.
+///
+/// method  path          parameters              response
+/// ------  ----          ----------              --------
+/// POST    /solve        plays, depth, time_ms   the position, its solutions
+///                                               and the best play
+/// GET     /valid-plays  plays                   the position and its valid
+///                                               plays
+/// POST    /play         plays, play             the position after the play
+/// GET     /stats        -                       request counts and device
+///                                               metrics
+///
+/// POST parameters are the fields of a JSON object in the body; GET parameters
+/// are in the query string. `depth` defaults to, and may not exceed, the
+/// server's `--depth`. With `time_ms`, the solver deepens from depth 1 until
+/// the time runs out (see `interactive::best_play`), and a timer stops it then
+/// even within a depth; the response has the deepest finished depth. Solutions
+/// are formatted as in the JSON output (see `output::json_solutions`).
+///
+/// An error is returned as `{"error": <message>, "code": <"E....">}`, with
+/// status 400 for a bad request, 404 or 405 for an unknown path or method, 503
+/// if the server is stopping, and 500 otherwise.
+
+use cli::*;
+use constants::*;
+use data::*;
+use error::*;
+use interactive::{best_play, loc_digits, parse_loc};
+use output::*;
+use signals::*;
+use solver::*;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::iter::Peekable;
+use std::net::{TcpListener, TcpStream};
+use std::str::Chars;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod tests;
+
+pub const SERVER_USAGE: &'static str = "\
+Usage: uttt-server [options]
+
+Serves the solver's HTTP API: POST /solve, GET /valid-plays, POST /play and
+GET /stats.
+
+Options:
+  --addr <a:p>     address to listen on (default: 127.0.0.1:8080)
+  --depth <n>      default and maximum solver depth (default: 16)
+  --stack <kind>   cpu, ram, ssd or mmap (default: ssd, or ram without a
+                   database)
+  --file <path>    solution file, for the mmap stack
+  --db <url>       PostgreSQL connection string (default: $UTTT_DATABASE_URL)
+  --format <f>     log format: text or json (default: text)
+  -v, --verbose    log every request
+";
+
+/// The flag that stops a solve; the stack of 'uttt-server' watches it (see
+/// `Stack::stop`). A solve's timer sets it, and so does a stop request.
+pub static SOLVE_STOP: AtomicBool = AtomicBool::new(false);
+
+/// The largest request body accepted, in bytes.
+pub const MAX_BODY: usize = 64 * 1024;
+
+/// The longest request line or header line accepted, in bytes.
+pub const MAX_LINE: usize = 8 * 1024;
+
+/// How long to wait for a client to send its request.
+const READ_TIMEOUT_MS: u64 = 5000;
+
+// == requests and responses ===================================================
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// The query string, without the '?'.
+    pub query: String,
+    pub body: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    /// A JSON value.
+    pub body: String,
+}
+
+impl Response {
+    /// Returns the response for an error; see the module documentation.
+    pub fn from_error(status: u16, e: &UtttError) -> Response {
+        Response {
+            status: status,
+            body: format!("{{\"error\":{},\"code\":\"{}\"}}",
+                          json_str(&e.to_string()), e.code()),
+        }
+    }
+
+    /// Writes the response, and the headers for it.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> UtttResult<()> {
+        write!(w, "HTTP/1.1 {} {}\r\n\
+                   Content-Type: application/json\r\n\
+                   Content-Length: {}\r\n\
+                   Connection: close\r\n\r\n{}",
+               self.status, reason(self.status), self.body.len(), self.body)
+            .and_then(|_| w.flush())
+            .map_err(|e| UtttError::from_io("E5001", e))
+    }
+}
+
+/// Reads a request: the request line, the headers (of which only
+/// Content-Length is used) and the body.
+pub fn read_request<R: BufRead>(r: &mut R) -> UtttResult<Request> {
+    let line = read_line(r)?;
+    let (method, target) = match line.split_whitespace()
+        .collect::<Vec<&str>>().as_slice() {
+        &[method, target, version] if version.starts_with("HTTP/1.") =>
+            (method.to_string(), target.to_string()),
+        _ => return Err(bad_request("E5002", "malformed request line")),
+    };
+    let mut length = 0;
+    loop {
+        let line = read_line(r)?;
+        if line.is_empty() { break; }
+        let (name, value) = match line.find(':') {
+            Some(i) => (&line[.. i], line[i + 1 ..].trim()),
+            None => return Err(bad_request("E5003", "malformed header")),
+        };
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            length = value.parse::<usize>().map_err(|_| {
+                bad_request("E5004", "malformed Content-Length")
+            })?;
+        }
+    }
+    if length > MAX_BODY {
+        return Err(bad_request("E5005", &format!(
+            "the body is longer than {} bytes", MAX_BODY)));
+    }
+    let mut body = vec![0; length];
+    r.read_exact(&mut body).map_err(|e| UtttError::from_io("E5006", e))?;
+    let body = String::from_utf8(body)
+        .map_err(|_| bad_request("E5007", "the body is not UTF-8"))?;
+    let (path, query) = match target.find('?') {
+        Some(i) => (target[.. i].to_string(), target[i + 1 ..].to_string()),
+        None => (target, String::new()),
+    };
+    Ok(Request { method: method, path: path, query: query, body: body })
+}
+
+/// Reads a line, without its line ending. Reads no more than `MAX_LINE`
+/// bytes (and the line ending).
+fn read_line<R: BufRead>(r: &mut R) -> UtttResult<String> {
+    let mut line = String::new();
+    r.take(MAX_LINE as u64 + 2).read_line(&mut line)
+        .map_err(|e| UtttError::from_io("E5008", e))?;
+    let trimmed = line.trim_end_matches(|c| c == '\r' || c == '\n');
+    if trimmed.len() > MAX_LINE {
+        return Err(bad_request("E5025", &format!(
+            "a line is longer than {} bytes", MAX_LINE)));
+    }
+    Ok(trimmed.to_string())
+}
+
+// == the API ==================================================================
+
+/// Handles requests with a stack; see the module documentation.
+pub struct Api<'a> {
+    stack: &'a Stack,
+    /// The stack's stop flag.
+    stop: &'static AtomicBool,
+    /// The default and maximum depth of a solve.
+    depth: Count,
+    requests: u64,
+    solves: u64,
+}
+
+impl<'a> Api<'a> {
+    /// Returns an API over a stack. The stack needs a stop flag, for the
+    /// timer of a solve; it should not be the `signals::STOP` flag, which
+    /// stops the server (see `serve`).
+    pub fn new(stack: &'a Stack, depth: Count) -> UtttResult<Api<'a>> {
+        let stop = stack.stop.ok_or_else(|| UtttError::invariant(
+            "E5026", "the server's stack has no stop flag"))?;
+        Ok(Api { stack: stack, stop: stop, depth: depth, requests: 0,
+                 solves: 0 })
+    }
+
+    pub fn handle(&mut self, request: &Request) -> Response {
+        self.requests += 1;
+        let result = match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/solve") =>
+                parse_body(&request.body).and_then(|p| self.solve(&p)),
+            ("GET", "/valid-plays") =>
+                valid_plays(&parse_query(&request.query)),
+            ("POST", "/play") =>
+                parse_body(&request.body).and_then(|p| play(&p)),
+            ("GET", "/stats") => Ok(self.stats()),
+            (_, "/solve") | (_, "/valid-plays") | (_, "/play") |
+            (_, "/stats") => return Response::from_error(405, &bad_request(
+                "E5010", &format!("{} {} is not allowed", request.method,
+                                  request.path))),
+            _ => return Response::from_error(404, &bad_request(
+                "E5011", &format!("no such path '{}'", request.path))),
+        };
+        match result {
+            Ok(body) => Response { status: 200, body: body },
+            Err(e) => {
+                let status = match e {
+                    UtttError::Usage { .. } => 400,
+                    UtttError::Interrupted { .. } => 503,
+                    _ => 500,
+                };
+                Response::from_error(status, &e)
+            },
+        }
+    }
+
+    fn solve(&mut self, params: &Params) -> UtttResult<String> {
+        let (plays, game) = position(params)?;
+        if game.is_over() {
+            return Err(bad_request("E5012", "the game is over"));
+        }
+        let depth = number_param(params, "depth")?
+            .unwrap_or(self.depth as u64);
+        if depth > self.depth as u64 {
+            return Err(bad_request("E5013", &format!(
+                "the depth may not exceed {}", self.depth)));
+        }
+        if depth < 1 {
+            return Err(bad_request("E5027", "the depth must be at least 1"));
+        }
+        let budget = number_param(params, "time_ms")?
+            .map(Duration::from_millis);
+        let start = Instant::now();
+        let timer = start_timer(self.stop, budget);
+        let result = best_play(self.stack, &game, depth as Count, budget);
+        // This clears the flag if the timer set it, for the next request.
+        timer.finish();
+        let reply = result?;
+        self.solves += 1;
+        Ok(format!("{{{},\"depth\":{},\"elapsed_ms\":{},\"best_play\":{},\
+                    \"solutions\":{}}}",
+                   position_fields(&plays, &game), reply.depth,
+                   millis(start.elapsed()), json_loc(Some(reply.play.loc)),
+                   json_solutions(&reply.solutions)))
+    }
+
+    fn stats(&self) -> String {
+        format!("{{\"requests\":{},\"solves\":{},\"devices\":{}}}",
+                self.requests, self.solves,
+                json_devices(&self.stack.metrics()))
+    }
+}
+
+fn valid_plays(params: &Params) -> UtttResult<String> {
+    let (plays, game) = position(params)?;
+    let valid = game.valid_plays().iter()
+        .map(|play| json_loc(Some(play.loc)))
+        .collect::<Vec<String>>();
+    Ok(format!("{{{},\"valid_plays\":[{}]}}",
+               position_fields(&plays, &game), valid.join(",")))
+}
+
+fn play(params: &Params) -> UtttResult<String> {
+    let (mut plays, mut game) = position(params)?;
+    let text = text_param(params, "play")?
+        .ok_or_else(|| bad_request("E5014", "'play' is missing"))?;
+    let loc = parse_loc(&text).ok_or_else(|| bad_request(
+        "E5015", &format!("'{}' is not a location", text)))?;
+    apply(&mut game, loc, &text)?;
+    plays.push(loc_digits(loc));
+    Ok(format!("{{{}}}", position_fields(&plays, &game)))
+}
+
+// == positions ================================================================
+
+/// Returns the plays of the 'plays' parameter (none if it is missing), each as
+/// a row digit and a column digit, and the game after them.
+fn position(params: &Params) -> UtttResult<(Vec<String>, Game)> {
+    let text = text_param(params, "plays")?.unwrap_or_default();
+    let mut plays: Vec<String> = vec![];
+    let mut game = EMPTY_GAME;
+    for play in text.split(|c: char| c.is_whitespace() || c == ',' ||
+                           c == '+').filter(|play| !play.is_empty()) {
+        let loc = parse_loc(play).ok_or_else(|| bad_request(
+            "E5016", &format!("'{}' is not a play", play)))?;
+        apply(&mut game, loc, play)?;
+        plays.push(loc_digits(loc));
+    }
+    Ok((plays, game))
+}
+
+/// Plays at a location for the next player.
+fn apply(game: &mut Game, loc: Loc, text: &str) -> UtttResult<()> {
+    let player = game.next_player()
+        .ok_or_else(|| bad_request("E5017", "the game is over"))?;
+    if !game.play(Play { loc: loc, player: player }) {
+        return Err(bad_request("E5018", &format!(
+            "'{}' is not a valid play", text)));
+    }
+    Ok(())
+}
+
+/// Returns the JSON fields (without braces) of a position: its plays, its
+/// board (see `output::game_notation`), the next player and the winner.
+fn position_fields(plays: &[String], game: &Game) -> String {
+    let player = |opt_player: Option<Player>| json_opt(
+        opt_player.map(|p| json_str(&format!("{:?}", p))));
+    format!("\"plays\":{},\"game\":\"{}\",\"next\":{},\"winner\":{},\
+             \"over\":{}",
+            json_str(&plays.join(" ")), game_notation(game),
+            player(game.next_player()), player(game.winner()), game.is_over())
+}
+
+// == parameters ===============================================================
+
+/// A JSON value, as far as parameters need: no arrays or nested objects.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+}
+
+/// Named parameters, from a query string or a JSON object.
+pub type Params = Vec<(String, JsonValue)>;
+
+/// Parses a body: a JSON object whose values are strings, numbers, booleans
+/// or null. An empty body has no parameters.
+pub fn parse_body(body: &str) -> UtttResult<Params> {
+    if body.trim().is_empty() { return Ok(vec![]); }
+    let mut parser = Parser { chars: body.chars().peekable() };
+    parser.expect('{')?;
+    let mut params: Params = vec![];
+    if parser.peek() == Some('}') {
+        parser.chars.next();
+    } else {
+        loop {
+            let name = parser.string()?;
+            parser.expect(':')?;
+            let value = parser.value()?;
+            params.push((name, value));
+            match parser.next() {
+                Some(',') => {},
+                Some('}') => break,
+                _ => return Err(bad_json("expected ',' or '}'")),
+            }
+        }
+    }
+    match parser.next() {
+        None => Ok(params),
+        Some(_) => Err(bad_json("unexpected text after the object")),
+    }
+}
+
+/// Parses a query string: '&'-separated "name=value" pairs, with '+' and
+/// "%XX" escapes. Every value is a string.
+pub fn parse_query(query: &str) -> Params {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(i) => (decode(&pair[.. i]),
+                        JsonValue::Str(decode(&pair[i + 1 ..]))),
+            None => (decode(pair), JsonValue::Str(String::new())),
+        })
+        .collect()
+}
+
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = s.get(i + 1 .. i + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            },
+            (b'+', _) => {
+                out.push(b' ');
+                i += 1;
+            },
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn param<'a>(params: &'a Params, name: &str) -> Option<&'a JsonValue> {
+    params.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref value)| value)
+}
+
+/// Returns a string parameter, or None if it is missing or null.
+fn text_param(params: &Params, name: &str) -> UtttResult<Option<String>> {
+    match param(params, name) {
+        None | Some(&JsonValue::Null) => Ok(None),
+        Some(&JsonValue::Str(ref s)) => Ok(Some(s.clone())),
+        Some(_) => Err(bad_request(
+            "E5019", &format!("'{}' must be a string", name))),
+    }
+}
+
+/// Returns a whole-number parameter (a number, or a string of digits), or
+/// None if it is missing or null.
+fn number_param(params: &Params, name: &str) -> UtttResult<Option<u64>> {
+    let err = || bad_request(
+        "E5020", &format!("'{}' must be a whole number", name));
+    match param(params, name) {
+        None | Some(&JsonValue::Null) => Ok(None),
+        Some(&JsonValue::Number(n)) if n >= 0.0 && n.fract() == 0.0 &&
+            n <= u32::max_value() as f64 => Ok(Some(n as u64)),
+        Some(&JsonValue::Str(ref s)) =>
+            s.trim().parse::<u64>().map(Some).map_err(|_| err()),
+        Some(_) => Err(err()),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns the next character that is not whitespace, without consuming
+    /// it.
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.peek().map_or(false, |c| c.is_whitespace()) {
+            self.chars.next();
+        }
+        self.chars.peek().cloned()
+    }
+
+    /// Consumes and returns the next character that is not whitespace.
+    fn next(&mut self) -> Option<char> {
+        self.peek();
+        self.chars.next()
+    }
+
+    fn expect(&mut self, expected: char) -> UtttResult<()> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(bad_json(&format!("expected '{}'", expected))),
+        }
+    }
+
+    fn string(&mut self) -> UtttResult<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err(bad_json("unterminated string")),
+                Some('"') => return Ok(s),
+                Some('\\') => s.push(self.escape()?),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> UtttResult<char> {
+        match self.chars.next() {
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('b') => Ok('\x08'),
+            Some('f') => Ok('\x0c'),
+            Some(c @ '"') | Some(c @ '\\') | Some(c @ '/') => Ok(c),
+            Some('u') => {
+                let hex = self.chars.by_ref().take(4).collect::<String>();
+                u32::from_str_radix(&hex, 16).ok()
+                    .and_then(::std::char::from_u32)
+                    .ok_or_else(|| bad_json("bad \\u escape"))
+            },
+            _ => Err(bad_json("bad escape")),
+        }
+    }
+
+    fn value(&mut self) -> UtttResult<JsonValue> {
+        match self.peek() {
+            Some('"') => self.string().map(JsonValue::Str),
+            Some(c) if c == '-' || c.is_digit(10) => {
+                let number = self.take_while(|c| {
+                    c.is_digit(10) || "+-.eE".contains(c)
+                });
+                number.parse::<f64>().map(JsonValue::Number)
+                    .map_err(|_| bad_json(&format!("bad number '{}'", number)))
+            },
+            Some(c) if c.is_alphabetic() => {
+                match self.take_while(char::is_alphabetic).as_str() {
+                    "null" => Ok(JsonValue::Null),
+                    "true" => Ok(JsonValue::Bool(true)),
+                    "false" => Ok(JsonValue::Bool(false)),
+                    word => Err(bad_json(&format!("unexpected '{}'", word))),
+                }
+            },
+            _ => Err(bad_json(
+                "expected a string, a number, a boolean or null")),
+        }
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> String {
+        let mut s = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !f(c) { break; }
+            s.push(c);
+            self.chars.next();
+        }
+        s
+    }
+}
+
+// == serving ==================================================================
+
+/// Returns the stack for the server: the one chosen by `--stack`, except that
+/// the ssd stack falls back to the ram stack if no database is configured or
+/// it cannot be reached.
+pub fn server_stack(opts: &Options, out: &mut dyn Sink) -> UtttResult<Stack> {
+    match opts.open_stack() {
+        Err(e) => match e {
+            UtttError::Usage { code: "E4101", .. } |
+            UtttError::Connection { .. } if opts.stack == StackKind::Ssd => {
+                out.value("notice", &format!(
+                    "{}; caching solutions in RAM instead", e))?;
+                Ok(RAM_CPU_Stack::new())
+            },
+            _ => Err(e),
+        },
+        stack => stack,
+    }
+}
+
+/// Listens on an address.
+pub fn bind(addr: &str) -> UtttResult<TcpListener> {
+    TcpListener::bind(addr).map_err(|e| UtttError::from_io("E5021", e))
+}
+
+/// Serves requests, one at a time, until a stop is requested (see the
+/// 'signals' module). A failure to talk to one client is logged, and does not
+/// stop the server.
+pub fn serve(api: &mut Api, listener: &TcpListener, out: &mut dyn Sink,
+             verbose: bool) -> UtttResult<()> {
+    listener.set_nonblocking(true)
+        .map_err(|e| UtttError::from_io("E5022", e))?;
+    while !stop_requested() {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                let start = Instant::now();
+                match respond(api, stream) {
+                    Ok(summary) => if verbose {
+                        out.value("request", &format!(
+                            "{} {} {:.1}ms", peer, summary,
+                            millis(start.elapsed())))?;
+                    },
+                    Err(e) => out.value("client error",
+                                        &format!("{} {}", peer, e))?,
+                }
+            },
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock =>
+                thread::sleep(Duration::from_millis(POLL_MS)),
+            Err(e) => return Err(UtttError::from_io("E5023", e)),
+        }
+    }
+    Ok(())
+}
+
+/// Reads a request from a client and writes the response. Returns a summary:
+/// the method, the path and the status.
+fn respond(api: &mut Api, stream: TcpStream) -> UtttResult<String> {
+    stream.set_nonblocking(false)
+        .and_then(|_| stream.set_read_timeout(
+            Some(Duration::from_millis(READ_TIMEOUT_MS))))
+        .map_err(|e| UtttError::from_io("E5024", e))?;
+    let (summary, response) = {
+        let mut reader = BufReader::new(&stream);
+        match read_request(&mut reader) {
+            Ok(request) => (format!("{} {}", request.method, request.path),
+                            api.handle(&request)),
+            Err(e @ UtttError::Usage { .. }) =>
+                ("-".to_string(), Response::from_error(400, &e)),
+            Err(e) => return Err(e),
+        }
+    };
+    response.write_to(&mut &stream)?;
+    Ok(format!("{} {}", summary, response.status))
+}
+
+// == helpers ==================================================================
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn bad_request(code: &'static str, message: &str) -> UtttError {
+    UtttError::usage(code, message)
+}
+
+fn bad_json(message: &str) -> UtttError {
+    UtttError::usage("E5009", &format!("bad JSON body: {}", message))
+}
@@ -0,0 +1,135 @@
+use server::*;
+use std::io::Cursor;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+
+// A flag per test, since tests run in parallel.
+static STOP_1: AtomicBool = AtomicBool::new(false);
+static STOP_2: AtomicBool = AtomicBool::new(false);
+static STOP_3: AtomicBool = AtomicBool::new(false);
+
+fn request(method: &str, target: &str, body: &str) -> Request {
+    let text = format!("{} {} HTTP/1.1\r\nHost: localhost\r\n\
+                        Content-Length: {}\r\n\r\n{}",
+                       method, target, body.len(), body);
+    read_request(&mut Cursor::new(text.into_bytes())).expect("E5031")
+}
+
+#[test]
+fn test_read_request() {
+    let req = request("GET", "/valid-plays?plays=44+35", "");
+    assert_eq!((req.method.as_str(), req.path.as_str(), req.query.as_str()),
+               ("GET", "/valid-plays", "plays=44+35"));
+    let req = request("POST", "/solve", "{\"depth\":2}");
+    assert_eq!(req.body, "{\"depth\":2}");
+    let mut bad = Cursor::new(b"GET /\r\n\r\n".to_vec());
+    match read_request(&mut bad) {
+        Err(UtttError::Usage { code: "E5002", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_body_and_query() {
+    assert_eq!(parse_body("  "), Ok(vec![]));
+    assert_eq!(parse_body(" { \"plays\" : \"44 \\u0033\\\"\", \"depth\": 6, \
+                           \"x\": null, \"y\": false } "),
+               Ok(vec![("plays".to_string(), JsonValue::Str("44 3\"".into())),
+                       ("depth".to_string(), JsonValue::Number(6.0)),
+                       ("x".to_string(), JsonValue::Null),
+                       ("y".to_string(), JsonValue::Bool(false))]));
+    for body in ["{", "{\"a\":}", "{\"a\":1,}", "{\"a\":[1]}", "{} x"].iter() {
+        assert!(parse_body(body).is_err(), "{}", body);
+    }
+    assert_eq!(parse_query("plays=44%2035+00&depth=3"),
+               vec![("plays".to_string(), JsonValue::Str("44 35 00".into())),
+                    ("depth".to_string(), JsonValue::Str("3".into()))]);
+}
+
+#[test]
+fn test_api() {
+    let mut stack = RAM_CPU_Stack::new();
+    stack.stop = Some(&STOP_1);
+    let mut api = Api::new(&stack, 3).expect("E5034");
+    let res = api.handle(&request("GET", "/valid-plays?plays=44,35", ""));
+    assert_eq!(res.status, 200);
+    assert!(res.body.contains("\"next\":\"X\""), "{}", res.body);
+    assert!(res.body.ends_with("\"valid_plays\":[\"06\",\"07\",\"08\",\
+                                \"16\",\"17\",\"18\",\"26\",\"27\",\"28\"]}"),
+            "{}", res.body);
+    let res = api.handle(&request(
+        "POST", "/solve", "{\"plays\":\"44 35\",\"depth\":2}"));
+    assert_eq!(res.status, 200);
+    assert!(res.body.contains("\"depth\":2,"), "{}", res.body);
+    assert!(res.body.contains("\"solutions\":[{\"play\":"), "{}", res.body);
+    let res = api.handle(&request(
+        "POST", "/play", "{\"plays\":\"44\",\"play\":\"R3C5\"}"));
+    assert_eq!(res.status, 200);
+    assert!(res.body.starts_with("{\"plays\":\"44 35\","), "{}", res.body);
+    let res = api.handle(&request("GET", "/stats", ""));
+    assert!(res.body.starts_with("{\"requests\":4,\"solves\":1,"));
+    assert!(res.body.contains("\"name\":\"RAM\""), "{}", res.body);
+}
+
+#[test]
+fn test_api_errors() {
+    let mut stack = CPU_Stack::new();
+    match Api::new(&stack, 3) {
+        Err(UtttError::Invariant { code: "E5026", .. }) => {},
+        _ => panic!("E5035"),
+    }
+    stack.stop = Some(&STOP_2);
+    let mut api = Api::new(&stack, 3).expect("E5036");
+    let cases = [
+        ("POST", "/play", "{\"plays\":\"44\",\"play\":\"00\"}", 400, "E5018"),
+        ("POST", "/play", "{\"plays\":\"44\"}", 400, "E5014"),
+        ("POST", "/solve", "{\"depth\":4}", 400, "E5013"),
+        ("POST", "/solve", "{\"depth\":0}", 400, "E5027"),
+        ("POST", "/solve", "{\"depth\":\"deep\"}", 400, "E5020"),
+        ("POST", "/solve", "{\"plays\":449}", 400, "E5019"),
+        ("GET", "/valid-plays?plays=4", "", 400, "E5016"),
+        ("GET", "/solve", "", 405, "E5010"),
+        ("GET", "/", "", 404, "E5011"),
+    ];
+    for &(method, target, body, status, code) in cases.iter() {
+        let res = api.handle(&request(method, target, body));
+        assert_eq!(res.status, status, "{} {}", method, target);
+        assert!(res.body.ends_with(&format!("\"code\":\"{}\"}}", code)),
+                "{}", res.body);
+    }
+}
+
+#[test]
+fn test_response_write_to() {
+    let mut bytes: Vec<u8> = Vec::new();
+    Response { status: 200, body: "{}".to_string() }.write_to(&mut bytes)
+        .expect("E5032");
+    let text = String::from_utf8(bytes).expect("E5033");
+    assert!(text.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(text.contains("Content-Length: 2\r\n"));
+    assert!(text.ends_with("\r\n\r\n{}"));
+}
+
+#[test]
+fn test_api_solve_deadline() {
+    // Too deep to finish in time: the timer stops the search within a depth.
+    let mut stack = CPU_Stack::new();
+    stack.stop = Some(&STOP_3);
+    let mut api = Api::new(&stack, 81).expect("E5037");
+    let start = Instant::now();
+    let res = api.handle(&request(
+        "POST", "/solve", "{\"plays\":\"44\",\"time_ms\":100}"));
+    assert_eq!(res.status, 200, "{}", res.body);
+    assert!(start.elapsed().as_secs() < 5);
+    assert!(!stack.is_stopping());
+}
+
+#[test]
+fn test_read_request_rejects_long_lines() {
+    let text = format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+                       "x".repeat(MAX_LINE));
+    match read_request(&mut Cursor::new(text.into_bytes())) {
+        Err(UtttError::Usage { code: "E5025", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+}
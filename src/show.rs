@@ -55,34 +55,55 @@ impl Show for Outcome {
 
 impl Show for Game {
     fn show(&self) -> String {
-        format!(
-            "{}\n{}",
-            self.board.show(),
-            match self.state() {
-                GameState::Won(_) => {
-                    format!("    n={:2}      last={}:{}      {} won",
-                            self.board.play_count(),
-                            self.last_player().show(),
-                            self.last_loc.show(),
-                            self.winner().show()
-                    )
-                },
-                GameState::Tied => {
-                    format!("    n={:2}      last={}:{}          tie",
-                            self.board.play_count(),
-                            self.last_player().show(),
-                            self.last_loc.show()
-                    )
-                },
-                GameState::Ongoing => {
-                    format!("    n={:2}      last={}:{}      ongoing",
-                            self.board.play_count(),
-                            self.last_player().show(),
-                            self.last_loc.show()
-                    )
-                },
+        format!("{}\n{}", self.board.show(), show_status(self))
+    }
+}
+
+/// Shows a game like `Show for Game`, but marks the empty slots that the next
+/// player may play in with a dot, to highlight the legal sub-boards.
+pub fn show_playable(game: &Game) -> String {
+    let locs = game.valid_plays().iter()
+        .map(|play| play.loc)
+        .collect::<Vec<Loc>>();
+    let mut rows: Vec<String> = Vec::with_capacity(9);
+    for row in 0 .. 9 {
+        let cells = (0 .. 9).map(|col| {
+            let loc = Loc::from_row_col(RI::from_u8(row), CI::from_u8(col));
+            match game.board.player_at_loc(loc) {
+                Some(player) => player.show(),
+                None if locs.contains(&loc) => "·".to_string(),
+                None => " ".to_string(),
             }
-        )
+        }).collect::<Vec<String>>();
+        rows.push(show_row(&cells));
+    }
+    format!("{}\n{}", show_rows(&rows), show_status(game))
+}
+
+fn show_status(game: &Game) -> String {
+    match game.state() {
+        GameState::Won(_) => {
+            format!("    n={:2}      last={}:{}      {} won",
+                    game.board.play_count(),
+                    game.last_player().show(),
+                    game.last_loc.show(),
+                    game.winner().show()
+            )
+        },
+        GameState::Tied => {
+            format!("    n={:2}      last={}:{}          tie",
+                    game.board.play_count(),
+                    game.last_player().show(),
+                    game.last_loc.show()
+            )
+        },
+        GameState::Ongoing => {
+            format!("    n={:2}      last={}:{}      ongoing",
+                    game.board.play_count(),
+                    game.last_player().show(),
+                    game.last_loc.show()
+            )
+        },
     }
 }
 
@@ -91,39 +112,45 @@ impl Show for Game {
 impl Show for Board {
     fn show(&self) -> String {
         let s: [[Slot; 9]; 9] = reorder_slots(self.slots_9x9());
-        let cols = "     0   1   2    3   4   5    6   7   8     ";
-        let line = "    ───┼───┼───  ───┼───┼───  ───┼───┼───    ";
-        format!(
-            "{}\n\
-             \n\
-             0   {}   0\n\
-             {}\n\
-             1   {}   1\n\
-             {}\n\
-             2   {}   2\n\
-             \n\
-             3   {}   3\n\
-             {}\n\
-             4   {}   4\n\
-             {}\n\
-             5   {}   5\n\
-             \n\
-             6   {}   6\n\
-             {}\n\
-             7   {}   7\n\
-             {}\n\
-             8   {}   8\n\
-             \n\
-             {}",
-            cols,
-            s[0].show(), line, s[1].show(), line, s[2].show(),
-            s[3].show(), line, s[4].show(), line, s[5].show(),
-            s[6].show(), line, s[7].show(), line, s[8].show(),
-            cols
-        )
+        let rows = s.iter().map(|row| row.show()).collect::<Vec<String>>();
+        show_rows(&rows)
     }
 }
 
+/// Frames 9 shown rows (see `show_row`) with row and column numbers.
+fn show_rows(rows: &[String]) -> String {
+    let cols = "     0   1   2    3   4   5    6   7   8     ";
+    let line = "    ───┼───┼───  ───┼───┼───  ───┼───┼───    ";
+    format!(
+        "{}\n\
+         \n\
+         0   {}   0\n\
+         {}\n\
+         1   {}   1\n\
+         {}\n\
+         2   {}   2\n\
+         \n\
+         3   {}   3\n\
+         {}\n\
+         4   {}   4\n\
+         {}\n\
+         5   {}   5\n\
+         \n\
+         6   {}   6\n\
+         {}\n\
+         7   {}   7\n\
+         {}\n\
+         8   {}   8\n\
+         \n\
+         {}",
+        cols,
+        rows[0], line, rows[1], line, rows[2],
+        rows[3], line, rows[4], line, rows[5],
+        rows[6], line, rows[7], line, rows[8],
+        cols
+    )
+}
+
 /// Reorders the 9x9 slots in a useful order for displaying:
 ///
 /// In case it is useful, here is the 'flattened' output order:
@@ -266,15 +293,22 @@ impl Show for SLoc {
 
 impl Show for [Slot; 9] {
     fn show(&self) -> String {
-        format!(
-            " {} │ {} │ {}    {} │ {} │ {}    {} │ {} │ {} ",
-            self[0].show(), self[1].show(), self[2].show(),
-            self[3].show(), self[4].show(), self[5].show(),
-            self[6].show(), self[7].show(), self[8].show(),
-        )
+        let cells = self.iter().map(|slot| slot.show())
+            .collect::<Vec<String>>();
+        show_row(&cells)
     }
 }
 
+/// Shows a row of 9 shown slots, grouped by sub-board.
+fn show_row(cells: &[String]) -> String {
+    format!(
+        " {} │ {} │ {}    {} │ {} │ {}    {} │ {} │ {} ",
+        cells[0], cells[1], cells[2],
+        cells[3], cells[4], cells[5],
+        cells[6], cells[7], cells[8],
+    )
+}
+
 impl Show for [Slot; 3] {
     fn show(&self) -> String {
         format!(" {} │ {} │ {} ",
@@ -0,0 +1,117 @@
+/// Signals.
+///
+/// SIGINT (Ctrl-C) and SIGTERM set the `STOP` flag instead of killing the
+/// program. A stack that watches the flag (see `Stack::stop`) stops between
+/// nodes, and the runners stop between trials, so that the program can flush
+/// its caches before exiting. A second signal exits at once. Either way, the
+/// exit status is 128 plus the number of the (first) signal, as a shell
+/// reports a process killed by it; see `stop_exit_status`.
+///
+/// A search that watches a flag of its own (see `Stack::stop`) is ended by a
+/// `StopTimer`, which sets that flag when its time runs out, or as soon as a
+/// stop is requested.
+
+use error::*;
+use libc;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Set when SIGINT or SIGTERM is received.
+pub static STOP: AtomicBool = AtomicBool::new(false);
+
+/// The number of the first signal received, or 0 if none was.
+pub static STOP_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// How often (in ms) a `StopTimer` checks for a stop request.
+pub const POLL_MS: u64 = 50;
+
+/// Installs the handlers for SIGINT and SIGTERM.
+pub fn install_stop_handlers() -> UtttResult<()> {
+    for &signal in [libc::SIGINT, libc::SIGTERM].iter() {
+        let handler = handle_stop as extern "C" fn(libc::c_int);
+        let previous = unsafe {
+            libc::signal(signal, handler as libc::sighandler_t)
+        };
+        if previous == libc::SIG_ERR {
+            return Err(UtttError::invariant(
+                "E4301", &format!("cannot handle signal {}", signal)));
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if a stop was requested.
+pub fn stop_requested() -> bool {
+    STOP.load(Ordering::SeqCst)
+}
+
+/// Returns the exit status after a stop: 130 after SIGINT (Ctrl-C), 143
+/// after SIGTERM. If the flag was set without a signal, SIGINT is assumed.
+pub fn stop_exit_status() -> i32 {
+    match STOP_SIGNAL.load(Ordering::SeqCst) {
+        0 => 128 + libc::SIGINT,
+        signal => 128 + signal,
+    }
+}
+
+/// Only does what is safe in a signal handler: atomic operations, and
+/// `_exit`. The signal is recorded before the flag is set, so that whoever
+/// sees the flag also sees the signal.
+extern "C" fn handle_stop(signal: libc::c_int) {
+    let _ = STOP_SIGNAL.compare_exchange(0, signal, Ordering::SeqCst,
+                                         Ordering::SeqCst);
+    if STOP.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(stop_exit_status()); }
+    }
+}
+
+// == timers ===================================================================
+
+/// A thread that sets a stop flag after a time budget, or as soon as a stop
+/// is requested; see `start_timer`.
+pub struct StopTimer {
+    tx: mpsc::Sender<()>,
+    handle: thread::JoinHandle<bool>,
+    stop: &'static AtomicBool,
+}
+
+/// Starts a timer that sets `stop` after the budget (if any), or as soon as
+/// a stop is requested, unless it is finished first.
+pub fn start_timer(stop: &'static AtomicBool, budget: Option<Duration>)
+                   -> StopTimer {
+    let (tx, rx) = mpsc::channel::<()>();
+    let start = Instant::now();
+    let poll = Duration::from_millis(POLL_MS);
+    let handle = thread::spawn(move || loop {
+        let wait = match budget {
+            Some(budget) => budget.checked_sub(start.elapsed())
+                .map_or(Duration::from_millis(0), |left| left.min(poll)),
+            None => poll,
+        };
+        match rx.recv_timeout(wait) {
+            Err(RecvTimeoutError::Timeout) => {
+                if stop_requested() ||
+                    budget.map_or(false, |b| start.elapsed() >= b) {
+                    stop.store(true, Ordering::SeqCst);
+                    return true;
+                }
+            },
+            _ => return false,
+        }
+    });
+    StopTimer { tx: tx, handle: handle, stop: stop }
+}
+
+impl StopTimer {
+    /// Ends the timer. If it had set the flag, clears it (for the next
+    /// search) and returns true.
+    pub fn finish(self) -> bool {
+        let StopTimer { tx, handle, stop } = self;
+        drop(tx);
+        let fired = handle.join().unwrap_or(false);
+        if fired { stop.store(false, Ordering::SeqCst); }
+        fired
+    }
+}
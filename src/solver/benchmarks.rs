@@ -14,7 +14,7 @@ fn solve_example_1(k: Count, depth: Count) {
     }
     let game = games_iter.next_back().expect("E1401");
     let stack = CPU_Stack::new();
-    game.solve(depth, &stack);
+    game.solve(depth, &stack).expect("E1402");
 }
 
 #[bench]
@@ -0,0 +1,388 @@
+/// Codec.
+///
+/// Compact binary encodings for games and solutions, shared by every storage
+/// backend (the 'solutions' table, solution files, export files) so that they
+/// agree bit for bit:
+///
+/// * A game is encoded as three integers (`GameColumns`), and as bytes, as a
+///   fixed-width, 20-byte `GameKey` (`key_from_columns`).
+/// * A solution is encoded as a 16-bit integer (`sol_i16`).
+/// * A vector of solutions is encoded as bytes, as a 16-bit count followed by
+///   the solutions (`write_solutions`).
+///
+/// All multi-byte integers are little-endian. `CODEC_VERSION` changes
+/// whenever an encoding does; formats built on this module record it.
+///
+/// Bit mapping for the 'game_1' (BIGINT = 64 bits) column:
+///
+/// bits      width   note
+/// -------   -----   --------
+/// 63 - 48      16   SBoard 3
+/// 47 - 32      16   SBoard 2
+/// 31 - 16      16   SBoard 1
+/// 15 -  0      16   SBoard 0
+///
+/// Bit mapping for the 'game_2' (BIGINT = 64 bits) column:
+///
+/// bits      width   note
+/// -------   -----   --------
+/// 63 - 48      16   SBoard 7
+/// 47 - 32      16   SBoard 6
+/// 31 - 16      16   SBoard 5
+/// 15 -  0      16   SBoard 4
+///
+/// Bit mapping for the 'game_3' (INT = 32 bits) column:
+///
+/// bit(s)    width   note
+/// -------   -----   --------
+/// 31 - 30       2   last player (0 = O, 1 = X, 2 = none)
+/// 29 - 28       2   next player (0 = O, 1 = X, 2 = none)
+/// 27 - 26       2   rules variant (0 = standard; see `Rules`)
+///      25       1   canonical key (see `KeyFlags`)
+///      24       1   reserved (0)
+/// 23 - 16       8   last location (see Loc.encoding)
+/// 15 -  0      16   SBoard 8
+///
+/// Bits 27 - 24 were unused (and zero) in version 1, so version 1 keys decode
+/// unchanged, as standard, non-canonical keys. Version 1 decoders reject any
+/// key with these bits set, rather than misreading it.
+///
+/// Bit mapping for the 'solution' (SMALLINT = 16 bits) column:
+///
+/// bits       width   note
+/// --------   -----   ----
+/// 15 - 14       2   outcome (0 = ?; 1 = tie, 2 = O to win, 3 = X to win)
+/// 13 -  7       7   location (0 to 80; 127 for none)
+///  6 -  0       7   turns (0 to 81; 82 to 127 impossible)
+///
+/// * I would have preferred to encode the same information as the Solution
+/// struct (Option<Play> + Outcome), which when expanded is (Option<Loc> +
+/// Option<Player> + Outcome). However, I could not encode all of this in 16
+/// bits, so I dropped the Player component. This is not a problem in context,
+/// because the player can quickly calculated after retrieving the current
+/// player from the 'game_3' column.
+
+use data::*;
+use error::*;
+use solver::{Outcome, Solution};
+
+/// The version of the encodings in this module.
+pub const CODEC_VERSION: u32 = 2;
+
+/// The width, in bytes, of a `GameKey`.
+pub const GAME_KEY_WIDTH: usize = 20;
+
+/// A game encoded as bytes: game_1 (8 bytes), game_2 (8 bytes) and game_3 (4
+/// bytes) of its `GameColumns`, each little-endian.
+pub type GameKey = [u8; GAME_KEY_WIDTH];
+
+/// The rules a key was written under. Keys written under other rules must not
+/// be read as standard games, even if their bits happen to decode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Rules {
+    /// The rules of the 'data' module.
+    Standard,
+}
+
+/// The metadata stored in bits 27 - 24 of the 'game_3' column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyFlags {
+    pub rules: Rules,
+    /// True if the game was canonicalized (replaced by a chosen
+    /// representative of its symmetric games) before it was encoded. A table
+    /// holds either canonical keys or keys as played, never both.
+    pub canonical: bool,
+}
+
+impl KeyFlags {
+    /// Returns the flags for a key as played, under standard rules.
+    pub fn new() -> KeyFlags {
+        KeyFlags { rules: Rules::Standard, canonical: false }
+    }
+
+    /// Returns the flags for a canonical key, under standard rules.
+    pub fn canonical() -> KeyFlags {
+        KeyFlags { rules: Rules::Standard, canonical: true }
+    }
+}
+
+// == bytes ====================================================================
+
+/// Converts columns to a `GameKey`, as stored in solution files.
+pub fn key_from_columns(columns: GameColumns) -> GameKey {
+    let mut key: GameKey = [0; GAME_KEY_WIDTH];
+    key[0 .. 8].copy_from_slice(&columns.0.to_le_bytes());
+    key[8 .. 16].copy_from_slice(&columns.1.to_le_bytes());
+    key[16 .. 20].copy_from_slice(&columns.2.to_le_bytes());
+    key
+}
+
+/// Converts a `GameKey` back to columns.
+pub fn columns_from_key(key: &GameKey) -> GameColumns {
+    let mut game_1 = [0; 8];
+    let mut game_2 = [0; 8];
+    let mut game_3 = [0; 4];
+    game_1.copy_from_slice(&key[0 .. 8]);
+    game_2.copy_from_slice(&key[8 .. 16]);
+    game_3.copy_from_slice(&key[16 .. 20]);
+    (i64::from_le_bytes(game_1),
+     i64::from_le_bytes(game_2),
+     i32::from_le_bytes(game_3))
+}
+
+/// Appends a vector of solutions: a 16-bit count, then each solution (see
+/// `sol_i16`). Fails if there are more than 65,535 solutions.
+pub fn write_solutions(bytes: &mut Vec<u8>, sols: &Vec<Solution>)
+                       -> UtttResult<()> {
+    if sols.len() > u16::max_value() as usize {
+        return Err(UtttError::invariant("E1701", "too many solutions"));
+    }
+    bytes.extend_from_slice(&(sols.len() as u16).to_le_bytes());
+    for sol in sols.iter() {
+        bytes.extend_from_slice(&sol_i16(*sol).to_le_bytes());
+    }
+    Ok(())
+}
+
+/// Reads a vector of solutions written by `write_solutions`, for a game whose
+/// next player is `player`. Returns the solutions and the number of bytes
+/// read.
+pub fn read_solutions(bytes: &[u8], player: Option<Player>)
+                      -> UtttResult<(Vec<Solution>, usize)> {
+    let i16_at = |i: usize| -> UtttResult<i16> {
+        match bytes.get(i .. i + 2) {
+            Some(b) => Ok(i16::from_le_bytes([b[0], b[1]])),
+            None => Err(UtttError::decode("E1702", "truncated solutions")),
+        }
+    };
+    let n = i16_at(0)? as u16 as usize;
+    let sols = (0 .. n)
+        .map(|j| solution_from(i16_at(2 + 2 * j)?, player))
+        .collect::<UtttResult<Vec<Solution>>>()?;
+    Ok((sols, 2 + 2 * n))
+}
+
+// == locations ================================================================
+
+/// Returns the index of a location: `row * 9 + column`, from 0 to 80. Stored
+/// solutions use it, and so do network policies (see the 'features' module).
+pub fn policy_index(loc: Loc) -> usize {
+    loc.row().as_u8() as usize * 9 + loc.col().as_u8() as usize
+}
+
+/// Returns the location of an index, or None if it is out of range.
+pub fn loc_from_index(i: usize) -> Option<Loc> {
+    if i >= 81 { return None; }
+    Some(Loc::from_row_col(RI::from_u8((i / 9) as u8),
+                           CI::from_u8((i % 9) as u8)))
+}
+
+// == conversions (structs -> encodings) =======================================
+
+/// The 'game_1', 'game_2', 'game_3' columns of a row in the 'solutions' table.
+/// Together, they form the primary key, and they are the fields of a
+/// `GameKey`.
+pub type GameColumns = (i64, i64, i32);
+
+/// Converts a Game struct to a 3-tuple (a triple) of types (i64, i64, i32)
+/// suitable for the 'game1', 'game2', 'game3' columns in the 'solutions' table.
+/// The key is as played, under standard rules; see `game_columns_with`.
+pub fn game_columns_from(game: &Game) -> GameColumns {
+    game_columns_with(game, KeyFlags::new())
+}
+
+/// Converts a Game struct to columns, with the given flags.
+pub fn game_columns_with(game: &Game, flags: KeyFlags) -> GameColumns {
+    let game_1: u64 =
+        (game.board.sboards[3].encoding as u64) << 48 |
+        (game.board.sboards[2].encoding as u64) << 32 |
+        (game.board.sboards[1].encoding as u64) << 16 |
+        (game.board.sboards[0].encoding as u64);
+    let game_2: u64 =
+        (game.board.sboards[7].encoding as u64) << 48 |
+        (game.board.sboards[6].encoding as u64) << 32 |
+        (game.board.sboards[5].encoding as u64) << 16 |
+        (game.board.sboards[4].encoding as u64);
+    let last_player: Option<Player> = game.last_player();
+    let game_3: u32 =
+        player_u32(last_player) << 30 |
+        player_u32(game.next_player_(last_player)) << 28 |
+        flags_u32(flags) << 24 |
+        last_location_u32(game) << 16 |
+        (game.board.sboards[8].encoding as u32);
+    (game_1 as i64, game_2 as i64, game_3 as i32)
+}
+
+/// Converts a Solution to a 16-bit integer.
+pub fn sol_i16(solution: Solution) -> i16 {
+    let x_outcome: u16 = match solution.outcome {
+        Outcome::Unknown { .. } => 0,
+        Outcome::Tie { .. } => 1,
+        Outcome::Win { player: Player::O, .. } => 2,
+        Outcome::Win { player: Player::X, .. } => 3,
+    };
+    let x_location: u16 = location_u16(solution.opt_play);
+    let x_turns: u16 = solution.outcome.turns() as u16;
+    let x: u16 = x_outcome << 14 | x_location << 7 | x_turns;
+    x as i16
+}
+
+// == helpers for conversions (structs -> encodings) ===========================
+
+/// Returns either a location's encoding (8 bits) or 127
+fn last_location_u32(game: &Game) -> u32 {
+    match game.last_loc {
+        Some(loc) => loc.encoding as u32,
+        None => 0xFF,
+    }
+}
+
+/// Returns the 4 bits of the flags (bits 27 - 24 of 'game_3').
+fn flags_u32(flags: KeyFlags) -> u32 {
+    let rules: u32 = match flags.rules {
+        Rules::Standard => 0,
+    };
+    rules << 2 | (flags.canonical as u32) << 1
+}
+
+/// Returns either 0, 1, or 2 for a given optional player.
+fn player_u32(opt_player: Option<Player>) -> u32 {
+    match opt_player {
+        Some(Player::O) => 0,
+        Some(Player::X) => 1,
+        None => 2,
+    }
+}
+
+/// Returns 0x7F for no play, or else the play's policy index, so that stored
+/// solutions and network outputs agree.
+fn location_u16(opt_play: Option<Play>) -> u16 {
+    match opt_play {
+        None => 0x7F,
+        Some(play) => policy_index(play.loc) as u16,
+    }
+}
+
+// == conversions (encodings -> structs) =======================================
+
+/// Converts a 16-bit solution (see `sol_i16`) to a Solution struct. The
+/// player must be the game's next player; see `next_player_from`.
+pub fn solution_from(sol: i16, player: Option<Player>) -> UtttResult<Solution> {
+    let x: u16 = sol as u16;
+    let outcome: u8 = (x >> 14 & 3) as u8;
+    let location: u8 = (x >> 7 & 0x7F) as u8;
+    let turns: u8 = (x & 0x7F) as u8;
+    let opt_play: Option<Play> = match opt_loc_from(location)? {
+        Some(loc) => {
+            match player {
+                Some(player) => Some(Play { loc: loc, player: player }),
+                None => return Err(UtttError::decode(
+                    "E85091", "solution has a play but the game is over")),
+            }
+        },
+        None => None,
+    };
+    let outcome = match outcome {
+        0 => Outcome::Unknown { turns: turns },
+        1 => Outcome::Tie { turns: turns },
+        2 => Outcome::Win { turns: turns, player: Player::O },
+        _ => Outcome::Win { turns: turns, player: Player::X },
+    };
+    Ok(Solution { outcome: outcome, opt_play: opt_play })
+}
+
+/// Returns the next player encoded in the 'game_3' column (bits 29 - 28).
+pub fn next_player_from(game_3: i32) -> UtttResult<Option<Player>> {
+    match (game_3 as u32) >> 28 & 3 {
+        0 => Ok(Some(Player::O)),
+        1 => Ok(Some(Player::X)),
+        2 => Ok(None),
+        _ => Err(UtttError::decode("E85093", "invalid next player")),
+    }
+}
+
+/// Returns the flags encoded in the 'game_3' column (bits 27 - 24). Fails if
+/// the key was written under unknown rules, or by a newer build (one that
+/// sets the reserved bit).
+pub fn key_flags_from(game_3: i32) -> UtttResult<KeyFlags> {
+    let x = (game_3 as u32) >> 24 & 0xF;
+    let rules = match x >> 2 {
+        0 => Rules::Standard,
+        _ => return Err(UtttError::decode(
+            "E85098", "unsupported rules variant")),
+    };
+    if x & 1 != 0 {
+        return Err(UtttError::decode("E85095", "reserved bit is set"));
+    }
+    Ok(KeyFlags { rules: rules, canonical: x >> 1 & 1 == 1 })
+}
+
+/// Converts the 'game_1', 'game_2', 'game_3' columns back to a Game struct;
+/// the inverse of `game_columns_from`. Rejects a key that is not as played,
+/// under standard rules; see `game_from_columns_with`.
+pub fn game_from_columns(columns: GameColumns) -> UtttResult<Game> {
+    game_from_columns_with(columns, KeyFlags::new())
+}
+
+/// Converts columns back to a Game struct; the inverse of
+/// `game_columns_with`. Rejects a corrupt key: one where a sub-board or the
+/// last location is not a valid encoding, the flags are invalid or are not
+/// `expected`, the last location is empty, or the player bits do not match
+/// the board.
+pub fn game_from_columns_with(columns: GameColumns, expected: KeyFlags)
+                              -> UtttResult<Game> {
+    let game_1 = columns.0 as u64;
+    let game_2 = columns.1 as u64;
+    let game_3 = columns.2 as u32;
+    let mut encodings: [u16; 9] = [0; 9];
+    for i in 0 .. 4 {
+        encodings[i] = (game_1 >> (16 * i)) as u16;
+        encodings[i + 4] = (game_2 >> (16 * i)) as u16;
+    }
+    encodings[8] = game_3 as u16;
+    let mut board = Board { sboards: [SBoard { encoding: 0 }; 9] };
+    for (i, &encoding) in encodings.iter().enumerate() {
+        board.sboards[i] = sboard_from(encoding)?;
+    }
+    let last_loc: Option<Loc> = match (game_3 >> 16 & 0xFF) as u8 {
+        0xFF => None,
+        x if x >> 4 < 9 && x & 0xF < 9 => Some(Loc { encoding: x }),
+        _ => return Err(UtttError::decode("E85094", "invalid last location")),
+    };
+    if key_flags_from(columns.2)? != expected {
+        return Err(UtttError::decode("E85099", "unexpected key flags"));
+    }
+    if let Some(loc) = last_loc {
+        if board.player_at_loc(loc).is_none() {
+            return Err(UtttError::decode("E85096", "last location is empty"));
+        }
+    }
+    let game = Game { board: board, last_loc: last_loc };
+    if game_columns_with(&game, expected).2 != columns.2 {
+        return Err(UtttError::decode(
+            "E85097", "player bits do not match the board"));
+    }
+    Ok(game)
+}
+
+// == helper conversions (encodings -> structs) ================================
+
+/// Converts from an 8-bit unsigned integer to an optional location.
+fn opt_loc_from(x: u8) -> UtttResult<Option<Loc>> {
+    match x {
+        127 => Ok(None),
+        _ => loc_from_index(x as usize).map(Some).ok_or_else(|| {
+            UtttError::decode("E85101", "invalid location")
+        }),
+    }
+}
+
+/// Converts a 16-bit integer to a sub-board, checking that each of its rows is
+/// valid (see `Row::from_u8`) and that the unused high bit is clear.
+fn sboard_from(x: u16) -> UtttResult<SBoard> {
+    let rows_valid = (0 .. 3).all(|i| (x >> (5 * i) & 0b11111) <= 0x1A);
+    if x >> 15 != 0 || !rows_valid {
+        return Err(UtttError::decode("E85102", "invalid sub-board"));
+    }
+    Ok(SBoard { encoding: x })
+}
@@ -0,0 +1,9 @@
+/// The 'solver/codec' module.
+
+// Include these submodules into this module.
+pub use self::codec::*;
+
+mod codec;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,101 @@
+use constants::*;
+use data::*;
+use error::*;
+use quickcheck::QuickCheck;
+use solver::*;
+use solver::codec::*;
+
+#[test]
+fn test_game_columns_round_trip() {
+    fn prop(game: Game) -> bool {
+        game_from_columns(game_columns_from(&game)) == Ok(game)
+    }
+    QuickCheck::new().tests(500).quickcheck(prop as fn(Game) -> bool);
+    assert!(prop(EMPTY_GAME));
+}
+
+#[test]
+fn test_game_columns_rejects_corrupt_keys() {
+    let (game_1, game_2, game_3) = game_columns_from(&EMPTY_GAME);
+    let corrupt: [((i64, i64, i32), &str); 7] = [
+        // A sub-board row that is not one of the 27 valid rows.
+        ((game_1 | 0x1F, game_2, game_3), "E85102"),
+        // A last location in row 9.
+        ((game_1, game_2, game_3 & !(0xFF << 16) | 0x90 << 16), "E85094"),
+        // The reserved bit, and a rules variant from a newer build.
+        ((game_1, game_2, game_3 | 1 << 24), "E85095"),
+        ((game_1, game_2, game_3 | 1 << 26), "E85098"),
+        // A canonical key, read as a key as played.
+        ((game_1, game_2, game_3 | 1 << 25), "E85099"),
+        // A last location on an empty board.
+        ((game_1, game_2, game_3 & !(0xFF << 16)), "E85096"),
+        // O to play first.
+        ((game_1, game_2, game_3 & !(3 << 28)), "E85097"),
+    ];
+    for &(columns, code) in corrupt.iter() {
+        match game_from_columns(columns) {
+            Err(UtttError::Decode { code: c, .. }) if c == code => {},
+            other => panic!("unexpected {:?} for {}", other, code),
+        }
+    }
+}
+
+#[test]
+fn test_key_flags() {
+    fn prop(game: Game) -> bool {
+        let flags = KeyFlags::canonical();
+        let columns = game_columns_with(&game, flags);
+        key_flags_from(columns.2) == Ok(flags) &&
+            game_from_columns_with(columns, flags) == Ok(game) &&
+            game_from_columns(columns).is_err()
+    }
+    QuickCheck::new().tests(200).quickcheck(prop as fn(Game) -> bool);
+    // Version 1 keys (with bits 27 - 24 clear) are standard keys as played.
+    let (_, _, game_3) = game_columns_from(&EMPTY_GAME);
+    assert!(game_3 as u32 >> 24 & 0xF == 0);
+    assert!(key_flags_from(game_3) == Ok(KeyFlags::new()));
+}
+
+#[test]
+fn test_game_key_round_trip() {
+    fn prop(game: Game) -> bool {
+        let columns = game_columns_from(&game);
+        columns_from_key(&key_from_columns(columns)) == columns
+    }
+    QuickCheck::new().tests(200).quickcheck(prop as fn(Game) -> bool);
+    // Little-endian: the low byte of game_1 (sub-board 0) comes first.
+    let mut game = EMPTY_GAME;
+    game.board.sboards[0].encoding = 0x1234;
+    let key = key_from_columns(game_columns_from(&game));
+    assert!(key[0] == 0x34 && key[1] == 0x12);
+}
+
+#[test]
+fn test_solutions_round_trip() {
+    fn prop(game: Game, sols: Vec<Solution>) -> bool {
+        // Decoding needs the next player, so use it for every play.
+        let player = game.next_player();
+        let sols = sols.into_iter()
+            .map(|sol| Solution {
+                opt_play: match (sol.opt_play, player) {
+                    (Some(play), Some(p)) =>
+                        Some(Play { loc: play.loc, player: p }),
+                    _ => None,
+                },
+                outcome: sol.outcome,
+            })
+            .collect::<Vec<Solution>>();
+        let mut bytes: Vec<u8> = vec![0xAA];
+        write_solutions(&mut bytes, &sols).expect("E1711");
+        bytes.push(0xBB);
+        let width = 2 + 2 * sols.len();
+        read_solutions(&bytes[1 ..], player) == Ok((sols, width))
+    }
+    QuickCheck::new().tests(200).quickcheck(
+        prop as fn(Game, Vec<Solution>) -> bool
+    );
+    match read_solutions(&[2, 0], None) {
+        Err(UtttError::Decode { code: "E1702", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+}
@@ -1,101 +1,231 @@
 /// Database.
 
 use data::*;
+use error::*;
 use postgres::{Connection, TlsMode};
 use postgres::params::IntoConnectParams;
 use postgres::rows::{Row as DataRow, Rows as DataRows};
-use postgres::stmt::Statement;
-use solver::{Outcome, Solution};
-use std::collections::HashSet;
+use postgres::types::{FromSql, ToSql};
+use solver::{Outcome, Solution, is_downgrade, merge_solutions};
+use solver::codec::*;
+use std::collections::{HashMap, HashSet};
 
 // == public API: table functions ==============================================
+//
+// To create the table and its indexes, see `db_migrate`.
 
-pub fn db_create_table(conn: &Connection) {
-    let rows_modified = conn.execute(CREATE_TABLE, &[]).expect("E85011");
-    if rows_modified != 0 { panic!("E85012") };
+pub fn db_drop_table(conn: &Connection) -> UtttResult<()> {
+    execute_ddl(conn, DROP_TABLE, "E85021", "E85022")
 }
 
-pub fn db_drop_table(conn: &Connection) {
-    let rows_modified = conn.execute(DROP_TABLE, &[]).expect("E85021");
-    if rows_modified != 0 { panic!("E85022") };
+pub fn db_truncate_table(conn: &Connection) -> UtttResult<()> {
+    execute_ddl(conn, TRUNCATE_TABLE, "E85031", "E85032")
 }
 
-pub fn db_truncate_table(conn: &Connection) {
-    let rows_modified = conn.execute(TRUNCATE_TABLE, &[]).expect("E85031");
-    if rows_modified != 0 { panic!("E85032") };
-}
-
-pub fn db_create_indexes(conn: &Connection) {
-    for command in CREATE_INDEXES {
-        let rows_modified = conn.execute(command, &[]).expect("E85041");
-        if rows_modified != 0 { panic!("E85042") };
+/// Executes a command that is not expected to modify any rows.
+fn execute_ddl(conn: &Connection, command: &str, query_code: &'static str,
+                   rows_code: &'static str) -> UtttResult<()> {
+    let rows_modified = conn.execute(command, &[])
+        .map_err(|e| UtttError::from_postgres(query_code, e))?;
+    if rows_modified != 0 {
+        return Err(UtttError::invariant(rows_code, "rows modified by DDL"));
     }
+    Ok(())
 }
 
 // == public API: connect / read / write =======================================
 
-pub fn db_connect<T: IntoConnectParams> (params: T) -> Connection {
-    Connection::connect(params, TlsMode::None).expect("E85051")
+pub fn db_connect<T: IntoConnectParams> (params: T)
+                                         -> UtttResult<Connection> {
+    Connection::connect(params, TlsMode::None)
+        .map_err(|e| UtttError::from_connect("E85051", e))
 }
 
-pub fn db_read_stmt<'c>(conn: &'c Connection) -> Statement<'c> {
-    conn.prepare(
-        "SELECT solutions \
-         FROM solutions \
-         WHERE game_1 = $1 AND game_2 = $2 AND game_3 = $3").expect("E85061")
-}
-
-/// Read function.
-pub fn db_read(stmt: &Statement, game: &Game) -> Vec<Solution> {
+/// Read function. The statement is prepared once per connection (and again
+/// after a reconnect); see `DbManager`.
+pub fn db_read(conn: &Connection, game: &Game) -> UtttResult<Vec<Solution>> {
+    let stmt = conn.prepare_cached(READ_COMMAND)
+        .map_err(|e| UtttError::from_postgres("E85061", e))?;
     let (game_1, game_2, game_3): (i64, i64, i32) = game_columns_from(game);
-    let rows: DataRows = stmt.query(
-        &[&game_1, &game_2, &game_3]).expect("E85062");
+    let rows: DataRows = stmt.query(&[&game_1, &game_2, &game_3])
+        .map_err(|e| UtttError::from_postgres("E85062", e))?;
     match rows.len() {
-        0 => vec![],
+        0 => Ok(vec![]),
         1 => {
             let next_player: Option<Player> = game.next_player();
             let row: DataRow = rows.get(0);
-            let solutions: Vec<i16> = row.get(0);
+            let solutions: Vec<i16> = column(&row, 0, "E85064")?;
             solutions.iter()
                 .map(|sol| solution_from(*sol, next_player))
-                .collect::<Vec<Solution>>()
+                .collect::<UtttResult<Vec<Solution>>>()
         },
-        _ => panic!("E85063"),
+        _ => Err(UtttError::invariant("E85063", "duplicate primary key")),
     }
 }
 
-pub fn db_write_stmt<'c>(conn: &'c Connection) -> Statement<'c> {
-    conn.prepare(
-        "INSERT INTO solutions
-         (game_1, game_2, game_3, plays, solutions, sol_turns, unknown) \
-         VALUES ($1, $2, $3, $4, $5, $6, $7) \
-         ON CONFLICT (game_1, game_2, game_3) \
-         DO UPDATE \
-         SET (plays, solutions, sol_turns, unknown) = ($4, $5, $6, $7) \
-         WHERE \
-         solutions.game_1 = $1 AND \
-         solutions.game_2 = $2 AND \
-         solutions.game_3 = $3").expect("E85071")
-}
-
-/// Write to database, inserting or updating as appropriate. This function is
-/// not responsible for testing if an overwrite is the 'sensible' thing to do;
-/// e.g. a caller could overwrite `Outcome::Unknown { turns: 6 }` to
-/// `Outcome::Unknown { turns : 3 }`.
-pub fn db_write(stmt: &Statement, game: &Game, sols: &Vec<Solution>) -> bool {
-    let (game_1, game_2, game_3): (i64, i64, i32) = game_columns_from(game);
-    let solutions: Vec<i16> = sols.into_iter()
-        .map(|sol| sol_i16(*sol))
-        .collect::<Vec<i16>>();
-    let plays: i16 = game.board.play_count() as i16;
-    let (sol_turns, unknown) = turns_and_unknown(sols);
+/// Write to database, inserting or updating as appropriate. An existing row is
+/// only updated if that would not lose knowledge (see `merge_solutions`); e.g.
+/// `Outcome::Unknown { turns: 6 }` is never overwritten by
+/// `Outcome::Unknown { turns : 3 }`. Returns false if the existing row was
+/// kept.
+pub fn db_write(conn: &Connection, game: &Game, sols: &Vec<Solution>)
+                -> UtttResult<bool> {
+    let stmt = conn.prepare_cached(WRITE_COMMAND)
+        .map_err(|e| UtttError::from_postgres("E85071", e))?;
+    let (game_1, game_2, game_3, plays, solutions, sol_turns, unknown) =
+        write_values(game, sols)?;
     let rows_modified = stmt.execute(
         &[&game_1, &game_2, &game_3, &plays, &solutions, &sol_turns, &unknown])
-        .expect("E85072");
+        .map_err(|e| UtttError::from_postgres("E85072", e))?;
+    match rows_modified {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(UtttError::invariant("E85073", "upsert modified many rows")),
+    }
+}
+
+/// The maximum number of rows in one multi-row upsert. (PostgreSQL allows at
+/// most 65,535 parameters per statement; each row uses 7.)
+pub const WRITE_BATCH_ROWS: usize = 1_000;
+
+/// Writes many games at once, inserting or updating as appropriate, with one
+/// multi-row upsert per `WRITE_BATCH_ROWS` games. Like `db_write`, it never
+/// overwrites a row with less knowledge. If a game appears more than once, the
+/// entries are merged first, since PostgreSQL does not allow one command to
+/// update the same row twice. Returns the number of rows written.
+pub fn db_write_batch(conn: &Connection, entries: &[(Game, Vec<Solution>)])
+                      -> UtttResult<u64> {
+    let mut merged: HashMap<Game, usize> = HashMap::new();
+    let mut unique: Vec<(Game, Vec<Solution>)> = Vec::new();
+    for &(game, ref sols) in entries.iter() {
+        match merged.get(&game) {
+            Some(&i) => unique[i].1 = merge_solutions(&unique[i].1, sols),
+            None => {
+                merged.insert(game, unique.len());
+                unique.push((game, sols.clone()));
+            },
+        }
+    }
+    let rows = unique.iter()
+        .map(|&(ref game, ref sols)| write_values(game, sols))
+        .collect::<UtttResult<Vec<WriteValues>>>()?;
+    write_rows(conn, &rows)
+}
+
+/// Writes raw rows (as returned by `db_scan`), inserting or updating as
+/// appropriate. Like `db_write_batch`, it never overwrites a row with less
+/// knowledge, and merges rows with the same key first. Each row's solutions
+/// are decoded (with `solution_from`) to derive the 'sol_turns' and 'unknown'
+/// columns, so invalid rows are rejected. Returns the number of rows written.
+pub fn db_write_raw(conn: &Connection, raw_rows: &[RawRow]) -> UtttResult<u64> {
+    let mut merged: HashMap<GameColumns, usize> = HashMap::new();
+    let mut unique: Vec<(RawRow, Vec<Solution>)> = Vec::new();
+    for row in raw_rows.iter() {
+        let sols = raw_solutions(row)?;
+        match merged.get(&row.0) {
+            Some(&i) => {
+                if !is_downgrade(&unique[i].1, &sols) {
+                    unique[i] = (row.clone(), sols);
+                }
+            },
+            None => {
+                merged.insert(row.0, unique.len());
+                unique.push((row.clone(), sols));
+            },
+        }
+    }
+    let rows = unique.into_iter()
+        .map(|(((game_1, game_2, game_3), plays, solutions), sols)| {
+            let (sol_turns, unknown) = turns_and_unknown(&sols)?;
+            Ok((game_1, game_2, game_3, plays, solutions, sol_turns, unknown))
+        })
+        .collect::<UtttResult<Vec<WriteValues>>>()?;
+    write_rows(conn, &rows)
+}
+
+/// Writes rows (distinct keys) with one multi-row upsert per
+/// `WRITE_BATCH_ROWS` rows. Returns the number of rows written.
+fn write_rows(conn: &Connection, rows: &[WriteValues]) -> UtttResult<u64> {
+    let mut count: u64 = 0;
+    for chunk in rows.chunks(WRITE_BATCH_ROWS) {
+        count += write_chunk(conn, chunk)?;
+    }
+    Ok(count)
+}
+
+/// Writes one chunk (of at most `WRITE_BATCH_ROWS` distinct games) with a
+/// single statement. Statements are cached per chunk size, so full chunks
+/// reuse the same prepared statement.
+fn write_chunk(conn: &Connection, rows: &[WriteValues]) -> UtttResult<u64> {
+    let mut params: Vec<&dyn ToSql> = Vec::with_capacity(7 * rows.len());
+    for row in rows.iter() {
+        params.push(&row.0);
+        params.push(&row.1);
+        params.push(&row.2);
+        params.push(&row.3);
+        params.push(&row.4);
+        params.push(&row.5);
+        params.push(&row.6);
+    }
+    let stmt = conn.prepare_cached(&write_batch_command(rows.len()))
+        .map_err(|e| UtttError::from_postgres("E85121", e))?;
+    let rows_modified = stmt.execute(&params)
+        .map_err(|e| UtttError::from_postgres("E85122", e))?;
+    if rows_modified > rows.len() as u64 {
+        return Err(UtttError::invariant("E85123", "upsert modified extra rows"));
+    }
+    Ok(rows_modified)
+}
+
+/// Column values for one row of the 'solutions' table, in column order.
+type WriteValues = (i64, i64, i32, i16, Vec<i16>, i16, bool);
+
+fn write_values(game: &Game, sols: &Vec<Solution>) -> UtttResult<WriteValues> {
+    let ((game_1, game_2, game_3), plays, solutions) = raw_row_from(game, sols);
+    let (sol_turns, unknown) = turns_and_unknown(sols)?;
+    Ok((game_1, game_2, game_3, plays, solutions, sol_turns, unknown))
+}
+
+/// Returns up to `limit` rows, in primary key order, whose keys sort after
+/// `after` (or from the start of the table if `after` is `None`). Each row is
+/// returned as a `RawRow`. To walk the whole table, pass the last key of each
+/// page as `after` for the next one.
+pub fn db_scan(conn: &Connection, after: Option<GameColumns>, limit: i64)
+               -> UtttResult<Vec<RawRow>> {
+    let rows: DataRows = match after {
+        None => conn.query(
+            "SELECT game_1, game_2, game_3, plays, solutions \
+             FROM solutions \
+             ORDER BY game_1, game_2, game_3 \
+             LIMIT $1", &[&limit]),
+        Some((game_1, game_2, game_3)) => conn.query(
+            "SELECT game_1, game_2, game_3, plays, solutions \
+             FROM solutions \
+             WHERE (game_1, game_2, game_3) > ($1, $2, $3) \
+             ORDER BY game_1, game_2, game_3 \
+             LIMIT $4", &[&game_1, &game_2, &game_3, &limit]),
+    }.map_err(|e| UtttError::from_postgres("E85111", e))?;
+    rows.iter()
+        .map(|row| Ok(((column(&row, 0, "E85112")?,
+                        column(&row, 1, "E85112")?,
+                        column(&row, 2, "E85112")?),
+                       column(&row, 3, "E85112")?,
+                       column(&row, 4, "E85112")?)))
+        .collect::<UtttResult<Vec<RawRow>>>()
+}
+
+/// Deletes the row for the given key. Returns true if there was one.
+pub fn db_delete(conn: &Connection, key: GameColumns) -> UtttResult<bool> {
+    let (game_1, game_2, game_3) = key;
+    let rows_modified = conn.execute(
+        "DELETE FROM solutions \
+         WHERE game_1 = $1 AND game_2 = $2 AND game_3 = $3",
+        &[&game_1, &game_2, &game_3])
+        .map_err(|e| UtttError::from_postgres("E85131", e))?;
     match rows_modified {
-        0 => false,
-        1 => true,
-        _ => panic!("E85073"),
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(UtttError::invariant("E85132", "delete removed many rows")),
     }
 }
 
@@ -103,7 +233,7 @@ pub fn db_write(stmt: &Statement, game: &Game, sols: &Vec<Solution>) -> bool {
 /// 1. The (shared / equal) turns value in each of the vector of solutions.
 /// 2. A boolean indicating if each of the solutions is unknown. (If one
 ///    is unknown, then they must all be unknown)
-pub fn turns_and_unknown(solutions: &Vec<Solution>) -> (i16, bool) {
+pub fn turns_and_unknown(solutions: &Vec<Solution>) -> UtttResult<(i16, bool)> {
     let mut turns_set: HashSet<Count> = HashSet::new();
     let mut wins: u32 = 0;
     let mut ties: u32 = 0;
@@ -118,16 +248,30 @@ pub fn turns_and_unknown(solutions: &Vec<Solution>) -> (i16, bool) {
     }
     if turns_set.len() == 1 {
         let turns: i16 = turns_set.into_iter().next().expect("E85081") as i16;
-        if unknowns > 0 && (ties > 0 || wins > 0) { panic!("E85082"); }
-        (turns, unknowns > 0)
+        if unknowns > 0 && (ties > 0 || wins > 0) {
+            return Err(UtttError::invariant(
+                "E85082", "unknown solutions mixed with known ones"));
+        }
+        Ok((turns, unknowns > 0))
     } else {
-        panic!("E85083");
+        Err(UtttError::invariant("E85083", "solutions have different turns"))
+    }
+}
+
+/// Returns a column of a row, or an error if it cannot be decoded.
+pub fn column<T: FromSql>(row: &DataRow, idx: usize, code: &'static str)
+                          -> UtttResult<T> {
+    match row.get_opt(idx) {
+        Some(Ok(value)) => Ok(value),
+        Some(Err(e)) => Err(UtttError::from_postgres(code, e)),
+        None => Err(UtttError::decode(code, "missing column")),
     }
 }
 
 // == PostgreSQL command strings ===============================================
 
-/// Command to create the 'solutions' table.
+/// Returns the command to create the 'solutions' table, optionally in a
+/// tablespace.
 ///
 /// Mapping between PostgreSQL and Rust types:
 ///
@@ -143,271 +287,112 @@ pub fn turns_and_unknown(solutions: &Vec<Solution>) -> (i16, bool) {
 ///
 /// Note: game_1, game_2, game_3 form a composite primary key.
 ///
-/// Bit mapping for the 'game_1' (BIGINT = 64 bits) column:
-///
-/// bits      width   note
-/// -------   -----   --------
-/// 63 - 48      16   SBoard 3
-/// 47 - 32      16   SBoard 2
-/// 31 - 16      16   SBoard 1
-/// 15 -  0      16   SBoard 0
-///
-/// Bit mapping for the 'game_2' (BIGINT = 64 bits) column:
-///
-/// bits      width   note
-/// -------   -----   --------
-/// 63 - 48      16   SBoard 7
-/// 47 - 32      16   SBoard 6
-/// 31 - 16      16   SBoard 5
-/// 15 -  0      16   SBoard 4
-///
-/// Bit mapping for the 'game_3' (INT = 32 bits) column:
-///
-/// bit(s)    width   note
-/// -------   -----   --------
-/// 31 - 30       2   last player (0 = O, 1 = X, 2 = none)
-/// 29 - 28       2   next player (0 = O, 1 = X, 2 = none)
-/// 27 - 24       4   unused
-/// 23 - 16       8   last location (see Loc.encoding)
-/// 15 -  0      16   SBoard 8
-///
-/// Bit mapping for the 'solution' (SMALLINT = 16 bits) column:
-///
-/// bits       width   note
-/// --------   -----   ----
-/// 15 - 14       2   outcome (0 = ?; 1 = tie, 2 = O to win, 3 = X to win)
-/// 13 -  7       7   location (0 to 80; 127 for none)
-///  6 -  0       7   turns (0 to 81; 82 to 127 impossible)
-///
-/// * I would have preferred to encode the same information as the Solution
-/// struct (Option<Play> + Outcome), which when expanded is (Option<Loc> +
-/// Option<Player> + Outcome). However, I could not encode all of this in 16
-/// bits, so I dropped the Player component. This is not a problem in context,
-/// because the player can quickly calculated after retrieving the current
-/// player from the 'game_3' column.
-pub const CREATE_TABLE: &'static str =
-    "CREATE TABLE IF NOT EXISTS solutions (\
-       game_1     BIGINT      NOT NULL, \
-       game_2     BIGINT      NOT NULL, \
-       game_3     INT         NOT NULL, \
-       plays      SMALLINT    NOT NULL, \
-       solutions  SMALLINT[]  NOT NULL, \
-       sol_turns  SMALLINT    NOT NULL, \
-       unknown    BOOLEAN     NOT NULL, \
-       PRIMARY KEY (game_1, game_2, game_3) USING INDEX TABLESPACE uttt_1 \
-     ) TABLESPACE uttt_1";
-
-pub const CREATE_INDEXES: &'static [ &'static str ] = &[
-    "CREATE INDEX IF NOT EXISTS solutions_plays_idx \
-    ON solutions (plays) TABLESPACE uttt_1",
-
-    "CREATE INDEX IF NOT EXISTS solutions_sol_turns_idx \
-    ON solutions (sol_turns) TABLESPACE uttt_1",
-
-    "CREATE INDEX IF NOT EXISTS solutions_unknown_idx \
-    ON solutions (unknown) TABLESPACE uttt_1",
-];
-
-pub const DROP_TABLE: &'static str = "DROP TABLE IF EXISTS solutions";
-
-pub const TRUNCATE_TABLE: &'static str = "TRUNCATE TABLE solutions";
-
-// == conversions (structs -> database values) =================================
-
-/// Converts a Game struct to a 3-tuple (a triple) of types (i64, i64, i32)
-/// suitable for the 'game1', 'game2', 'game3' columns in the 'solutions' table.
-fn game_columns_from(game: &Game) -> (i64, i64, i32) {
-    let game_1: u64 =
-        (game.board.sboards[3].encoding as u64) << 48 |
-        (game.board.sboards[2].encoding as u64) << 32 |
-        (game.board.sboards[1].encoding as u64) << 16 |
-        (game.board.sboards[0].encoding as u64);
-    let game_2: u64 =
-        (game.board.sboards[7].encoding as u64) << 48 |
-        (game.board.sboards[6].encoding as u64) << 32 |
-        (game.board.sboards[5].encoding as u64) << 16 |
-        (game.board.sboards[4].encoding as u64);
-    let last_player: Option<Player> = game.last_player();
-    let game_3: u32 =
-        player_u32(last_player) << 30 |
-        player_u32(game.next_player_(last_player)) << 28 |
-        last_location_u32(game) << 16 |
-        (game.board.sboards[8].encoding as u32);
-    (game_1 as i64, game_2 as i64, game_3 as i32)
+/// The key columns and the 'solutions' column hold the encodings defined in
+/// the 'codec' module; see `game_columns_from` and `sol_i16`.
+pub fn create_table_command(tablespace: Option<&str>) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS solutions (\
+           game_1     BIGINT      NOT NULL, \
+           game_2     BIGINT      NOT NULL, \
+           game_3     INT         NOT NULL, \
+           plays      SMALLINT    NOT NULL, \
+           solutions  SMALLINT[]  NOT NULL, \
+           sol_turns  SMALLINT    NOT NULL, \
+           unknown    BOOLEAN     NOT NULL, \
+           PRIMARY KEY (game_1, game_2, game_3){} \
+         ){}",
+        tablespace_clause(" USING INDEX", tablespace),
+        tablespace_clause("", tablespace))
 }
 
-/// Converts a Solution to a 16-bit integer.
-fn sol_i16(solution: Solution) -> i16 {
-    let x_outcome: u16 = match solution.outcome {
-        Outcome::Unknown { .. } => 0,
-        Outcome::Tie { .. } => 1,
-        Outcome::Win { player: Player::O, .. } => 2,
-        Outcome::Win { player: Player::X, .. } => 3,
-    };
-    let x_location: u16 = location_u16(solution.opt_play);
-    let x_turns: u16 = solution.outcome.turns() as u16;
-    let x: u16 = x_outcome << 14 | x_location << 7 | x_turns;
-    x as i16
+/// Commands to create the secondary indexes on the 'solutions' table.
+pub fn create_index_commands(tablespace: Option<&str>) -> Vec<String> {
+    ["plays", "sol_turns", "unknown"].iter()
+        .map(|column| format!(
+            "CREATE INDEX IF NOT EXISTS solutions_{}_idx \
+             ON solutions ({}){}",
+            column, column, tablespace_clause("", tablespace)))
+        .collect::<Vec<String>>()
 }
 
-// == helpers for conversions (structs -> database values) =====================
-
-/// Returns either a location's encoding (8 bits) or 127
-fn last_location_u32(game: &Game) -> u32 {
-    match game.last_loc {
-        Some(loc) => loc.encoding as u32,
-        None => 0xFF,
+/// Returns a TABLESPACE clause (with the given prefix) for the tablespace, if
+/// any. The tablespace name is quoted as an identifier.
+fn tablespace_clause(prefix: &str, tablespace: Option<&str>) -> String {
+    match tablespace {
+        None => String::new(),
+        Some(name) => format!("{} TABLESPACE \"{}\"",
+                              prefix, name.replace("\"", "\"\"")),
     }
 }
 
-/// Returns either 0, 1, or 2 for a given optional player.
-fn player_u32(opt_player: Option<Player>) -> u32 {
-    match opt_player {
-        Some(Player::O) => 0,
-        Some(Player::X) => 1,
-        None => 2,
-    }
-}
+/// Command to read the solutions of one game.
+pub const READ_COMMAND: &'static str =
+    "SELECT solutions \
+     FROM solutions \
+     WHERE game_1 = $1 AND game_2 = $2 AND game_3 = $3";
 
-fn location_u16(opt_play: Option<Play>) -> u16 {
-    match opt_play {
-        None => 0x7F,
-        Some(play) => {
-            let location: Loc = play.loc;
-            let row_idx = location.row().as_u8();
-            let col_idx = location.col().as_u8();
-            (row_idx * 9 + col_idx) as u16
-        }
-    }
+/// The upsert used by `db_write`. On conflict, the `WHERE` clause is the SQL
+/// version of `merge_solutions`: update unless the new solutions are unknown
+/// and the stored ones are either decided or deeper.
+pub const WRITE_COMMAND: &'static str =
+    "INSERT INTO solutions \
+     (game_1, game_2, game_3, plays, solutions, sol_turns, unknown) \
+     VALUES ($1, $2, $3, $4, $5, $6, $7) \
+     ON CONFLICT (game_1, game_2, game_3) \
+     DO UPDATE \
+     SET (plays, solutions, sol_turns, unknown) = ($4, $5, $6, $7) \
+     WHERE NOT EXCLUDED.unknown OR \
+     (solutions.unknown AND EXCLUDED.sol_turns >= solutions.sol_turns)";
+
+/// Returns the command for a multi-row upsert of `n` rows. Parameters are
+/// numbered row by row, 7 per row, in the same order as `WRITE_COMMAND`.
+fn write_batch_command(n: usize) -> String {
+    let values = (0 .. n)
+        .map(|i| {
+            let k = 7 * i;
+            format!("(${}, ${}, ${}, ${}, ${}, ${}, ${})",
+                    k + 1, k + 2, k + 3, k + 4, k + 5, k + 6, k + 7)
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!(
+        "INSERT INTO solutions \
+         (game_1, game_2, game_3, plays, solutions, sol_turns, unknown) \
+         VALUES {} \
+         ON CONFLICT (game_1, game_2, game_3) \
+         DO UPDATE \
+         SET (plays, solutions, sol_turns, unknown) = \
+         (EXCLUDED.plays, EXCLUDED.solutions, EXCLUDED.sol_turns, \
+         EXCLUDED.unknown) \
+         WHERE NOT EXCLUDED.unknown OR \
+         (solutions.unknown AND EXCLUDED.sol_turns >= solutions.sol_turns)",
+        values)
 }
 
-// == conversions (database values -> structs) =================================
-
-/// Converts the 'solution' column (an i16) in the 'solution' table to
-/// a Solution struct.
-fn solution_from(sol: i16, player: Option<Player>) -> Solution {
-    let x: u16 = sol as u16;
-    let outcome: u8 = (x >> 14 & 3) as u8;
-    let location: u8 = (x >> 7 & 0x7F) as u8;
-    let turns: u8 = (x & 0x7F) as u8;
-    let opt_play: Option<Play> = match opt_loc_from(location) {
-        Some(loc) => {
-            Some(Play {
-                loc: loc,
-                player: player.expect("E85091")
-            })
-        },
-        None => None,
-    };
-    match outcome {
-        0 => Solution {
-            outcome: Outcome::Unknown { turns: turns },
-            opt_play: opt_play,
-        },
-        1 => Solution {
-            outcome: Outcome::Tie { turns: turns },
-            opt_play: opt_play,
-        },
-        2 => Solution {
-            outcome: Outcome::Win { turns: turns, player: Player::O },
-            opt_play: opt_play,
-        },
-        3 => Solution {
-            outcome: Outcome::Win { turns: turns, player: Player::X },
-            opt_play: opt_play,
-        },
-        _ => panic!("E85092"),
-    }
+pub const DROP_TABLE: &'static str = "DROP TABLE IF EXISTS solutions";
+
+pub const TRUNCATE_TABLE: &'static str = "TRUNCATE TABLE solutions";
+
+// == rows =====================================================================
+
+/// A row of the 'solutions' table as stored, without the columns that are
+/// derived from the solutions: the key columns, 'plays', and the encoded
+/// solutions.
+pub type RawRow = (GameColumns, i16, Vec<i16>);
+
+/// Converts a game and its solutions to a `RawRow`.
+pub fn raw_row_from(game: &Game, sols: &Vec<Solution>) -> RawRow {
+    let solutions: Vec<i16> = sols.iter()
+        .map(|sol| sol_i16(*sol))
+        .collect::<Vec<i16>>();
+    let plays: i16 = game.board.play_count() as i16;
+    (game_columns_from(game), plays, solutions)
 }
 
-// == helper conversions (database values -> structs) ==========================
-
-/// Converts from an 8-bit unsigned integer to an optional location.
-fn opt_loc_from(x: u8) -> Option<Loc> {
-    // TODO: Use math instead of this dreadful pattern match.
-    match x {
-        127 => None,
-        0 => Some(Loc::from_row_col(RI::R0, CI::C0)),
-        1 => Some(Loc::from_row_col(RI::R0, CI::C1)),
-        2 => Some(Loc::from_row_col(RI::R0, CI::C2)),
-        3 => Some(Loc::from_row_col(RI::R0, CI::C3)),
-        4 => Some(Loc::from_row_col(RI::R0, CI::C4)),
-        5 => Some(Loc::from_row_col(RI::R0, CI::C5)),
-        6 => Some(Loc::from_row_col(RI::R0, CI::C6)),
-        7 => Some(Loc::from_row_col(RI::R0, CI::C7)),
-        8 => Some(Loc::from_row_col(RI::R0, CI::C8)),
-        9 => Some(Loc::from_row_col(RI::R1, CI::C0)),
-        10 => Some(Loc::from_row_col(RI::R1, CI::C1)),
-        11 => Some(Loc::from_row_col(RI::R1, CI::C2)),
-        12 => Some(Loc::from_row_col(RI::R1, CI::C3)),
-        13 => Some(Loc::from_row_col(RI::R1, CI::C4)),
-        14 => Some(Loc::from_row_col(RI::R1, CI::C5)),
-        15 => Some(Loc::from_row_col(RI::R1, CI::C6)),
-        16 => Some(Loc::from_row_col(RI::R1, CI::C7)),
-        17 => Some(Loc::from_row_col(RI::R1, CI::C8)),
-        18 => Some(Loc::from_row_col(RI::R2, CI::C0)),
-        19 => Some(Loc::from_row_col(RI::R2, CI::C1)),
-        20 => Some(Loc::from_row_col(RI::R2, CI::C2)),
-        21 => Some(Loc::from_row_col(RI::R2, CI::C3)),
-        22 => Some(Loc::from_row_col(RI::R2, CI::C4)),
-        23 => Some(Loc::from_row_col(RI::R2, CI::C5)),
-        24 => Some(Loc::from_row_col(RI::R2, CI::C6)),
-        25 => Some(Loc::from_row_col(RI::R2, CI::C7)),
-        26 => Some(Loc::from_row_col(RI::R2, CI::C8)),
-        27 => Some(Loc::from_row_col(RI::R3, CI::C0)),
-        28 => Some(Loc::from_row_col(RI::R3, CI::C1)),
-        29 => Some(Loc::from_row_col(RI::R3, CI::C2)),
-        30 => Some(Loc::from_row_col(RI::R3, CI::C3)),
-        31 => Some(Loc::from_row_col(RI::R3, CI::C4)),
-        32 => Some(Loc::from_row_col(RI::R3, CI::C5)),
-        33 => Some(Loc::from_row_col(RI::R3, CI::C6)),
-        34 => Some(Loc::from_row_col(RI::R3, CI::C7)),
-        35 => Some(Loc::from_row_col(RI::R3, CI::C8)),
-        36 => Some(Loc::from_row_col(RI::R4, CI::C0)),
-        37 => Some(Loc::from_row_col(RI::R4, CI::C1)),
-        38 => Some(Loc::from_row_col(RI::R4, CI::C2)),
-        39 => Some(Loc::from_row_col(RI::R4, CI::C3)),
-        40 => Some(Loc::from_row_col(RI::R4, CI::C4)),
-        41 => Some(Loc::from_row_col(RI::R4, CI::C5)),
-        42 => Some(Loc::from_row_col(RI::R4, CI::C6)),
-        43 => Some(Loc::from_row_col(RI::R4, CI::C7)),
-        44 => Some(Loc::from_row_col(RI::R4, CI::C8)),
-        45 => Some(Loc::from_row_col(RI::R5, CI::C0)),
-        46 => Some(Loc::from_row_col(RI::R5, CI::C1)),
-        47 => Some(Loc::from_row_col(RI::R5, CI::C2)),
-        48 => Some(Loc::from_row_col(RI::R5, CI::C3)),
-        49 => Some(Loc::from_row_col(RI::R5, CI::C4)),
-        50 => Some(Loc::from_row_col(RI::R5, CI::C5)),
-        51 => Some(Loc::from_row_col(RI::R5, CI::C6)),
-        52 => Some(Loc::from_row_col(RI::R5, CI::C7)),
-        53 => Some(Loc::from_row_col(RI::R5, CI::C8)),
-        54 => Some(Loc::from_row_col(RI::R6, CI::C0)),
-        55 => Some(Loc::from_row_col(RI::R6, CI::C1)),
-        56 => Some(Loc::from_row_col(RI::R6, CI::C2)),
-        57 => Some(Loc::from_row_col(RI::R6, CI::C3)),
-        58 => Some(Loc::from_row_col(RI::R6, CI::C4)),
-        59 => Some(Loc::from_row_col(RI::R6, CI::C5)),
-        60 => Some(Loc::from_row_col(RI::R6, CI::C6)),
-        61 => Some(Loc::from_row_col(RI::R6, CI::C7)),
-        62 => Some(Loc::from_row_col(RI::R6, CI::C8)),
-        63 => Some(Loc::from_row_col(RI::R7, CI::C0)),
-        64 => Some(Loc::from_row_col(RI::R7, CI::C1)),
-        65 => Some(Loc::from_row_col(RI::R7, CI::C2)),
-        66 => Some(Loc::from_row_col(RI::R7, CI::C3)),
-        67 => Some(Loc::from_row_col(RI::R7, CI::C4)),
-        68 => Some(Loc::from_row_col(RI::R7, CI::C5)),
-        69 => Some(Loc::from_row_col(RI::R7, CI::C6)),
-        70 => Some(Loc::from_row_col(RI::R7, CI::C7)),
-        71 => Some(Loc::from_row_col(RI::R7, CI::C8)),
-        72 => Some(Loc::from_row_col(RI::R8, CI::C0)),
-        73 => Some(Loc::from_row_col(RI::R8, CI::C1)),
-        74 => Some(Loc::from_row_col(RI::R8, CI::C2)),
-        75 => Some(Loc::from_row_col(RI::R8, CI::C3)),
-        76 => Some(Loc::from_row_col(RI::R8, CI::C4)),
-        77 => Some(Loc::from_row_col(RI::R8, CI::C5)),
-        78 => Some(Loc::from_row_col(RI::R8, CI::C6)),
-        79 => Some(Loc::from_row_col(RI::R8, CI::C7)),
-        80 => Some(Loc::from_row_col(RI::R8, CI::C8)),
-        _ => panic!("E85101"),
-    }
+/// Decodes the solutions of a `RawRow`.
+pub fn raw_solutions(row: &RawRow) -> UtttResult<Vec<Solution>> {
+    let next_player = next_player_from((row.0).2)?;
+    row.2.iter()
+        .map(|sol| solution_from(*sol, next_player))
+        .collect::<UtttResult<Vec<Solution>>>()
 }
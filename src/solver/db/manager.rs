@@ -0,0 +1,114 @@
+/// Database connection manager.
+///
+/// Owns a connection and replaces it when it breaks. `DbManager::run` runs an
+/// operation; if the operation fails with a connection error (see
+/// `UtttError::is_connection`), the manager drops the connection, waits
+/// (with exponential backoff), reconnects and runs the operation again.
+/// Statements are prepared with `prepare_cached`, so they are prepared again
+/// on each new connection.
+///
+/// Operations are retried as a whole, so they must be safe to repeat. Reads
+/// are; so are writes, since the upsert never loses knowledge (see
+/// `WRITE_COMMAND`).
+
+use error::*;
+use postgres::Connection;
+use solver::db::*;
+use std::cell::{Cell, RefCell};
+use std::cmp::min;
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait between attempts, and how many to make.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Backoff {
+    /// The wait before the first retry, in milliseconds.
+    pub initial_ms: u64,
+    /// The longest wait between retries, in milliseconds.
+    pub max_ms: u64,
+    /// The number of retries before giving up. With the defaults, the manager
+    /// keeps trying for about ten minutes, which covers a database restart.
+    pub max_retries: u32,
+}
+
+impl Backoff {
+    pub fn new() -> Backoff {
+        Backoff { initial_ms: 100, max_ms: 30_000, max_retries: 25 }
+    }
+
+    /// Returns the wait before the given retry (counting from 0): the initial
+    /// wait, doubled for each earlier retry, up to the maximum.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 1u64.checked_shl(retry).unwrap_or(u64::max_value());
+        Duration::from_millis(
+            min(self.initial_ms.saturating_mul(factor), self.max_ms))
+    }
+}
+
+pub struct DbManager {
+    params: String,
+    backoff: Backoff,
+    conn: RefCell<Option<Connection>>,
+    reconnects: Cell<u32>,
+}
+
+impl DbManager {
+    /// Returns a manager that connects using `params` when first used.
+    pub fn new(params: &str, backoff: Backoff) -> DbManager {
+        DbManager {
+            params: params.to_string(),
+            backoff: backoff,
+            conn: RefCell::new(None),
+            reconnects: Cell::new(0),
+        }
+    }
+
+    /// Returns a manager that connects using `params`. Connects right away, so
+    /// that bad parameters are reported early.
+    pub fn connect(params: &str, backoff: Backoff) -> UtttResult<DbManager> {
+        let manager = DbManager::new(params, backoff);
+        *manager.conn.borrow_mut() = Some(db_connect(params)?);
+        Ok(manager)
+    }
+
+    /// Runs `f` with a connection, reconnecting and retrying on connection
+    /// errors. Returns the first other error, or the last connection error
+    /// once the retries run out.
+    pub fn run<T, F>(&self, f: F) -> UtttResult<T>
+        where F: Fn(&Connection) -> UtttResult<T> {
+        let mut retry: u32 = 0;
+        loop {
+            let result = match self.connection() {
+                Ok(()) => match *self.conn.borrow() {
+                    Some(ref conn) => f(conn),
+                    None => Err(UtttError::invariant("E85401", "no connection")),
+                },
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(ref e) if e.is_connection() &&
+                    retry < self.backoff.max_retries => {
+                    *self.conn.borrow_mut() = None;
+                    thread::sleep(self.backoff.delay(retry));
+                    retry += 1;
+                },
+                _ => return result,
+            }
+        }
+    }
+
+    /// Returns the number of times the manager has connected, other than by
+    /// `DbManager::connect`.
+    pub fn reconnects(&self) -> u32 {
+        self.reconnects.get()
+    }
+
+    /// Connects, unless already connected.
+    fn connection(&self) -> UtttResult<()> {
+        if self.conn.borrow().is_some() { return Ok(()); }
+        let conn = db_connect(self.params.as_str())?;
+        *self.conn.borrow_mut() = Some(conn);
+        self.reconnects.set(self.reconnects.get() + 1);
+        Ok(())
+    }
+}
@@ -2,5 +2,16 @@
 
 // Include these submodules into this module.
 pub use self::db::*;
+pub use self::manager::*;
+pub use self::query::*;
+pub use self::schema::*;
+pub use self::writer::*;
 
 mod db;
+mod manager;
+mod query;
+mod schema;
+mod writer;
+
+#[cfg(test)]
+mod tests;
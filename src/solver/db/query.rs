@@ -0,0 +1,211 @@
+/// Queries.
+///
+/// Finds stored solutions by their properties rather than by game: by play
+/// count, by outcome, and by turns. The filters use the 'plays', 'sol_turns'
+/// and 'unknown' columns, which are indexed (see `create_index_commands`).
+/// Results come in primary key order, a page at a time, so that a query can
+/// walk a large table without holding it in memory.
+
+use data::*;
+use error::*;
+use postgres::Connection;
+use postgres::types::ToSql;
+use solver::*;
+use solver::codec::*;
+use solver::db::*;
+use std::vec;
+
+/// The default number of rows fetched at a time.
+pub const QUERY_PAGE_SIZE: i64 = 1_000;
+
+/// Which outcomes a query matches.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutcomeFilter {
+    Any,
+    Unknown,
+    /// Wins and ties.
+    Decided,
+    Win(Player),
+    Tie,
+}
+
+/// A filter over the 'solutions' table. Ranges are inclusive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolutionQuery {
+    /// The (min, max) number of plays made in the game.
+    pub plays: Option<(i16, i16)>,
+    /// The (min, max) number of turns of the solutions.
+    pub turns: Option<(i16, i16)>,
+    pub outcome: OutcomeFilter,
+    /// The number of rows fetched at a time.
+    pub page_size: i64,
+}
+
+impl SolutionQuery {
+    /// Returns a query that matches every row.
+    pub fn new() -> SolutionQuery {
+        SolutionQuery {
+            plays: None,
+            turns: None,
+            outcome: OutcomeFilter::Any,
+            page_size: QUERY_PAGE_SIZE,
+        }
+    }
+}
+
+/// The number of rows with a given play count and turns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolutionCounts {
+    pub plays: i16,
+    pub sol_turns: i16,
+    pub decided: i64,
+    pub unknown: i64,
+}
+
+// == public API ===============================================================
+
+/// Returns an iterator over the games (and their solutions) that match the
+/// query. Rows are fetched a page at a time, as the iterator advances. The
+/// iterator stops after the first error.
+pub fn db_query<'a>(conn: &'a Connection, query: &SolutionQuery)
+                    -> QueryIter<'a> {
+    QueryIter {
+        conn: conn,
+        query: query.clone(),
+        after: None,
+        page: vec![].into_iter(),
+        done: false,
+    }
+}
+
+/// Returns up to `limit` matching games, whose keys sort after `after` (or
+/// from the start, if None). To get the next page, pass the key of the last
+/// game (see `game_columns_from`) as `after`.
+pub fn db_query_page(conn: &Connection, query: &SolutionQuery,
+                     after: Option<GameColumns>, limit: i64)
+                     -> UtttResult<Vec<(Game, Vec<Solution>)>> {
+    let (mut command, mut params) = where_clause(query);
+    if let Some((game_1, game_2, game_3)) = after {
+        let n = params.len();
+        command.push_str(&format!(
+            " AND (game_1, game_2, game_3) > (${}, ${}, ${})",
+            n + 1, n + 2, n + 3));
+        params.push(Box::new(game_1));
+        params.push(Box::new(game_2));
+        params.push(Box::new(game_3));
+    }
+    command.push_str(&format!(
+        " ORDER BY game_1, game_2, game_3 LIMIT ${}", params.len() + 1));
+    params.push(Box::new(limit));
+    let command = format!(
+        "SELECT game_1, game_2, game_3, plays, solutions \
+         FROM solutions WHERE {}", command);
+    let param_refs = params.iter()
+        .map(|param| &**param)
+        .collect::<Vec<&dyn ToSql>>();
+    let rows = conn.query(&command, &param_refs)
+        .map_err(|e| UtttError::from_postgres("E85501", e))?;
+    rows.iter()
+        .map(|row| {
+            let raw: RawRow = ((column(&row, 0, "E85502")?,
+                                column(&row, 1, "E85502")?,
+                                column(&row, 2, "E85502")?),
+                               column(&row, 3, "E85502")?,
+                               column(&row, 4, "E85502")?);
+            Ok((game_from_columns(raw.0)?, raw_solutions(&raw)?))
+        })
+        .collect::<UtttResult<Vec<(Game, Vec<Solution>)>>>()
+}
+
+/// Returns the number of matching rows for each (plays, sol_turns) pair, split
+/// into decided and unknown, ordered by plays and then turns.
+pub fn db_counts(conn: &Connection, query: &SolutionQuery)
+                 -> UtttResult<Vec<SolutionCounts>> {
+    let (command, params) = where_clause(query);
+    let command = format!(
+        "SELECT plays, sol_turns, \
+         COUNT(*) FILTER (WHERE NOT unknown), \
+         COUNT(*) FILTER (WHERE unknown) \
+         FROM solutions WHERE {} \
+         GROUP BY plays, sol_turns \
+         ORDER BY plays, sol_turns", command);
+    let param_refs = params.iter()
+        .map(|param| &**param)
+        .collect::<Vec<&dyn ToSql>>();
+    let rows = conn.query(&command, &param_refs)
+        .map_err(|e| UtttError::from_postgres("E85511", e))?;
+    rows.iter()
+        .map(|row| Ok(SolutionCounts {
+            plays: column(&row, 0, "E85512")?,
+            sol_turns: column(&row, 1, "E85512")?,
+            decided: column(&row, 2, "E85512")?,
+            unknown: column(&row, 3, "E85512")?,
+        }))
+        .collect::<UtttResult<Vec<SolutionCounts>>>()
+}
+
+/// An iterator over the results of a query; see `db_query`.
+pub struct QueryIter<'a> {
+    conn: &'a Connection,
+    query: SolutionQuery,
+    after: Option<GameColumns>,
+    page: vec::IntoIter<(Game, Vec<Solution>)>,
+    done: bool,
+}
+
+impl<'a> Iterator for QueryIter<'a> {
+    type Item = UtttResult<(Game, Vec<Solution>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.page.next() { return Some(Ok(item)); }
+        if self.done { return None; }
+        match db_query_page(self.conn, &self.query, self.after,
+                            self.query.page_size) {
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            },
+            Ok(page) => {
+                self.done = (page.len() as i64) < self.query.page_size;
+                self.after = page.last().map(|&(ref game, _)| {
+                    game_columns_from(game)
+                });
+                self.page = page.into_iter();
+                self.page.next().map(Ok)
+            },
+        }
+    }
+}
+
+// == helpers ==================================================================
+
+/// Returns the conditions of a WHERE clause for the query, and the parameters
+/// they refer to ($1, $2, ...).
+pub fn where_clause(query: &SolutionQuery) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut conditions: Vec<String> = vec![];
+    let mut params: Vec<Box<dyn ToSql>> = vec![];
+    for &(name, range) in [("plays", query.plays),
+                           ("sol_turns", query.turns)].iter() {
+        if let Some((min, max)) = range {
+            conditions.push(format!("{} BETWEEN ${} AND ${}",
+                                    name, params.len() + 1, params.len() + 2));
+            params.push(Box::new(min));
+            params.push(Box::new(max));
+        }
+    }
+    // The first solution's top two bits encode the outcome; see `sol_i16`.
+    let outcome = "(solutions[1] >> 14) & 3";
+    match query.outcome {
+        OutcomeFilter::Any => {},
+        OutcomeFilter::Unknown => conditions.push("unknown".to_string()),
+        OutcomeFilter::Decided => conditions.push("NOT unknown".to_string()),
+        OutcomeFilter::Win(Player::X) =>
+            conditions.push(format!("NOT unknown AND {} = 3", outcome)),
+        OutcomeFilter::Win(Player::O) =>
+            conditions.push(format!("NOT unknown AND {} = 2", outcome)),
+        OutcomeFilter::Tie =>
+            conditions.push(format!("NOT unknown AND {} = 1", outcome)),
+    }
+    if conditions.is_empty() { conditions.push("TRUE".to_string()); }
+    (conditions.join(" AND "), params)
+}
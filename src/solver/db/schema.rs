@@ -0,0 +1,154 @@
+/// Database schema.
+///
+/// The schema is versioned. Each migration brings the database from one
+/// version to the next, and is recorded in the 'schema_migrations' table, so
+/// `db_migrate` can be run every time the program starts: it only applies the
+/// migrations a database has not seen yet.
+///
+/// Migrations are append-only. To change the schema, add a migration at the
+/// end of `MIGRATIONS`; never edit one that has been released.
+
+use error::*;
+use postgres::{Connection, GenericConnection};
+use solver::db::*;
+
+/// Options for creating the schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaConfig {
+    /// The tablespace for the 'solutions' table and its indexes. If None, the
+    /// database's default tablespace is used. The tablespace must already
+    /// exist; see the README.
+    pub tablespace: Option<String>,
+}
+
+impl SchemaConfig {
+    /// Returns the default configuration (no tablespace).
+    pub fn new() -> SchemaConfig {
+        SchemaConfig { tablespace: None }
+    }
+
+    /// Returns a configuration that uses the given tablespace.
+    pub fn with_tablespace(tablespace: &str) -> SchemaConfig {
+        SchemaConfig { tablespace: Some(tablespace.to_string()) }
+    }
+}
+
+/// A step from schema version `version - 1` to `version`.
+pub struct Migration {
+    pub version: i32,
+    pub description: &'static str,
+    /// Returns the commands to run, in order, in one transaction.
+    pub commands: fn(&SchemaConfig) -> Vec<String>,
+}
+
+pub const MIGRATIONS: &'static [Migration] = &[
+    Migration {
+        version: 1,
+        description: "create the solutions table",
+        commands: migration_1,
+    },
+    Migration {
+        version: 2,
+        description: "create the solutions indexes",
+        commands: migration_2,
+    },
+];
+
+/// The schema version this program expects.
+pub fn schema_version() -> i32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+// == public API ===============================================================
+
+/// Returns the schema version of the database: the version of the last
+/// migration applied, or 0 if none have been.
+pub fn db_schema_version(conn: &Connection) -> UtttResult<i32> {
+    conn.execute(CREATE_MIGRATIONS_TABLE, &[])
+        .map_err(|e| UtttError::from_postgres("E85301", e))?;
+    select_version(conn, "E85302")
+}
+
+/// Applies any pending migrations and returns the resulting schema version.
+/// Each migration runs in its own transaction, together with the row that
+/// records it. Fails if the database is newer than this program.
+///
+/// Databases created before schema versioning (with the same table and
+/// indexes) are adopted as-is, because every migration command uses IF NOT
+/// EXISTS.
+pub fn db_migrate(conn: &Connection, config: &SchemaConfig)
+                  -> UtttResult<i32> {
+    let initial = db_schema_version(conn)?;
+    if initial > schema_version() {
+        return Err(UtttError::invariant(
+            "E85303", "database schema is newer than this program"));
+    }
+    let mut version = initial;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > initial) {
+        let pg = |code| move |e| UtttError::from_postgres(code, e);
+        let tx = conn.transaction().map_err(pg("E85304"))?;
+        // Serialize concurrent migrations; re-check under the lock.
+        tx.execute(LOCK_MIGRATIONS_TABLE, &[]).map_err(pg("E85305"))?;
+        if select_version(&tx, "E85306")? < migration.version {
+            for command in (migration.commands)(config) {
+                tx.execute(&command, &[]).map_err(pg("E85307"))?;
+            }
+            tx.execute(INSERT_MIGRATION,
+                       &[&migration.version, &migration.description])
+                .map_err(pg("E85308"))?;
+        }
+        tx.commit().map_err(pg("E85309"))?;
+        version = migration.version;
+    }
+    Ok(version)
+}
+
+/// Drops the 'solutions' table and the migration history.
+pub fn db_drop_schema(conn: &Connection) -> UtttResult<()> {
+    db_drop_table(conn)?;
+    conn.execute(DROP_MIGRATIONS_TABLE, &[])
+        .map_err(|e| UtttError::from_postgres("E85310", e))?;
+    Ok(())
+}
+
+// == migrations ===============================================================
+
+fn migration_1(config: &SchemaConfig) -> Vec<String> {
+    vec![create_table_command(tablespace(config))]
+}
+
+fn migration_2(config: &SchemaConfig) -> Vec<String> {
+    create_index_commands(tablespace(config))
+}
+
+fn select_version<C: GenericConnection>(conn: &C, code: &'static str)
+                                        -> UtttResult<i32> {
+    let rows = conn.query(SELECT_VERSION, &[])
+        .map_err(|e| UtttError::from_postgres(code, e))?;
+    Ok(rows.get(0).get::<_, i32>(0))
+}
+
+fn tablespace(config: &SchemaConfig) -> Option<&str> {
+    config.tablespace.as_ref().map(|name| name.as_str())
+}
+
+// == PostgreSQL command strings ===============================================
+
+const CREATE_MIGRATIONS_TABLE: &'static str =
+    "CREATE TABLE IF NOT EXISTS schema_migrations (\
+       version      INT          PRIMARY KEY, \
+       description  TEXT         NOT NULL, \
+       applied_at   TIMESTAMPTZ  NOT NULL DEFAULT now() \
+     )";
+
+const SELECT_VERSION: &'static str =
+    "SELECT COALESCE(MAX(version), 0) FROM schema_migrations";
+
+const LOCK_MIGRATIONS_TABLE: &'static str =
+    "LOCK TABLE schema_migrations IN EXCLUSIVE MODE";
+
+const INSERT_MIGRATION: &'static str =
+    "INSERT INTO schema_migrations (version, description) VALUES ($1, $2)";
+
+const DROP_MIGRATIONS_TABLE: &'static str =
+    "DROP TABLE IF EXISTS schema_migrations";
@@ -0,0 +1,74 @@
+use error::*;
+use solver::codec::*;
+use solver::db::*;
+
+#[test]
+fn test_migrations_are_ordered() {
+    for (i, migration) in MIGRATIONS.iter().enumerate() {
+        assert!(migration.version == i as i32 + 1);
+    }
+    assert!(schema_version() == MIGRATIONS.len() as i32);
+}
+
+#[test]
+fn test_tablespace_is_optional() {
+    let plain = create_table_command(None);
+    assert!(!plain.contains("TABLESPACE"));
+    let placed = create_table_command(Some("uttt_1"));
+    assert!(placed.contains("USING INDEX TABLESPACE \"uttt_1\""));
+    assert!(placed.ends_with(") TABLESPACE \"uttt_1\""));
+    for command in create_index_commands(Some("a\"b")) {
+        assert!(command.ends_with(" TABLESPACE \"a\"\"b\""));
+    }
+    for command in create_index_commands(None) {
+        assert!(!command.contains("TABLESPACE"));
+    }
+}
+
+#[test]
+fn test_decode_errors() {
+    let bad_location: i16 = 100 << 7;
+    match solution_from(bad_location, None) {
+        Err(UtttError::Decode { code: "E85101", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+    let play_without_player: i16 = 40 << 7 | 3;
+    match solution_from(play_without_player, None) {
+        Err(UtttError::Decode { code: "E85091", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+    match next_player_from(3 << 28) {
+        Err(UtttError::Decode { code: "E85093", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+    match turns_and_unknown(&vec![]) {
+        Err(UtttError::Invariant { code: "E85083", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_backoff_delay() {
+    use std::time::Duration;
+    let backoff = Backoff { initial_ms: 100, max_ms: 1_000, max_retries: 3 };
+    assert!(backoff.delay(0) == Duration::from_millis(100));
+    assert!(backoff.delay(1) == Duration::from_millis(200));
+    assert!(backoff.delay(3) == Duration::from_millis(800));
+    assert!(backoff.delay(4) == Duration::from_millis(1_000));
+    assert!(backoff.delay(200) == Duration::from_millis(1_000));
+}
+
+#[test]
+fn test_query_where_clause() {
+    use data::Player;
+    let (all, params) = where_clause(&SolutionQuery::new());
+    assert!(all == "TRUE" && params.is_empty());
+    let mut query = SolutionQuery::new();
+    query.plays = Some((10, 20));
+    query.turns = Some((3, 3));
+    query.outcome = OutcomeFilter::Win(Player::O);
+    let (clause, params) = where_clause(&query);
+    assert!(clause == "plays BETWEEN $1 AND $2 AND sol_turns BETWEEN $3 AND $4 \
+                       AND NOT unknown AND (solutions[1] >> 14) & 3 = 2");
+    assert!(params.len() == 4);
+}
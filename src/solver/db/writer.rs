@@ -0,0 +1,191 @@
+/// Database writer.
+///
+/// A background thread that persists solutions on behalf of the SSD device, so
+/// that the search does not wait on PostgreSQL round-trips. The device sends
+/// writes over a bounded channel; when the channel is full, the device blocks
+/// until the writer catches up (back-pressure). The writer drains the channel
+/// in batches and writes each batch with `db_write_batch`.
+///
+/// The writer opens its own connection, since a `Connection` cannot be shared
+/// across threads. It reconnects as needed (see `DbManager`). Writes that still
+/// fail with a connection error stay in a buffer and are retried, both when
+/// more messages arrive and every `RETRY_INTERVAL_MS`, so they are not lost
+/// while the database is down. A write that fails for another reason (a bad
+/// row) is skipped, and the writer carries on.
+
+use data::*;
+use error::*;
+use solver::Solution;
+use solver::db::*;
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError,
+                      Sender, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often to retry buffered writes while no messages arrive.
+pub const RETRY_INTERVAL_MS: u64 = 5_000;
+
+pub struct DbWriter {
+    sender: SyncSender<Message>,
+    handle: Option<JoinHandle<()>>,
+}
+
+enum Message {
+    /// Write solutions for a game.
+    Write(Game, Vec<Solution>),
+    /// Reply once all previously sent writes are persisted.
+    Flush(Sender<UtttResult<u32>>),
+    /// Persist all previously sent writes, then exit.
+    Stop,
+}
+
+// == public API ===============================================================
+
+/// Spawns a writer thread with its own connection. `capacity` bounds the
+/// number of queued writes; `batch_size` bounds the number of new writes
+/// passed to each `db_write_batch` call (in addition to any buffered ones).
+pub fn db_writer_spawn(params: String, capacity: usize, batch_size: usize,
+                       backoff: Backoff) -> DbWriter {
+    let (sender, receiver) = sync_channel(capacity);
+    let handle = thread::Builder::new()
+        .name("db_writer".to_string())
+        .spawn(move || run(params, receiver, batch_size, backoff))
+        .expect("E85201");
+    DbWriter { sender: sender, handle: Some(handle) }
+}
+
+impl DbWriter {
+    /// Queues a write. Blocks while the queue is full. Fails if the writer
+    /// thread has stopped.
+    pub fn write(&self, game: &Game, sols: &Vec<Solution>) -> UtttResult<()> {
+        self.sender.send(Message::Write(*game, sols.clone()))
+            .map_err(|_| stopped("E85202"))
+    }
+
+    /// Blocks until every queued write is persisted. Returns the number of
+    /// rows written since the previous flush. Fails if writes could not be
+    /// persisted, with their number: after a connection error, they stay
+    /// buffered and are retried; after another error, they were skipped.
+    pub fn flush(&self) -> UtttResult<u32> {
+        let (reply_sender, reply_receiver) = channel();
+        self.sender.send(Message::Flush(reply_sender))
+            .map_err(|_| stopped("E85203"))?;
+        reply_receiver.recv().map_err(|_| stopped("E85204"))?
+    }
+}
+
+impl Drop for DbWriter {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Message::Stop);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// == writer thread ============================================================
+
+/// Runs until stopped. Writes that fail with a connection error stay buffered
+/// and are retried; a flush meanwhile fails, saying how many writes are not
+/// persisted (and will be lost if the program exits). A batch that fails with
+/// any other error is written again one game at a time, so that only the games
+/// that fail are skipped; the next flush fails, saying how many were.
+fn run(params: String, receiver: Receiver<Message>, batch_size: usize,
+       backoff: Backoff) {
+    let manager = DbManager::new(&params, backoff);
+    let mut pending: Vec<(Game, Vec<Solution>)> = Vec::new();
+    let mut count: u32 = 0;
+    let mut skipped: Option<(u32, UtttError)> = None;
+    loop {
+        let mut replies: Vec<Sender<UtttResult<u32>>> = Vec::new();
+        let mut stop = false;
+        // Block for one message (or, with writes to retry, until the retry
+        // interval passes), then take whatever else is already queued.
+        let (mut next, disconnected) = if pending.is_empty() {
+            match receiver.recv() {
+                Ok(message) => (Some(message), false),
+                Err(_) => (None, true),
+            }
+        } else {
+            let timeout = Duration::from_millis(RETRY_INTERVAL_MS);
+            match receiver.recv_timeout(timeout) {
+                Ok(message) => (Some(message), false),
+                Err(RecvTimeoutError::Timeout) => (None, false),
+                Err(RecvTimeoutError::Disconnected) => (None, true),
+            }
+        };
+        let buffered = pending.len();
+        while let Some(message) = next {
+            match message {
+                Message::Write(game, sols) => pending.push((game, sols)),
+                Message::Flush(reply) => replies.push(reply),
+                Message::Stop => stop = true,
+            }
+            next = if pending.len() - buffered < batch_size && !stop {
+                receiver.try_recv().ok()
+            } else {
+                None
+            };
+        }
+        let (written, result) =
+            write_pending(&manager, &mut pending, &mut skipped);
+        count += written;
+        for reply in replies {
+            let _ = reply.send(match (&result, skipped.take()) {
+                (&Err(ref e), _) => Err(UtttError::Connection {
+                    code: "E85205",
+                    message: format!("{} writes are not persisted: {}",
+                                     pending.len(), e),
+                }),
+                (&Ok(()), Some((n, e))) => Err(UtttError::Query {
+                    code: "E85206",
+                    message: format!("{} writes were skipped: {}", n, e),
+                }),
+                (&Ok(()), None) => Ok(count),
+            });
+            if result.is_ok() { count = 0; }
+        }
+        if stop || disconnected { break; }
+    }
+}
+
+/// Writes the pending writes and removes the ones that are done. Returns the
+/// number of rows written, and the connection error that stopped it, if any;
+/// the remaining writes stay pending. Games whose write fails with another
+/// error are removed too, and counted in `skipped`, with the last such error.
+fn write_pending(manager: &DbManager, pending: &mut Vec<(Game, Vec<Solution>)>,
+                 skipped: &mut Option<(u32, UtttError)>)
+                 -> (u32, UtttResult<()>) {
+    if pending.is_empty() { return (0, Ok(())); }
+    match manager.run(|conn| db_write_batch(conn, pending)) {
+        Ok(n) => {
+            pending.clear();
+            return (n as u32, Ok(()));
+        },
+        Err(ref e) if e.is_connection() => return (0, Err(e.clone())),
+        Err(_) => {},
+    }
+    let mut count: u32 = 0;
+    for i in 0 .. pending.len() {
+        match manager.run(|conn| db_write_batch(conn, &pending[i .. i + 1])) {
+            Ok(n) => count += n as u32,
+            Err(ref e) if e.is_connection() => {
+                pending.drain(.. i);
+                return (count, Err(e.clone()));
+            },
+            Err(e) => {
+                let n = skipped.as_ref().map_or(0, |&(n, _)| n);
+                *skipped = Some((n + 1, e));
+            },
+        }
+    }
+    pending.clear();
+    (count, Ok(()))
+}
+
+fn stopped(code: &'static str) -> UtttError {
+    UtttError::Connection {
+        code: code,
+        message: "the background writer has stopped".to_string(),
+    }
+}
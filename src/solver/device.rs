@@ -1,25 +1,30 @@
 use data::*;
-use postgres::Connection;
-use postgres::stmt::Statement;
+use error::*;
 use solver::*;
+use solver::db::{DbManager, DbWriter};
+use solver::file::SolutionFile;
 use solver::ram_cache::*;
 use std::cell::RefCell;
 
 pub const MAX_DEPTH: usize = 81;
 
 /// Device capabilities.
-pub struct Device<'c> {
+pub struct Device {
+    /// A short name, for reports.
+    pub name: &'static str,
+
     /// Compute one or more solutions to the specified depth.
-    pub compute: fn(&Game, Count, &Stack) -> Vec<Solution>,
+    pub compute: fn(&Game, Count, &Stack) -> UtttResult<Vec<Solution>>,
 
     /// Read one or more solutions from the device.
-    pub read: fn(&Device, &Game) -> Vec<Solution>,
+    pub read: fn(&Device, &Game) -> UtttResult<Vec<Solution>>,
 
     /// Write one or more solution to the device.
-    pub write: fn(&Device, &Game, &Vec<Solution>) -> bool,
+    pub write: fn(&Device, &Game, &Vec<Solution>) -> UtttResult<()>,
 
-    /// Flush any cached solutions to the device.
-    pub flush: fn(&Device) -> (bool, u32),
+    /// Flush any cached solutions to the device. Returns the number of
+    /// solutions flushed.
+    pub flush: fn(&Device) -> UtttResult<u32>,
 
     /// Supports the 'compute' function?
     pub has_compute: bool,
@@ -39,15 +44,22 @@ pub struct Device<'c> {
     /// An optional (large) RAM cache.
     pub cache_2: Option<RefCell<RamCache>>,
 
-    /// An array where the index=solver_depth and value=count.
+    /// Counters of the work done on the device; see `DeviceMetrics`.
+    pub counters: RefCell<Counters>,
+
+    /// An optional array of admitted writes, where the index=solver_depth and
+    /// value=count. Used by the admission policy.
     pub stats: Option<RefCell<[u32; MAX_DEPTH]>>,
 
-    /// An optional PostgreSQL database connection.
-    pub conn: Option<&'c Connection>,
+    /// An optional write admission policy, with its counters.
+    pub admission: Option<RefCell<Admission>>,
+
+    /// An optional (reconnecting) PostgreSQL connection for device reads.
+    pub db: Option<DbManager>,
 
-    /// An optional PostgreSQL prepared statement for device reads.
-    pub read_stmt: Option<Statement<'c>>,
+    /// An optional background writer for device writes.
+    pub writer: Option<DbWriter>,
 
-    /// An optional PostgreSQL prepared statement for device writes.
-    pub write_stmt: Option<Statement<'c>>,
+    /// An optional (read-only, memory-mapped) solution file.
+    pub file: Option<SolutionFile>,
 }
@@ -0,0 +1,94 @@
+/// Write Admission.
+///
+/// When a solution leaves the SSD device's cache_1, an admission policy decides
+/// if it is worth persisting. Every policy except `Always` admits all wins and
+/// ties; they differ in how they treat unknown outcomes.
+
+use data::*;
+use random::hash_fraction;
+use solver::*;
+
+pub const LOW_MARK: u32 = 200;
+pub const MULTIPLIER: u32 = 20000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdmissionPolicy {
+    /// Admit every write.
+    Always,
+    /// Admit wins and ties; reject unknowns.
+    DecidedOnly,
+    /// Admit wins and ties. Admit an unknown unless its depth already has many
+    /// more writes than the least-written depth; see `threshold`.
+    DepthBalanced { low_mark: u32, multiplier: u32 },
+    /// Admit wins and ties. Admit a fraction (`rate`, from 0.0 to 1.0) of
+    /// unknowns. The choice is made by hashing the game, so a given game is
+    /// either always admitted or always rejected.
+    Sampling { rate: f64 },
+}
+
+/// An admission policy and its counters.
+pub struct Admission {
+    pub policy: AdmissionPolicy,
+    pub accepted: u64,
+    pub rejected: u64,
+}
+
+impl Admission {
+    pub fn new(policy: AdmissionPolicy) -> Admission {
+        Admission { policy: policy, accepted: 0, rejected: 0 }
+    }
+
+    /// Applies the policy and updates the counters. `stats` has the number of
+    /// admitted writes, indexed by turns.
+    pub fn admit(&mut self, game: &Game, turns: i16, unknown: bool,
+                 stats: &[u32; MAX_DEPTH]) -> bool {
+        let admit = self.policy.admit(game, turns, unknown, stats);
+        if admit { self.accepted += 1; } else { self.rejected += 1; }
+        admit
+    }
+}
+
+impl AdmissionPolicy {
+    /// Returns the default policy, `DepthBalanced` with `LOW_MARK` and
+    /// `MULTIPLIER`.
+    pub fn default_policy() -> AdmissionPolicy {
+        AdmissionPolicy::DepthBalanced {
+            low_mark: LOW_MARK,
+            multiplier: MULTIPLIER,
+        }
+    }
+
+    /// Should the SSD write a solution of 'turns' turns given the current
+    /// statistical information?
+    pub fn admit(self, game: &Game, turns: i16, unknown: bool,
+                 stats: &[u32; MAX_DEPTH]) -> bool {
+        match self {
+            AdmissionPolicy::Always => true,
+            _ if !unknown => true,
+            AdmissionPolicy::DecidedOnly => false,
+            AdmissionPolicy::DepthBalanced { low_mark, multiplier } => {
+                match nonzero_min(stats) {
+                    Some(min) => {
+                        stats[turns as usize] <
+                            threshold(min, low_mark, multiplier)
+                    },
+                    None => true,
+                }
+            },
+            AdmissionPolicy::Sampling { rate } => hash_fraction(game) < rate,
+        }
+    }
+}
+
+/// Returns the smallest nonzero value in the stats array, if any.
+fn nonzero_min(stats: &[u32; MAX_DEPTH]) -> Option<u32> {
+    stats.iter().filter(|&&val| val != 0).cloned().min()
+}
+
+fn threshold(min: u32, low_mark: u32, multiplier: u32) -> u32 {
+    if min < low_mark {
+        low_mark.saturating_mul(multiplier)
+    } else {
+        min.saturating_mul(multiplier)
+    }
+}
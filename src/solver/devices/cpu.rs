@@ -1,13 +1,16 @@
 /// CPU Device.
 
 use data::*;
+use error::*;
 use solver::*;
+use std::cell::RefCell;
 
 pub struct CPU {}
 
 impl CPU {
-    pub fn new<'c>() -> Device<'c> {
+    pub fn new() -> Device {
         Device {
+            name: "CPU",
             compute: CPU::compute,
             read: CPU::read,
             write: CPU::write,
@@ -18,29 +21,33 @@ impl CPU {
             has_flush: false,
             cache_1: None,
             cache_2: None,
+            counters: RefCell::new(Counters::new()),
             stats: None,
-            conn: None,
-            read_stmt: None,
-            write_stmt: None,
+            admission: None,
+            db: None,
+            writer: None,
+            file: None,
         }
     }
 
-    fn compute(game: &Game, depth: Count, stack: &Stack) -> Vec<Solution> {
+    fn compute(game: &Game, depth: Count, stack: &Stack)
+               -> UtttResult<Vec<Solution>> {
         game.solve(depth, stack)
     }
 
     #[allow(unused_variables)]
-    fn read(device: &Device, game: &Game) -> Vec<Solution> {
-        panic!("E6301");
+    fn read(device: &Device, game: &Game) -> UtttResult<Vec<Solution>> {
+        Err(UtttError::capability("E6301", "read"))
     }
 
     #[allow(unused_variables)]
-    fn write(device: &Device, game: &Game, sols: &Vec<Solution>) -> bool {
-        panic!("E6302");
+    fn write(device: &Device, game: &Game, sols: &Vec<Solution>)
+             -> UtttResult<()> {
+        Err(UtttError::capability("E6302", "write"))
     }
 
     #[allow(unused_variables)]
-    fn flush(device: &Device) -> (bool, u32) {
-        panic!("E6303");
+    fn flush(device: &Device) -> UtttResult<u32> {
+        Err(UtttError::capability("E6303", "flush"))
     }
 }
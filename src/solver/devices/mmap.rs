@@ -0,0 +1,59 @@
+/// MMAP Device.
+
+use data::*;
+use error::*;
+use solver::*;
+use std::cell::RefCell;
+use solver::file::*;
+
+/// A read-only device backed by a memory-mapped solution file. It has no RAM
+/// caches of its own; see the 'solver/file' module.
+pub struct MMAP {}
+
+impl MMAP {
+    pub fn new(file: SolutionFile) -> Device {
+        Device {
+            name: "MMAP",
+            compute: MMAP::compute,
+            read: MMAP::read,
+            write: MMAP::write,
+            flush: MMAP::flush,
+            has_compute: false,
+            has_read: true,
+            has_write: false,
+            has_flush: false,
+            cache_1: None,
+            cache_2: None,
+            counters: RefCell::new(Counters::new()),
+            stats: None,
+            admission: None,
+            db: None,
+            writer: None,
+            file: Some(file),
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn compute(game: &Game, depth: Count, stack: &Stack)
+               -> UtttResult<Vec<Solution>> {
+        Err(UtttError::capability("E6501", "compute"))
+    }
+
+    fn read(device: &Device, game: &Game) -> UtttResult<Vec<Solution>> {
+        match device.file {
+            Some(ref file) => file_read(file, game),
+            None => Err(UtttError::invariant("E6502", "no solution file")),
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn write(device: &Device, game: &Game, sols: &Vec<Solution>)
+             -> UtttResult<()> {
+        Err(UtttError::capability("E6503", "write"))
+    }
+
+    #[allow(unused_variables)]
+    fn flush(device: &Device) -> UtttResult<u32> {
+        Err(UtttError::capability("E6504", "flush"))
+    }
+}
@@ -1,7 +1,13 @@
 /// Solver Devices.
 
+pub use self::admission::*;
 pub use self::cpu::*;
+pub use self::mmap::*;
+pub use self::ram::*;
 pub use self::ssd::*;
 
+mod admission;
 mod cpu;
+mod mmap;
+mod ram;
 mod ssd;
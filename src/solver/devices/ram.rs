@@ -0,0 +1,75 @@
+/// RAM Device.
+
+use data::*;
+use error::*;
+use solver::*;
+use solver::ram_cache::*;
+use std::cell::RefCell;
+
+/// A device that keeps solutions in a RAM cache only: it needs no database or
+/// file, and forgets everything when the program exits.
+pub struct RAM {}
+
+/// The default capacity of the cache; about 95 MB (see the table in the
+/// 'solver/devices/ssd' module).
+pub const RAM_CACHE_CAP: usize = 1_000_000;
+
+impl RAM {
+    pub fn new(capacity: usize) -> Device {
+        Device {
+            name: "RAM",
+            compute: RAM::compute,
+            read: RAM::read,
+            write: RAM::write,
+            flush: RAM::flush,
+            has_compute: false,
+            has_read: true,
+            has_write: true,
+            has_flush: false,
+            cache_1: Some(RefCell::new(cache_new(capacity))),
+            cache_2: None,
+            counters: RefCell::new(Counters::new()),
+            stats: None,
+            admission: None,
+            db: None,
+            writer: None,
+            file: None,
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn compute(game: &Game, depth: Count, stack: &Stack)
+               -> UtttResult<Vec<Solution>> {
+        Err(UtttError::capability("E6201", "compute"))
+    }
+
+    fn read(device: &Device, game: &Game) -> UtttResult<Vec<Solution>> {
+        let mut_cache = &mut *cache(device)?.borrow_mut();
+        Ok(cache_get(mut_cache, game))
+    }
+
+    /// Write to the cache, evicting the least-recently-used solutions if it
+    /// is full.
+    fn write(device: &Device, game: &Game, solutions: &Vec<Solution>)
+             -> UtttResult<()> {
+        let mut_cache = &mut *cache(device)?.borrow_mut();
+        if mut_cache.len() == mut_cache.capacity() &&
+            !mut_cache.contains_key(game) {
+            if cache_remove_lru(mut_cache).is_some() {
+                device.counters.borrow_mut().evictions += 1;
+            }
+        }
+        cache_insert(mut_cache, game, solutions);
+        Ok(())
+    }
+
+    #[allow(unused_variables)]
+    fn flush(device: &Device) -> UtttResult<u32> {
+        Err(UtttError::capability("E6202", "flush"))
+    }
+}
+
+fn cache<'a>(device: &'a Device) -> UtttResult<&'a RefCell<RamCache>> {
+    device.cache_1.as_ref().ok_or_else(|| UtttError::invariant(
+        "E6203", "RAM device has no cache"))
+}
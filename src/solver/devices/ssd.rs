@@ -1,8 +1,7 @@
 /// SSD Device.
 
 use data::*;
-use postgres::Connection;
-use postgres::stmt::Statement;
+use error::*;
 use solver::*;
 use solver::db::*;
 use solver::ram_cache::*;
@@ -10,7 +9,7 @@ use std::cell::RefCell;
 
 pub struct SSD {}
 
-// Cache Sizes
+// Cache Sizes (defaults; see `SsdConfig`)
 //
 // If each item requires 100 bytes (just a guess) then:
 //
@@ -25,18 +24,63 @@ pub struct SSD {}
 pub const CACHE_1_CAP: usize =      5_000;
 pub const CACHE_2_CAP: usize = 50_000_000;
 
-pub const CREATE_TABLE: bool = false;
-pub const CREATE_INDEXES: bool = false;
-pub const TRUNCATE_TABLE: bool = false;
+// Background Writer (defaults; see `SsdConfig`)
+//
+// Writes beyond WRITE_QUEUE_CAP block the search until the writer catches up.
+// The writer commits up to WRITE_BATCH_SIZE writes per transaction.
+pub const WRITE_QUEUE_CAP: usize = 20_000;
+pub const WRITE_BATCH_SIZE: usize = 1_000;
+
+/// Runtime configuration for an SSD device.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SsdConfig {
+    /// Capacity of the (small) write-delaying RAM cache.
+    pub cache_1_cap: usize,
+    /// Capacity of the (large) general purpose RAM cache.
+    pub cache_2_cap: usize,
+    /// Capacity of the background writer's queue.
+    pub write_queue_cap: usize,
+    /// Maximum number of writes per batch.
+    pub write_batch_size: usize,
+    /// Decides which solutions leaving cache_1 are persisted.
+    pub admission: AdmissionPolicy,
+    /// Reconnect schedule, for both reads and the background writer.
+    pub backoff: Backoff,
+}
+
+impl SsdConfig {
+    /// Returns the default configuration.
+    pub fn new() -> SsdConfig {
+        SsdConfig {
+            cache_1_cap: CACHE_1_CAP,
+            cache_2_cap: CACHE_2_CAP,
+            write_queue_cap: WRITE_QUEUE_CAP,
+            write_batch_size: WRITE_BATCH_SIZE,
+            admission: AdmissionPolicy::default_policy(),
+            backoff: Backoff::new(),
+        }
+    }
+}
 
 impl SSD {
-    pub fn new<'c>(conn: &'c Connection) -> Device<'c> {
-        let read_stmt: Statement = db_read_stmt(conn);
-        let write_stmt: Statement = db_write_stmt(conn);
-        if CREATE_TABLE { db_create_table(conn); }
-        if CREATE_INDEXES { db_create_indexes(conn); }
-        if TRUNCATE_TABLE { db_truncate_table(conn); }
-        Device {
+    /// Returns an SSD device with the default configuration. The device owns
+    /// two connections, both made using `params` and both reconnecting as
+    /// needed: one for reads, and one for the background writer. The schema
+    /// must be up to date; see `db_migrate`.
+    pub fn new(params: &str) -> UtttResult<Device> {
+        SSD::with_config(params, &SsdConfig::new())
+    }
+
+    /// Returns an SSD device with the given configuration. Fails if cache_1
+    /// cannot hold a solution, since every write goes through it.
+    pub fn with_config(params: &str, config: &SsdConfig)
+                       -> UtttResult<Device> {
+        if config.cache_1_cap == 0 {
+            return Err(UtttError::usage("E1808", "cache_1 needs a capacity"));
+        }
+        let db = DbManager::connect(params, config.backoff)?;
+        Ok(Device {
+            name: "SSD",
             compute: SSD::compute,
             read: SSD::read,
             write: SSD::write,
@@ -45,101 +89,79 @@ impl SSD {
             has_read: true,
             has_write: true,
             has_flush: true,
-            cache_1: Some(RefCell::new(cache_new(CACHE_1_CAP))),
-            cache_2: Some(RefCell::new(cache_new(CACHE_2_CAP))),
+            cache_1: Some(RefCell::new(cache_new(config.cache_1_cap))),
+            cache_2: Some(RefCell::new(cache_new(config.cache_2_cap))),
+            counters: RefCell::new(Counters::new()),
             stats: Some(RefCell::new([0; MAX_DEPTH])),
-            conn: Some(conn),
-            read_stmt: Some(read_stmt),
-            write_stmt: Some(write_stmt),
-        }
+            admission: Some(RefCell::new(Admission::new(config.admission))),
+            db: Some(db),
+            writer: Some(db_writer_spawn(params.to_string(),
+                                         config.write_queue_cap,
+                                         config.write_batch_size,
+                                         config.backoff)),
+            file: None,
+        })
     }
 
     #[allow(unused_variables)]
-    fn compute(game: &Game, depth: Count, stack: &Stack) -> Vec<Solution> {
-        panic!("E18XX");
+    fn compute(game: &Game, depth: Count, stack: &Stack)
+               -> UtttResult<Vec<Solution>> {
+        Err(UtttError::capability("E1801", "compute"))
     }
 
     /// Try reading from cache_1, then cache_2, then from the SSD.
-    fn read(device: &Device, game: &Game) -> Vec<Solution> {
-        match device.cache_1 {
-            None => panic!("E18XX"),
-            Some(ref cache_1) => {
-                let mut mut_cache_1 = &mut *cache_1.borrow_mut();
-                let solutions_1 = cache_get(mut_cache_1, game);
-                if solutions_1.is_empty() {
-                    match device.cache_2 {
-                        None => panic!("E18XX"),
-                        Some(ref cache_2) => {
-                            let mut_cache_2 = &mut *cache_2.borrow_mut();
-                            let solutions_2 = cache_get(mut_cache_2, game);
-                            if solutions_2.is_empty() {
-                                match device.read_stmt {
-                                    Some(ref stmt) => db_read(stmt, game),
-                                    _ => panic!("E18XX"),
-                                }
-                            } else {
-                                solutions_2
-                            }
-                        },
-                    }
-                } else { solutions_1 }
-            },
+    fn read(device: &Device, game: &Game) -> UtttResult<Vec<Solution>> {
+        let mut_cache_1 = &mut *cache_1(device)?.borrow_mut();
+        let solutions_1 = cache_get(mut_cache_1, game);
+        if !solutions_1.is_empty() { return Ok(solutions_1); }
+        let mut_cache_2 = &mut *cache_2(device)?.borrow_mut();
+        let solutions_2 = cache_get(mut_cache_2, game);
+        if !solutions_2.is_empty() { return Ok(solutions_2); }
+        match device.db {
+            Some(ref db) => db.run(|conn| db_read(conn, game)),
+            None => Err(missing("E1802", "database connection")),
         }
     }
 
-    /// Write to cache_1. If it overflows, write to cache_2 and SSD. Returns
-    /// true unless the write to SSD fails.
-    fn write(device: &Device, game: &Game, solutions: &Vec<Solution>) -> bool {
-        match device.cache_1 {
-            None => panic!("E18XX"),
-            Some(ref cache_1) => {
-                let mut mut_cache_1 = &mut *cache_1.borrow_mut();
-                // If cache_1 would overflow, remove and write to
-                // cache_2 and SSD.
-                let result = if mut_cache_1.len() == CACHE_1_CAP {
-                    match cache_remove_lru(mut_cache_1) {
-                        None => panic!("E18XX"),
-                        Some((game_, solutions_)) => {
-                            match device.cache_2 {
-                                None => panic!("E18XX"),
-                                Some(ref cache_2) => {
-                                    let mut mut_cache_2 = &mut *cache_2.borrow_mut();
-                                    cache_insert(mut_cache_2, &game_, &solutions_);
-                                    maybe_write(device, &game_, &solutions_)
-                                },
-                            }
-                        },
-                    }
-                } else { true };
-                cache_insert(mut_cache_1, game, solutions);
-                result
-            },
-        }
+    /// Write to cache_1. If it overflows, write to cache_2 and queue a write to
+    /// SSD. Fails if the background writer has stopped; the new solutions are
+    /// still cached.
+    fn write(device: &Device, game: &Game, solutions: &Vec<Solution>)
+             -> UtttResult<()> {
+        let mut_cache_1 = &mut *cache_1(device)?.borrow_mut();
+        // If cache_1 would overflow, remove and write to cache_2 and SSD.
+        let result = if mut_cache_1.len() == mut_cache_1.capacity() {
+            match cache_remove_lru(mut_cache_1) {
+                None => Err(UtttError::invariant("E1803", "cache_1 is empty")),
+                Some((game_, solutions_)) => {
+                    let mut_cache_2 = &mut *cache_2(device)?.borrow_mut();
+                    // Moving to a full cache_2 evicts its LRU entry, which is
+                    // then only on the SSD.
+                    let evicted = if mut_cache_2.len() ==
+                        mut_cache_2.capacity() &&
+                        !mut_cache_2.contains_key(&game_) { 2 } else { 1 };
+                    device.counters.borrow_mut().evictions += evicted;
+                    cache_insert(mut_cache_2, &game_, &solutions_);
+                    maybe_write(device, &game_, &solutions_)
+                },
+            }
+        } else { Ok(()) };
+        cache_insert(mut_cache_1, game, solutions);
+        result
     }
 
-    // Drain cache_1 and write to SSD. This is useful if the program gets
-    // interrupted. Returns a (success, write_count) tuple.
-    pub fn flush(device: &Device) -> (bool, u32) {
-        match device.cache_1 {
-            None => panic!("E18XX"),
-            Some(ref cache) => {
-                let mut mut_cache = &mut *cache.borrow_mut();
-                let mut success = true;
-                let mut count: u32 = 0;
-                loop {
-                    match cache_remove_lru(mut_cache) {
-                        None => break,
-                        Some((game, solutions)) => {
-                            count += 1;
-                            if !maybe_write(device, &game, &solutions) {
-                                success = false;
-                            }
-                        },
-                    }
-                }
-                (success, count)
-            },
+    // Drain cache_1 and write to SSD, then wait for the background writer to
+    // persist everything queued. This is useful if the program gets
+    // interrupted. Returns the number of entries drained from cache_1.
+    pub fn flush(device: &Device) -> UtttResult<u32> {
+        let mut_cache = &mut *cache_1(device)?.borrow_mut();
+        let mut count: u32 = 0;
+        while let Some((game, solutions)) = cache_remove_lru(mut_cache) {
+            count += 1;
+            maybe_write(device, &game, &solutions)?;
         }
+        writer(device)?.flush()?;
+        Ok(count)
     }
 
     pub fn cache_1_len(device: &Device) -> usize {
@@ -152,6 +174,25 @@ impl SSD {
         }
     }
 
+    /// Returns the number of times the read connection was replaced.
+    pub fn reconnects(device: &Device) -> u32 {
+        match device.db {
+            None => 0,
+            Some(ref db) => db.reconnects(),
+        }
+    }
+
+    /// Returns the admission policy's (accepted, rejected) counters.
+    pub fn admission_counts(device: &Device) -> (u64, u64) {
+        match device.admission {
+            None => (0, 0),
+            Some(ref admission) => {
+                let admission = & *admission.borrow();
+                (admission.accepted, admission.rejected)
+            },
+        }
+    }
+
     pub fn cache_2_len(device: &Device) -> usize {
         match device.cache_2 {
             None => 0,
@@ -163,58 +204,36 @@ impl SSD {
     }
 }
 
-fn maybe_write(device: &Device, game: &Game, sols: &Vec<Solution>) -> bool {
-    match device.stats {
-        Some(ref stats) => {
-            let (turns, unknown) = turns_and_unknown(sols);
-            let mut mut_stats = &mut *stats.borrow_mut();
-            if save_to_db(turns, unknown, mut_stats) {
+fn maybe_write(device: &Device, game: &Game, sols: &Vec<Solution>)
+               -> UtttResult<()> {
+    match (&device.stats, &device.admission) {
+        (&Some(ref stats), &Some(ref admission)) => {
+            let (turns, unknown) = turns_and_unknown(sols)?;
+            let mut_stats = &mut *stats.borrow_mut();
+            let mut_admission = &mut *admission.borrow_mut();
+            if mut_admission.admit(game, turns, unknown, mut_stats) {
                 mut_stats[turns as usize] += 1;
-                match device.write_stmt {
-                    Some(ref stmt) => db_write(stmt, game, sols),
-                    _ => panic!("E18XX"),
-                }
-            } else { true }
+                writer(device)?.write(game, sols)
+            } else { Ok(()) }
         },
-        None => panic!("E18XX"),
+        _ => Err(missing("E1804", "stats or admission policy")),
     }
 }
 
-/// Should the SSD write a solution of 'depth' turns given the current
-/// statistical information?
-fn save_to_db(turns: i16, unknown: bool, stats: &mut [u32; MAX_DEPTH]) -> bool {
-    // Find maximum and minimum of stats array.
-    let mut max: u32 = 0;
-    let mut nonzero_min: Option<u32> = None;
-    for val in stats.into_iter() {
-        if *val != 0 {
-            if *val > max { max = *val; }
-            match nonzero_min {
-                Some(min) => if *val < min { nonzero_min = Some(*val); },
-                None => nonzero_min = Some(*val),
-            }
-        }
-    }
-    // Determine to save or not based on 'stats' and threshold function.
-    match nonzero_min {
-        Some(min) => {
-            if unknown {
-                stats[turns as usize] < threshold(min)
-            } else {
-                true
-            }
-        }
-        None => true,
-    }
+// == helpers: device fields ===================================================
+
+fn cache_1<'a>(device: &'a Device) -> UtttResult<&'a RefCell<RamCache>> {
+    device.cache_1.as_ref().ok_or_else(|| missing("E1805", "cache_1"))
 }
 
-const LOW_MARK: u32 = 200;
-const MULTIPLIER: u32 = 20000;
+fn cache_2<'a>(device: &'a Device) -> UtttResult<&'a RefCell<RamCache>> {
+    device.cache_2.as_ref().ok_or_else(|| missing("E1806", "cache_2"))
+}
 
-fn threshold(min: u32) -> u32 {
-    if min < LOW_MARK {
-        LOW_MARK * MULTIPLIER
-    } else {
-        min * MULTIPLIER
-    }
+fn writer<'a>(device: &'a Device) -> UtttResult<&'a DbWriter> {
+    device.writer.as_ref().ok_or_else(|| missing("E1807", "writer"))
+}
+
+fn missing(code: &'static str, field: &str) -> UtttError {
+    UtttError::invariant(code, &format!("SSD device has no {}", field))
 }
@@ -0,0 +1,433 @@
+/// Export and import.
+///
+/// Streams the rows of the 'solutions' table to a file, and loads such a file
+/// back, so that datasets can be shared, backed up and moved between machines
+/// without `pg_dump`. The binary format is compact and can be imported; the
+/// CSV and JSON-lines formats are for analysis only.
+///
+/// Every exported row is decoded with `solution_from` first, so an export
+/// fails on a row that could not be read back. An import reads the whole file
+/// once before writing anything, so that a truncated or corrupt file (one
+/// whose trailer does not match) leaves the table as it was. Imports go
+/// through `db_write_raw`, so they never overwrite a row with less knowledge.
+
+use data::*;
+use error::*;
+use postgres::Connection;
+use solver::*;
+use solver::codec::*;
+use solver::db::*;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+// == file format ==============================================================
+
+/// The format of a binary export file.
+///
+/// section   note
+/// -------   ----
+/// header    magic number (EXPORT_MAGIC, 8 bytes), format version
+///           (EXPORT_VERSION, 4 bytes, little-endian), codec version
+///           (CODEC_VERSION, 4 bytes, little-endian)
+/// records   one per row, in primary key order; see below
+/// trailer   end tag (0, 1 byte), record count (8 bytes, little-endian),
+///           checksum (8 bytes, little-endian)
+///
+/// Fields of each record. A varint is an unsigned LEB128 integer; a delta is
+/// the zigzag-encoded difference from the same field of the previous record
+/// (or from 0, for the first record), stored as a varint.
+///
+/// field       encoding
+/// -----       --------
+/// tag         1 (1 byte)
+/// game_1      delta
+/// game_2      delta
+/// game_3      delta
+/// plays       varint
+/// solutions   a 16-bit count, then each solution; see `write_solutions`
+///
+/// Since records are sorted, consecutive keys tend to be close, and their
+/// deltas short. The checksum is the 64-bit FNV-1a hash of all record bytes.
+/// A file written with another codec version is rejected.
+pub const EXPORT_MAGIC: &'static [u8; 8] = b"UTTTEXPT";
+
+pub const EXPORT_VERSION: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    /// The binary format described above. It is the only format that can be
+    /// imported.
+    Binary,
+    /// Comma-separated values, with a header line. The 'solutions' field holds
+    /// space-separated solutions, each written as 'location:outcome:turns',
+    /// where location is 0 to 80 (row * 9 + column) or '-' for none, and
+    /// outcome is 'X' or 'O' (the winner), 'tie' or 'unknown'.
+    Csv,
+    /// One JSON object per line, with the same fields as the CSV format.
+    JsonLines,
+}
+
+const CSV_HEADER: &'static str =
+    "game_1,game_2,game_3,plays,next_player,solutions\n";
+
+/// The number of rows read from the database at a time.
+const PAGE_SIZE: i64 = 10_000;
+
+// == public API ===============================================================
+
+/// Writes every row of the 'solutions' table to a new file at the given path,
+/// replacing any existing file. Returns the number of rows written.
+pub fn export_from_db<P: AsRef<Path>>(conn: &Connection, path: P,
+                                      format: ExportFormat)
+                                      -> UtttResult<u64> {
+    let file = File::create(path).map_err(|e| UtttError::from_io("E6701", e))?;
+    let mut writer = ExportWriter::new(BufWriter::new(file), format)?;
+    let mut after: Option<GameColumns> = None;
+    loop {
+        let rows = db_scan(conn, after, PAGE_SIZE)?;
+        if rows.is_empty() { break; }
+        after = rows.last().map(|&(key, _, _)| key);
+        for row in rows.iter() {
+            writer.write_row(row)?;
+        }
+    }
+    writer.finish()
+}
+
+/// Loads a binary export file into the 'solutions' table. Returns a (read,
+/// written) tuple: the number of rows in the file, and the number of rows
+/// inserted or updated. (A row is not updated if the table already has more
+/// knowledge about the game.) Writes nothing unless the whole file checks out;
+/// see `export_check`.
+pub fn import_into_db<P: AsRef<Path>>(conn: &Connection, path: P)
+                                      -> UtttResult<(u64, u64)> {
+    export_check(path.as_ref())?;
+    let file = File::open(path).map_err(|e| UtttError::from_io("E6702", e))?;
+    let mut reader = ExportReader::new(BufReader::new(file))?;
+    let mut batch: Vec<RawRow> = Vec::with_capacity(WRITE_BATCH_ROWS);
+    let mut read: u64 = 0;
+    let mut written: u64 = 0;
+    while let Some(row) = reader.read_row()? {
+        read += 1;
+        batch.push(row);
+        if batch.len() == WRITE_BATCH_ROWS {
+            written += db_write_raw(conn, &batch)?;
+            batch.clear();
+        }
+    }
+    written += db_write_raw(conn, &batch)?;
+    Ok((read, written))
+}
+
+/// Reads a binary export file without importing it. Checks the header, that
+/// each row's key decodes to a game (see `game_from_columns`) with the row's
+/// play count, that its solutions decode (as `db_write_raw` requires), and
+/// the trailer's record count and checksum. Returns the number of rows.
+pub fn export_check<P: AsRef<Path>>(path: P) -> UtttResult<u64> {
+    let file = File::open(path).map_err(|e| UtttError::from_io("E6707", e))?;
+    let mut reader = ExportReader::new(BufReader::new(file))?;
+    let mut read: u64 = 0;
+    while let Some(row) = reader.read_row()? {
+        let game = game_from_columns(row.0)?;
+        if row.1 != game.board.play_count() as i16 {
+            return Err(UtttError::decode("E6708", &format!(
+                "row {} has {} plays, but its game has {}", read + 1, row.1,
+                game.board.play_count())));
+        }
+        turns_and_unknown(&raw_solutions(&row)?)?;
+        read += 1;
+    }
+    Ok(read)
+}
+
+/// Writes rows in one of the export formats.
+pub struct ExportWriter<W: Write> {
+    w: W,
+    format: ExportFormat,
+    prev: GameColumns,
+    count: u64,
+    checksum: u64,
+}
+
+impl<W: Write> ExportWriter<W> {
+    /// Returns a writer, after writing the header (if any) for the format.
+    pub fn new(mut w: W, format: ExportFormat) -> UtttResult<ExportWriter<W>> {
+        let header: Vec<u8> = match format {
+            ExportFormat::Binary => {
+                let mut bytes = EXPORT_MAGIC.to_vec();
+                bytes.extend_from_slice(&EXPORT_VERSION.to_le_bytes());
+                bytes.extend_from_slice(&CODEC_VERSION.to_le_bytes());
+                bytes
+            },
+            ExportFormat::Csv => CSV_HEADER.as_bytes().to_vec(),
+            ExportFormat::JsonLines => vec![],
+        };
+        w.write_all(&header).map_err(|e| UtttError::from_io("E6703", e))?;
+        Ok(ExportWriter {
+            w: w,
+            format: format,
+            prev: (0, 0, 0),
+            count: 0,
+            checksum: FNV_OFFSET,
+        })
+    }
+
+    /// Writes a row. Fails if its solutions cannot be decoded.
+    pub fn write_row(&mut self, row: &RawRow) -> UtttResult<()> {
+        let sols = raw_solutions(row)?;
+        let bytes: Vec<u8> = match self.format {
+            ExportFormat::Binary => {
+                let bytes = binary_record(row, &sols, self.prev)?;
+                self.checksum = fnv(self.checksum, &bytes);
+                self.prev = row.0;
+                bytes
+            },
+            ExportFormat::Csv => csv_line(row, &sols).into_bytes(),
+            ExportFormat::JsonLines => json_line(row, &sols).into_bytes(),
+        };
+        self.w.write_all(&bytes).map_err(|e| UtttError::from_io("E6704", e))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Writes the trailer (if any) for the format, and flushes. Returns the
+    /// number of rows written.
+    pub fn finish(mut self) -> UtttResult<u64> {
+        if self.format == ExportFormat::Binary {
+            let mut trailer: Vec<u8> = vec![0];
+            trailer.extend_from_slice(&self.count.to_le_bytes());
+            trailer.extend_from_slice(&self.checksum.to_le_bytes());
+            self.w.write_all(&trailer)
+                .map_err(|e| UtttError::from_io("E6705", e))?;
+        }
+        self.w.flush().map_err(|e| UtttError::from_io("E6706", e))?;
+        Ok(self.count)
+    }
+}
+
+/// Reads rows from a binary export file.
+pub struct ExportReader<R: Read> {
+    r: R,
+    prev: GameColumns,
+    count: u64,
+    checksum: u64,
+    done: bool,
+}
+
+impl<R: Read> ExportReader<R> {
+    /// Returns a reader, after checking the header.
+    pub fn new(mut r: R) -> UtttResult<ExportReader<R>> {
+        let mut header = [0; 16];
+        r.read_exact(&mut header).map_err(|e| UtttError::from_io("E6711", e))?;
+        if &header[0 .. 8] != EXPORT_MAGIC {
+            return Err(UtttError::decode("E6712", "not an export file"));
+        }
+        let mut version = [0; 4];
+        version.copy_from_slice(&header[8 .. 12]);
+        if u32::from_le_bytes(version) != EXPORT_VERSION {
+            return Err(UtttError::decode("E6713", "unsupported export version"));
+        }
+        version.copy_from_slice(&header[12 .. 16]);
+        if u32::from_le_bytes(version) != CODEC_VERSION {
+            return Err(UtttError::decode("E6722", "unsupported codec version"));
+        }
+        Ok(ExportReader {
+            r: r,
+            prev: (0, 0, 0),
+            count: 0,
+            checksum: FNV_OFFSET,
+            done: false,
+        })
+    }
+
+    /// Returns the next row, or None after the last one. At the end, checks
+    /// the record count and the checksum.
+    pub fn read_row(&mut self) -> UtttResult<Option<RawRow>> {
+        if self.done { return Ok(None); }
+        match self.read_byte()? {
+            0 => {
+                self.read_trailer()?;
+                self.done = true;
+                Ok(None)
+            },
+            1 => {
+                self.checksum = fnv(self.checksum, &[1]);
+                let game_1 = self.prev.0.wrapping_add(self.read_delta()?);
+                let game_2 = self.prev.1.wrapping_add(self.read_delta()?);
+                let game_3 = (self.prev.2 as i64)
+                    .wrapping_add(self.read_delta()?) as i32;
+                let plays = self.read_varint()? as i16;
+                let mut bytes = self.read_bytes(2)?;
+                let n = u16::from_le_bytes([bytes[0], bytes[1]]) as usize;
+                bytes.extend(self.read_bytes(2 * n)?);
+                let (sols, _) =
+                    read_solutions(&bytes, next_player_from(game_3)?)?;
+                let solutions = sols.into_iter()
+                    .map(sol_i16)
+                    .collect::<Vec<i16>>();
+                self.prev = (game_1, game_2, game_3);
+                self.count += 1;
+                Ok(Some((self.prev, plays, solutions)))
+            },
+            _ => Err(UtttError::decode("E6714", "invalid record tag")),
+        }
+    }
+
+    fn read_trailer(&mut self) -> UtttResult<()> {
+        let mut trailer = [0; 16];
+        self.r.read_exact(&mut trailer)
+            .map_err(|e| UtttError::from_io("E6715", e))?;
+        let mut count = [0; 8];
+        let mut checksum = [0; 8];
+        count.copy_from_slice(&trailer[0 .. 8]);
+        checksum.copy_from_slice(&trailer[8 .. 16]);
+        if u64::from_le_bytes(count) != self.count {
+            return Err(UtttError::decode("E6716", "record count mismatch"));
+        }
+        if u64::from_le_bytes(checksum) != self.checksum {
+            return Err(UtttError::decode("E6717", "checksum mismatch"));
+        }
+        let mut rest = [0; 1];
+        match self.r.read(&mut rest) {
+            Ok(0) => Ok(()),
+            Ok(_) => Err(UtttError::decode("E6718", "data after trailer")),
+            Err(e) => Err(UtttError::from_io("E6719", e)),
+        }
+    }
+
+    /// Reads one byte. Does not update the checksum.
+    fn read_byte(&mut self) -> UtttResult<u8> {
+        let mut byte = [0; 1];
+        self.r.read_exact(&mut byte)
+            .map_err(|e| UtttError::from_io("E6720", e))?;
+        Ok(byte[0])
+    }
+
+    /// Reads bytes, and adds them to the checksum.
+    fn read_bytes(&mut self, n: usize) -> UtttResult<Vec<u8>> {
+        let mut bytes = vec![0; n];
+        self.r.read_exact(&mut bytes)
+            .map_err(|e| UtttError::from_io("E6720", e))?;
+        self.checksum = fnv(self.checksum, &bytes);
+        Ok(bytes)
+    }
+
+    fn read_varint(&mut self) -> UtttResult<u64> {
+        let mut x: u64 = 0;
+        for i in 0 .. 10 {
+            let byte = self.read_byte()?;
+            self.checksum = fnv(self.checksum, &[byte]);
+            x |= ((byte & 0x7F) as u64) << (7 * i);
+            if byte & 0x80 == 0 { return Ok(x); }
+        }
+        Err(UtttError::decode("E6721", "varint too long"))
+    }
+
+    fn read_delta(&mut self) -> UtttResult<i64> {
+        Ok(unzigzag(self.read_varint()?))
+    }
+}
+
+// == helpers: binary records ==================================================
+
+fn binary_record(row: &RawRow, sols: &Vec<Solution>, prev: GameColumns)
+                 -> UtttResult<Vec<u8>> {
+    let &((game_1, game_2, game_3), plays, _) = row;
+    let mut bytes: Vec<u8> = vec![1];
+    push_varint(&mut bytes, zigzag(game_1.wrapping_sub(prev.0)));
+    push_varint(&mut bytes, zigzag(game_2.wrapping_sub(prev.1)));
+    push_varint(&mut bytes, zigzag(game_3 as i64 - prev.2 as i64));
+    push_varint(&mut bytes, plays as u16 as u64);
+    write_solutions(&mut bytes, sols)?;
+    Ok(bytes)
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        bytes.push((x as u8 & 0x7F) | 0x80);
+        x >>= 7;
+    }
+    bytes.push(x as u8);
+}
+
+fn zigzag(x: i64) -> u64 {
+    ((x << 1) ^ (x >> 63)) as u64
+}
+
+fn unzigzag(x: u64) -> i64 {
+    ((x >> 1) as i64) ^ -((x & 1) as i64)
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes.iter() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+// == helpers: text formats ====================================================
+
+fn csv_line(row: &RawRow, sols: &Vec<Solution>) -> String {
+    let &((game_1, game_2, game_3), plays, _) = row;
+    let solutions = sols.iter()
+        .map(|sol| format!("{}:{}:{}", location_str(sol.opt_play),
+                           outcome_str(sol.outcome), sol.outcome.turns()))
+        .collect::<Vec<String>>()
+        .join(" ");
+    format!("{},{},{},{},{},{}\n", game_1, game_2, game_3, plays,
+            player_str(next_player_from(game_3).ok().and_then(|p| p)),
+            solutions)
+}
+
+fn json_line(row: &RawRow, sols: &Vec<Solution>) -> String {
+    let &((game_1, game_2, game_3), plays, _) = row;
+    let solutions = sols.iter()
+        .map(|sol| {
+            let location = match sol.opt_play {
+                None => "null".to_string(),
+                Some(_) => location_str(sol.opt_play),
+            };
+            format!("{{\"location\":{},\"outcome\":\"{}\",\"turns\":{}}}",
+                    location, outcome_str(sol.outcome), sol.outcome.turns())
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    let next_player = match next_player_from(game_3).ok().and_then(|p| p) {
+        None => "null".to_string(),
+        Some(player) => format!("\"{}\"", player_str(Some(player))),
+    };
+    format!("{{\"game_1\":{},\"game_2\":{},\"game_3\":{},\"plays\":{},\
+             \"next_player\":{},\"solutions\":[{}]}}\n",
+            game_1, game_2, game_3, plays, next_player, solutions)
+}
+
+/// Returns the location (0 to 80; see `policy_index`) of a play, or "-" for
+/// none.
+fn location_str(opt_play: Option<Play>) -> String {
+    match opt_play {
+        None => "-".to_string(),
+        Some(play) => policy_index(play.loc).to_string(),
+    }
+}
+
+fn outcome_str(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Win { player, .. } => player_str(Some(player)),
+        Outcome::Tie { .. } => "tie",
+        Outcome::Unknown { .. } => "unknown",
+    }
+}
+
+fn player_str(opt_player: Option<Player>) -> &'static str {
+    match opt_player {
+        Some(Player::X) => "X",
+        Some(Player::O) => "O",
+        None => "-",
+    }
+}
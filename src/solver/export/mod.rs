@@ -0,0 +1,9 @@
+/// The 'solver/export' module.
+
+// Include these submodules into this module.
+pub use self::export::*;
+
+mod export;
+
+#[cfg(test)]
+mod tests;
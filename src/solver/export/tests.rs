@@ -0,0 +1,117 @@
+use data::*;
+use rand::{SeedableRng, XorShiftRng};
+use random::random_games;
+use solver::*;
+use solver::codec::CODEC_VERSION;
+use solver::db::*;
+use solver::export::*;
+use std::env;
+use std::fs;
+use std::io::Cursor;
+
+/// Returns rows for the last few games of a seeded random game, in primary
+/// key order, each with its solutions to the given depth.
+fn solved_rows(depth: Count) -> Vec<RawRow> {
+    let seed: [u32; 4] = [1456198685, 762656086, 844876651, 1745969790];
+    let mut rng: XorShiftRng = SeedableRng::from_seed(seed);
+    let stack = CPU_Stack::new();
+    let mut rows = random_games(&mut rng).iter().rev().take(8)
+        .map(|game| raw_row_from(game, &game.solve(depth, &stack).expect("E6801")))
+        .collect::<Vec<RawRow>>();
+    rows.sort_by_key(|row| row.0);
+    rows
+}
+
+fn export_bytes(rows: &Vec<RawRow>, format: ExportFormat) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    {
+        let mut writer = ExportWriter::new(&mut bytes, format).expect("E6802");
+        for row in rows.iter() {
+            writer.write_row(row).expect("E6803");
+        }
+        assert!(writer.finish() == Ok(rows.len() as u64));
+    }
+    bytes
+}
+
+#[test]
+fn test_export_round_trip() {
+    let rows = solved_rows(3);
+    let bytes = export_bytes(&rows, ExportFormat::Binary);
+    let mut reader = ExportReader::new(Cursor::new(bytes)).expect("E6804");
+    let mut read: Vec<RawRow> = vec![];
+    while let Some(row) = reader.read_row().expect("E6805") {
+        read.push(row);
+    }
+    assert!(read == rows);
+}
+
+#[test]
+fn test_export_detects_corruption() {
+    let rows = solved_rows(3);
+    let mut bytes = export_bytes(&rows, ExportFormat::Binary);
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    let mut reader = ExportReader::new(Cursor::new(bytes)).expect("E6806");
+    let mut result = reader.read_row();
+    while let Ok(Some(_)) = result {
+        result = reader.read_row();
+    }
+    assert!(result.map_err(|e| e.code()) == Err("E6717"));
+    let bad_magic = b"NOTUTTT!\x01\x00\x00\x00".to_vec();
+    assert!(ExportReader::new(Cursor::new(bad_magic)).is_err());
+}
+
+#[test]
+fn test_export_text_formats() {
+    let rows = solved_rows(3);
+    let csv = String::from_utf8(export_bytes(&rows, ExportFormat::Csv))
+        .expect("E6807");
+    let lines = csv.lines().collect::<Vec<&str>>();
+    assert!(lines.len() == rows.len() + 1);
+    assert!(lines[0] == "game_1,game_2,game_3,plays,next_player,solutions");
+    for (line, row) in lines[1 ..].iter().zip(rows.iter()) {
+        let &((game_1, game_2, game_3), plays, _) = row;
+        let prefix = format!("{},{},{},{},", game_1, game_2, game_3, plays);
+        assert!(line.starts_with(&prefix));
+    }
+    let jsonl = String::from_utf8(export_bytes(&rows, ExportFormat::JsonLines))
+        .expect("E6808");
+    assert!(jsonl.lines().count() == rows.len());
+    assert!(jsonl.lines().all(|line| line.starts_with("{\"game_1\":") &&
+                                     line.ends_with("]}")));
+}
+
+#[test]
+fn test_export_check() {
+    let rows = solved_rows(2);
+    let mut bytes = export_bytes(&rows, ExportFormat::Binary);
+    let path = env::temp_dir().join("uttt_test_export_check.bin");
+    fs::write(&path, &bytes).expect("E6807");
+    assert!(export_check(&path) == Ok(rows.len() as u64));
+    // A file written with another codec version is rejected.
+    let mut version = bytes.clone();
+    version[12 .. 16].copy_from_slice(&(CODEC_VERSION + 1).to_le_bytes());
+    fs::write(&path, &version).expect("E6812");
+    assert!(export_check(&path).map_err(|e| e.code()) == Err("E6722"));
+    // A file cut short has no trailer, so nothing may be imported from it.
+    let len = bytes.len();
+    bytes.truncate(len - 20);
+    fs::write(&path, &bytes).expect("E6808");
+    assert!(export_check(&path).map_err(|e| e.code()) == Err("E6720"));
+    // A row whose play count disagrees with its game is rejected.
+    let mut wrong_plays = rows.clone();
+    wrong_plays[0].1 += 1;
+    fs::write(&path, &export_bytes(&wrong_plays, ExportFormat::Binary))
+        .expect("E6810");
+    assert!(export_check(&path).map_err(|e| e.code()) == Err("E6708"));
+    // So is a corrupt key. Setting a sub-board's unused high bit in the last
+    // row keeps the keys in order.
+    let mut corrupt = rows.clone();
+    let last = corrupt.len() - 1;
+    (corrupt[last].0).0 |= 1 << 15;
+    fs::write(&path, &export_bytes(&corrupt, ExportFormat::Binary))
+        .expect("E6811");
+    assert!(export_check(&path).map_err(|e| e.code()) == Err("E85102"));
+    fs::remove_file(&path).expect("E6809");
+}
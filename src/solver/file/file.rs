@@ -0,0 +1,376 @@
+/// Solution file.
+///
+/// A solution file is an immutable, sorted snapshot of solutions, intended for
+/// publishing results. It is opened with `mmap`, so a lookup only touches the
+/// few pages visited by a binary search, and the operating system decides
+/// which pages stay resident. Unlike the SSD device, it does not need a
+/// database server, and it does not hold `CACHE_2_CAP` solutions in RAM.
+/// `uttt db freeze` writes one from the 'solutions' table.
+
+use data::*;
+use error::*;
+use libc;
+use postgres::Connection;
+use solver::{Solution, is_downgrade};
+use solver::codec::*;
+use solver::db::*;
+use solver::ram_cache::RamCache;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::slice;
+
+// == file format ==============================================================
+
+/// The format of a solution file. All integers are little-endian.
+///
+/// The file has three sections:
+///
+/// section    width         note
+/// -------    -----         ----
+/// header     40            see below
+/// records    28 * count    sorted by (game_1, game_2, game_3)
+/// heap       heap width    the solutions of all records, back to back
+///
+/// Byte mapping for the header:
+///
+/// bytes     width   note
+/// -------   -----   ----
+///  0 -  7       8   magic number (FILE_MAGIC)
+///  8 - 11       4   format version (FILE_VERSION)
+/// 12 - 15       4   codec version (CODEC_VERSION)
+/// 16 - 19       4   record width (RECORD_WIDTH)
+/// 20 - 23       4   unused
+/// 24 - 31       8   record count
+/// 32 - 39       8   heap width, in bytes
+///
+/// Byte mapping for each (fixed-width) record:
+///
+/// bytes     width   note
+/// -------   -----   ----
+///  0 - 19      20   game key (GameKey)
+/// 20 - 27       8   offset of the record's solutions in the heap (u64)
+///
+/// The game keys and each record's solutions (a 16-bit count, then the 16-bit
+/// solutions; see `write_solutions`) use the encodings of the 'codec' module,
+/// as does the 'solutions' table. A file written with another codec version
+/// is rejected.
+pub const FILE_MAGIC: &'static [u8; 8] = b"UTTTSOLS";
+
+pub const FILE_VERSION: u32 = 2;
+
+pub const HEADER_WIDTH: usize = 40;
+
+pub const RECORD_WIDTH: usize = 28;
+
+/// Entries to be written to a solution file, keyed (and therefore sorted) by
+/// their game columns.
+pub type FileEntries = BTreeMap<GameColumns, Vec<Solution>>;
+
+// == public API: writing ======================================================
+
+/// Adds a game and its solutions to the entries. If the game is already
+/// present, merges the solutions with `merge_solutions`.
+pub fn file_entries_insert(entries: &mut FileEntries, game: &Game,
+                           sols: &Vec<Solution>) -> UtttResult<()> {
+    entries_merge(entries, game_columns_from(game), sols.clone());
+    Ok(())
+}
+
+/// Adds every game and its solutions from a RAM cache to the entries.
+pub fn file_entries_from_cache(entries: &mut FileEntries, cache: &RamCache)
+                               -> UtttResult<()> {
+    for (game, sols) in cache.iter() {
+        file_entries_insert(entries, game, sols)?;
+    }
+    Ok(())
+}
+
+/// Writes the entries to a new solution file at the given path, replacing
+/// any existing file. Returns the number of records written.
+pub fn file_write<P: AsRef<Path>>(path: P, entries: &FileEntries)
+                                  -> UtttResult<u64> {
+    let mut w = file_writer(path)?;
+    for (&columns, solutions) in entries.iter() {
+        w.push(columns, solutions)?;
+    }
+    w.finish()
+}
+
+/// Writes every row of the 'solutions' table to a new solution file at the
+/// given path, replacing any existing file. Rows are read one page at a time
+/// and, since they come in key order, written as they arrive. Returns the
+/// number of records written.
+pub fn file_write_from_db<P: AsRef<Path>>(path: P, conn: &Connection)
+                                          -> UtttResult<u64> {
+    const PAGE_SIZE: i64 = 10_000;
+    let mut w = file_writer(path)?;
+    let mut after: Option<GameColumns> = None;
+    loop {
+        let rows = db_scan(conn, after, PAGE_SIZE)?;
+        if rows.is_empty() { break; }
+        after = rows.last().map(|&(key, _, _)| key);
+        for row in rows.iter() {
+            w.push(row.0, &raw_solutions(row)?)?;
+        }
+    }
+    w.finish()
+}
+
+/// Writes a solution file one record at a time; see `file_writer`. The
+/// records go straight to the file and the solutions to a temporary heap file
+/// next to it, which `finish` appends; so memory use does not grow with the
+/// number of records.
+pub struct FileWriter {
+    w: BufWriter<File>,
+    heap: BufWriter<File>,
+    heap_path: PathBuf,
+    count: u64,
+    heap_width: u64,
+    last: Option<GameColumns>,
+}
+
+/// Creates a solution file at the given path, replacing any existing file,
+/// and returns a writer for its records. Keys must be pushed in increasing
+/// order; the file is complete after `finish`.
+pub fn file_writer<P: AsRef<Path>>(path: P) -> UtttResult<FileWriter> {
+    let mut heap_path = path.as_ref().as_os_str().to_owned();
+    heap_path.push(".heap");
+    let mut w = BufWriter::new(File::create(path).map_err(io_error)?);
+    // A placeholder, until the counts are known.
+    w.write_all(&[0; HEADER_WIDTH]).map_err(io_error)?;
+    let heap = BufWriter::new(File::create(&heap_path).map_err(io_error)?);
+    Ok(FileWriter {
+        w: w,
+        heap: heap,
+        heap_path: PathBuf::from(heap_path),
+        count: 0,
+        heap_width: 0,
+        last: None,
+    })
+}
+
+impl FileWriter {
+    /// Writes the record for a game key and its solutions.
+    pub fn push(&mut self, columns: GameColumns, sols: &Vec<Solution>)
+                -> UtttResult<()> {
+        if self.last.map_or(false, |last| last >= columns) {
+            return Err(UtttError::invariant(
+                "E6614", "solution file keys are not sorted"));
+        }
+        let mut solutions: Vec<u8> = Vec::with_capacity(2 + 2 * sols.len());
+        write_solutions(&mut solutions, sols)?;
+        self.write_record(columns, &solutions).map_err(io_error)?;
+        self.count += 1;
+        self.heap_width += solutions.len() as u64;
+        self.last = Some(columns);
+        Ok(())
+    }
+
+    /// Appends the heap and writes the header. Returns the number of records
+    /// written.
+    pub fn finish(mut self) -> UtttResult<u64> {
+        self.write_heap_and_header().map_err(io_error)?;
+        Ok(self.count)
+    }
+
+    /// Writes a record, and its (encoded) solutions to the heap.
+    fn write_record(&mut self, columns: GameColumns, solutions: &[u8])
+                    -> io::Result<()> {
+        self.w.write_all(&key_from_columns(columns))?;
+        self.w.write_all(&self.heap_width.to_le_bytes())?;
+        self.heap.write_all(solutions)
+    }
+
+    fn write_heap_and_header(&mut self) -> io::Result<()> {
+        self.heap.flush()?;
+        io::copy(&mut File::open(&self.heap_path)?, &mut self.w)?;
+        let mut header: Vec<u8> = Vec::with_capacity(HEADER_WIDTH);
+        header.extend_from_slice(FILE_MAGIC);
+        header.extend_from_slice(&FILE_VERSION.to_le_bytes());
+        header.extend_from_slice(&CODEC_VERSION.to_le_bytes());
+        header.extend_from_slice(&(RECORD_WIDTH as u32).to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&self.count.to_le_bytes());
+        header.extend_from_slice(&self.heap_width.to_le_bytes());
+        self.w.seek(SeekFrom::Start(0))?;
+        self.w.write_all(&header)?;
+        self.w.flush()
+    }
+}
+
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.heap_path);
+    }
+}
+
+// == public API: reading ======================================================
+
+/// A read-only, memory-mapped solution file.
+pub struct SolutionFile {
+    ptr: *mut libc::c_void,
+    len: usize,
+    count: usize,
+}
+
+/// Opens and memory-maps the solution file at the given path. Checks the
+/// header (including the codec version) and the file size, and that each
+/// record's solutions are within the heap; but not the keys or the solutions
+/// themselves.
+pub fn file_open<P: AsRef<Path>>(path: P) -> UtttResult<SolutionFile> {
+    let file = File::open(path).map_err(io_error)?;
+    let len = file.metadata().map_err(io_error)?.len() as usize;
+    if len < HEADER_WIDTH {
+        return Err(invalid_data("solution file is too short"));
+    }
+    let ptr = unsafe {
+        libc::mmap(ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE,
+                   file.as_raw_fd(), 0)
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(io_error(io::Error::last_os_error()));
+    }
+    let mut sf = SolutionFile { ptr: ptr, len: len, count: 0 };
+    let (count, heap_width) = {
+        let bytes = sf.bytes();
+        if &bytes[0 .. 8] != FILE_MAGIC {
+            return Err(invalid_data("not a solution file"));
+        }
+        if u32_at(bytes, 8) != FILE_VERSION {
+            return Err(invalid_data("unsupported solution file version"));
+        }
+        if u32_at(bytes, 12) != CODEC_VERSION {
+            return Err(invalid_data("unsupported codec version"));
+        }
+        if u32_at(bytes, 16) as usize != RECORD_WIDTH {
+            return Err(invalid_data("unexpected solution file record width"));
+        }
+        (u64_at(bytes, 24), u64_at(bytes, 32))
+    };
+    let expected_len = (count as usize).checked_mul(RECORD_WIDTH)
+        .and_then(|records| records.checked_add(heap_width as usize))
+        .and_then(|body| body.checked_add(HEADER_WIDTH));
+    if count > usize::max_value() as u64 ||
+        heap_width > usize::max_value() as u64 || expected_len != Some(len) {
+        return Err(invalid_data("solution file size does not match header"));
+    }
+    sf.count = count as usize;
+    for i in 0 .. sf.count {
+        // The solution count comes first; it must fit, and so must the rest.
+        let offset = sf.offset_at(i);
+        let end = offset.checked_add(2)
+            .and_then(|end| sf.heap().get(offset .. end))
+            .and_then(|n| offset.checked_add(2 + 2 * u16_at(n, 0) as usize));
+        if end.map_or(true, |end| end > heap_width as usize) {
+            return Err(invalid_data("solution file record is out of bounds"));
+        }
+    }
+    Ok(sf)
+}
+
+/// Returns the solutions for a game, or an empty vector if the file does not
+/// contain the game. Uses binary search over the sorted records.
+pub fn file_read(file: &SolutionFile, game: &Game)
+                 -> UtttResult<Vec<Solution>> {
+    match file.find(game_columns_from(game)) {
+        None => Ok(vec![]),
+        Some(i) => {
+            let heap = &file.heap()[file.offset_at(i) ..];
+            read_solutions(heap, game.next_player()).map(|(sols, _)| sols)
+        },
+    }
+}
+
+impl SolutionFile {
+    /// Returns the number of records (games) in the file.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    fn key_at(&self, i: usize) -> GameColumns {
+        let offset = HEADER_WIDTH + i * RECORD_WIDTH;
+        let mut key: GameKey = [0; GAME_KEY_WIDTH];
+        key.copy_from_slice(&self.bytes()[offset .. offset + GAME_KEY_WIDTH]);
+        columns_from_key(&key)
+    }
+
+    /// Returns the offset of a record's solutions in the heap.
+    fn offset_at(&self, i: usize) -> usize {
+        let offset = HEADER_WIDTH + i * RECORD_WIDTH;
+        u64_at(self.bytes(), offset + 20) as usize
+    }
+
+    fn heap(&self) -> &[u8] {
+        &self.bytes()[HEADER_WIDTH + self.count * RECORD_WIDTH ..]
+    }
+
+    fn find(&self, key: GameColumns) -> Option<usize> {
+        let mut lo: usize = 0;
+        let mut hi: usize = self.count;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let mid_key = self.key_at(mid);
+            if mid_key < key {
+                lo = mid + 1;
+            } else if mid_key > key {
+                hi = mid;
+            } else {
+                return Some(mid);
+            }
+        }
+        None
+    }
+}
+
+impl Drop for SolutionFile {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len); }
+    }
+}
+
+// == helpers ==================================================================
+
+/// Inserts solutions into the entries, merging with any existing solutions
+/// for the same key.
+fn entries_merge(entries: &mut FileEntries, key: GameColumns,
+                 sols: Vec<Solution>) {
+    let merged = match entries.get(&key) {
+        Some(old) if is_downgrade(old, &sols) => old.clone(),
+        _ => sols,
+    };
+    entries.insert(key, merged);
+}
+
+fn invalid_data(message: &str) -> UtttError {
+    UtttError::decode("E6611", message)
+}
+
+fn io_error(e: io::Error) -> UtttError {
+    UtttError::from_io("E6612", e)
+}
+
+fn u16_at(bytes: &[u8], i: usize) -> u16 {
+    let mut b = [0; 2];
+    b.copy_from_slice(&bytes[i .. i + 2]);
+    u16::from_le_bytes(b)
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> u32 {
+    let mut b = [0; 4];
+    b.copy_from_slice(&bytes[i .. i + 4]);
+    u32::from_le_bytes(b)
+}
+
+fn u64_at(bytes: &[u8], i: usize) -> u64 {
+    let mut b = [0; 8];
+    b.copy_from_slice(&bytes[i .. i + 8]);
+    u64::from_le_bytes(b)
+}
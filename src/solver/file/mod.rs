@@ -0,0 +1,9 @@
+/// The 'solver/file' module.
+
+// Include these submodules into this module.
+pub use self::file::*;
+
+mod file;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,106 @@
+use constants::*;
+use data::*;
+use error::*;
+use solver::*;
+use solver::codec::CODEC_VERSION;
+use solver::file::*;
+use std::env;
+use std::fs;
+use tests::fixtures::seeded_games;
+
+/// Returns the games of `seeded_games`, each with its solutions to the given
+/// depth.
+fn solved_games(depth: Count) -> Vec<(Game, Vec<Solution>)> {
+    let stack = CPU_Stack::new();
+    seeded_games().into_iter()
+        .map(|game| (game, game.solve(depth, &stack).expect("E6607")))
+        .collect::<Vec<(Game, Vec<Solution>)>>()
+}
+
+#[test]
+fn test_file_round_trip() {
+    let solved = solved_games(3);
+    let mut entries = FileEntries::new();
+    for &(game, ref sols) in solved.iter() {
+        file_entries_insert(&mut entries, &game, sols).expect("E6608");
+    }
+    let path = env::temp_dir().join("uttt_test_file_round_trip.sol");
+    let n = entries.len();
+    assert!(file_write(&path, &entries).expect("E6601") == n as u64);
+    let file = file_open(&path).expect("E6602");
+    assert!(file.len() == n);
+    for &(game, ref sols) in solved.iter() {
+        assert!(file_read(&file, &game) == Ok(sols.clone()));
+    }
+    assert!(file_read(&file, &EMPTY_GAME) == Ok(vec![]));
+    fs::remove_file(&path).expect("E6603");
+}
+
+#[test]
+fn test_mmap_cpu_stack() {
+    let solved = solved_games(3);
+    let mut entries = FileEntries::new();
+    for &(game, ref sols) in solved.iter() {
+        file_entries_insert(&mut entries, &game, sols).expect("E6609");
+    }
+    let path = env::temp_dir().join("uttt_test_mmap_cpu_stack.sol");
+    file_write(&path, &entries).expect("E6604");
+    let stack = MMAP_CPU_Stack::new(file_open(&path).expect("E6605"));
+    for &(game, ref sols) in solved.iter() {
+        assert!(stack.get_and_put(&game, 3) == Ok(sols.clone()));
+    }
+    fs::remove_file(&path).expect("E6606");
+}
+
+#[test]
+fn test_file_open_rejects_bad_records() {
+    let solved = solved_games(2);
+    let mut entries = FileEntries::new();
+    for &(game, ref sols) in solved.iter() {
+        file_entries_insert(&mut entries, &game, sols).expect("E6621");
+    }
+    let path = env::temp_dir().join("uttt_test_file_open_rejects.sol");
+    file_write(&path, &entries).expect("E6622");
+    // Point the last record's solutions past the end of the heap.
+    let bytes = fs::read(&path).expect("E6623");
+    let offset = HEADER_WIDTH + (entries.len() - 1) * RECORD_WIDTH + 20;
+    let mut record = bytes.clone();
+    let index = u64::max_value().to_le_bytes();
+    record[offset .. offset + 8].copy_from_slice(&index);
+    fs::write(&path, &record).expect("E6624");
+    match file_open(&path) {
+        Err(UtttError::Decode { code: "E6611", .. }) => {},
+        _ => panic!("E6632"),
+    }
+    // A count so large that the expected size overflows.
+    let mut count = bytes.clone();
+    count[24 .. 32].copy_from_slice(&u64::max_value().to_le_bytes());
+    fs::write(&path, &count).expect("E6625");
+    match file_open(&path) {
+        Err(UtttError::Decode { code: "E6611", .. }) => {},
+        _ => panic!("E6632"),
+    }
+    // A file written with another codec version.
+    let mut version = bytes.clone();
+    version[12 .. 16].copy_from_slice(&(CODEC_VERSION + 1).to_le_bytes());
+    fs::write(&path, &version).expect("E6633");
+    match file_open(&path) {
+        Err(UtttError::Decode { code: "E6611", .. }) => {},
+        _ => panic!("E6632"),
+    }
+    fs::write(&path, &bytes).expect("E6634");
+    let file = file_open(&path).expect("E6635");
+    assert!(file.len() == entries.len());
+    drop(file);
+    fs::remove_file(&path).expect("E6626");
+    // Keys must be pushed in order.
+    let mut w = file_writer(&path).expect("E6627");
+    let mut keys = entries.keys().rev();
+    w.push(*keys.next().expect("E6628"), &vec![]).expect("E6629");
+    match w.push(*keys.next().expect("E6630"), &vec![]) {
+        Err(UtttError::Invariant { code: "E6614", .. }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
+    drop(w);
+    fs::remove_file(&path).expect("E6631");
+}
@@ -0,0 +1,56 @@
+/// Merging stored solutions.
+///
+/// Every device that stores solutions uses the same rule when a game is
+/// written again: keep whichever of the stored and new solutions says more
+/// about the game. As a result, stored knowledge only ever improves; for
+/// example, `Unknown { turns: 6 }` is never replaced by `Unknown { turns: 3 }`,
+/// and a win or tie is never replaced by an unknown.
+///
+/// The PostgreSQL upsert implements the same rule in SQL; see
+/// `WRITE_COMMAND`.
+
+use data::*;
+use solver::*;
+
+/// How much a vector of solutions (for one game) says about the game, in
+/// increasing order.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Knowledge {
+    /// Nothing; there are no solutions.
+    Nothing,
+    /// The outcome is unknown to a search depth of `turns`.
+    Unknown { turns: Count },
+    /// The outcome is a win or a tie.
+    Decided,
+}
+
+/// Returns the knowledge represented by a vector of solutions. (If any
+/// solution is unknown, the vector counts as unknown, to its shallowest
+/// depth.)
+pub fn knowledge(solutions: &Vec<Solution>) -> Knowledge {
+    let min_unknown_turns = solutions.iter()
+        .filter_map(|sol| match sol.outcome {
+            Outcome::Unknown { turns: t } => Some(t),
+            _ => None,
+        })
+        .min();
+    match min_unknown_turns {
+        Some(t) => Knowledge::Unknown { turns: t },
+        None if solutions.is_empty() => Knowledge::Nothing,
+        None => Knowledge::Decided,
+    }
+}
+
+/// Returns true if replacing the stored solutions (`old`) with `new` would
+/// lose knowledge.
+pub fn is_downgrade(old: &Vec<Solution>, new: &Vec<Solution>) -> bool {
+    knowledge(new) < knowledge(old)
+}
+
+/// Merges newly computed solutions (`new`) into stored solutions (`old`) for
+/// the same game. Returns `new` unless that would lose knowledge, in which
+/// case returns `old`.
+pub fn merge_solutions(old: &Vec<Solution>, new: &Vec<Solution>)
+                       -> Vec<Solution> {
+    if is_downgrade(old, new) { old.clone() } else { new.clone() }
+}
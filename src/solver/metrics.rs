@@ -0,0 +1,99 @@
+/// Device Metrics.
+///
+/// Every device in a stack counts the work done on it: reads (and computes),
+/// hits and misses by depth, writes, evictions, flushes, and the time spent.
+/// The stack updates most counters itself (see `Stack::get` and
+/// `Stack::put`), so a device only counts what the stack cannot see, such as
+/// evictions from its RAM caches. `Stack::metrics` returns a snapshot.
+
+use data::*;
+use solver::*;
+use std::time::Duration;
+
+/// The counters of one device.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Counters {
+    /// The number of reads (or computes).
+    pub reads: u64,
+    /// The number of reads that returned a deep enough solution, indexed by
+    /// the depth asked for.
+    pub hits: [u64; MAX_DEPTH],
+    /// The number of reads that did not, indexed by the depth asked for.
+    pub misses: [u64; MAX_DEPTH],
+    pub writes: u64,
+    /// The number of solutions removed from a RAM cache to make room.
+    pub evictions: u64,
+    pub flushes: u64,
+    /// The number of solutions flushed, across all flushes.
+    pub flushed: u64,
+    /// The time spent reading (or computing). For a device that computes,
+    /// this excludes the time spent in the stack for the games below the game
+    /// being computed, which is counted where it is spent.
+    pub read_time: Duration,
+    pub write_time: Duration,
+    pub flush_time: Duration,
+}
+
+impl Counters {
+    pub fn new() -> Counters {
+        Counters {
+            reads: 0,
+            hits: [0; MAX_DEPTH],
+            misses: [0; MAX_DEPTH],
+            writes: 0,
+            evictions: 0,
+            flushes: 0,
+            flushed: 0,
+            read_time: Duration::from_secs(0),
+            write_time: Duration::from_secs(0),
+            flush_time: Duration::from_secs(0),
+        }
+    }
+
+    /// Records a read at `depth` that took `time`.
+    pub fn record_read(&mut self, depth: Count, hit: bool, time: Duration) {
+        let i = (depth as usize).min(MAX_DEPTH - 1);
+        self.reads += 1;
+        if hit { self.hits[i] += 1; } else { self.misses[i] += 1; }
+        self.read_time += time;
+    }
+
+    pub fn total_hits(&self) -> u64 {
+        self.hits.iter().sum()
+    }
+
+    pub fn total_misses(&self) -> u64 {
+        self.misses.iter().sum()
+    }
+}
+
+/// A snapshot of one device: its counters and the state of its optional
+/// parts (None if the device does not have the part).
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceMetrics {
+    pub name: &'static str,
+    pub counters: Counters,
+    pub cache_1_len: Option<usize>,
+    pub cache_2_len: Option<usize>,
+    /// The admission policy's (accepted, rejected) counters.
+    pub admission: Option<(u64, u64)>,
+    /// The number of times the read connection was replaced.
+    pub reconnects: Option<u32>,
+}
+
+impl DeviceMetrics {
+    /// Returns a snapshot of the device.
+    pub fn of(device: &Device) -> DeviceMetrics {
+        DeviceMetrics {
+            name: device.name,
+            counters: *device.counters.borrow(),
+            cache_1_len: device.cache_1.as_ref().map(|c| c.borrow().len()),
+            cache_2_len: device.cache_2.as_ref().map(|c| c.borrow().len()),
+            admission: device.admission.as_ref().map(|a| {
+                let a = a.borrow();
+                (a.accepted, a.rejected)
+            }),
+            reconnects: device.db.as_ref().map(|db| db.reconnects()),
+        }
+    }
+}
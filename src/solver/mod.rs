@@ -3,6 +3,8 @@
 // Include these submodules into this module.
 pub use self::device::*;
 pub use self::devices::*;
+pub use self::merge::*;
+pub use self::metrics::*;
 pub use self::outcome::*;
 pub use self::solution::*;
 pub use self::solve::*;
@@ -11,6 +13,8 @@ pub use self::stacks::*;
 
 mod device;
 mod devices;
+mod merge;
+mod metrics;
 mod outcome;
 mod solution;
 mod solve;
@@ -19,7 +23,11 @@ mod stacks;
 
 // Expose and keep these sub-modules (distinct) below this module.
 pub mod ram_cache;
+pub mod codec;
 pub mod db;
+pub mod export;
+pub mod file;
+pub mod verify;
 
 #[cfg(test)]
 mod tests;
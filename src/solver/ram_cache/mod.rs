@@ -25,9 +25,15 @@ pub fn cache_get(cache: &mut RamCache, game: &Game) -> Vec<Solution> {
     }
 }
 
-/// Cache a (Game + Solution) key-value pair.
+/// Cache a (Game + Solution) key-value pair. If the game is already cached,
+/// merges the solutions with `merge_solutions`, so the cache never loses
+/// knowledge.
 pub fn cache_insert(cache: &mut RamCache, game: &Game, sols: &Vec<Solution>) {
-    cache.insert(*game, sols.clone());
+    let merged = match cache.get_mut(game) {
+        Some(old) => merge_solutions(old, sols),
+        None => sols.clone(),
+    };
+    cache.insert(*game, merged);
 }
 
 /// Remove the least-recently-used key-value pair.
@@ -98,13 +98,19 @@ pub fn p_solution(label: &str, depth: Count, solution: &Solution) {
 }
 
 pub fn p_solutions(label: &str, depth: Count, solutions: &Vec<Solution>) {
-    if solutions.is_empty() {
-        println!("- {} depth={} (no solutions)", label, depth);
+    println!("{}", show_solutions(label, depth, solutions));
+}
+
+/// Returns what `p_solutions` prints.
+pub fn show_solutions(label: &str, depth: Count, solutions: &Vec<Solution>)
+                      -> String {
+    let mut s = if solutions.is_empty() {
+        format!("- {} depth={} (no solutions)\n", label, depth)
     } else {
-        println!("- {} depth={}", label, depth);
-        for solution in solutions {
-            println!("    {}", solution.show());
-        }
+        format!("- {} depth={}\n", label, depth)
+    };
+    for solution in solutions {
+        s.push_str(&format!("    {}\n", solution.show()));
     }
-    println!("");
+    s
 }
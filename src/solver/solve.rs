@@ -1,4 +1,5 @@
 use data::*;
+use error::*;
 use solver::*;
 use std::cmp::Ordering;
 use std::collections::HashSet;
@@ -10,13 +11,14 @@ impl Game {
     /// looks ahead for a number of moves (specified by `depth`). If there are
     /// no valid moves, returns a solution where the optional play is `None` and
     /// the outcome is either a win or a tie.
-    pub fn solve(&self, depth: Count, stack: &Stack) -> Vec<Solution> {
+    pub fn solve(&self, depth: Count, stack: &Stack)
+                 -> UtttResult<Vec<Solution>> {
         if depth == 0 {
-            vec![self.solve_zero_depth()]
+            Ok(vec![self.solve_zero_depth()])
         } else if depth > 0 {
             self.solve_positive_depth(depth, stack)
         } else {
-            panic!("E2501");
+            Err(UtttError::invariant("E2501", "negative depth"))
         }
     }
 
@@ -50,19 +52,22 @@ impl Game {
     /// value. (Since it might be the optimal solution, even though it was not
     /// dominant.)  Finally, return the best solution.
     fn solve_positive_depth(&self, depth: Count, stack: &Stack)
-                            -> Vec<Solution> {
-        let sols = stack.get_and_put(self, depth - 1);
-        if sols.is_empty() { panic!("E2502"); }
+                            -> UtttResult<Vec<Solution>> {
+        let sols = stack.get_and_put(self, depth - 1)?;
+        if sols.is_empty() {
+            return Err(UtttError::invariant("E2502", "no solutions"));
+        }
         let opt_player = self.next_player();
         let dom_sols: Vec<Solution> = sols.iter()
             .filter(|sol| sol.is_dominant(opt_player, depth))
             .cloned().collect::<Vec<Solution>>();
         if dom_sols.is_empty() {
-            let player = opt_player.expect("E2503");
-            let solutions = self.candidate_solutions(depth, stack);
+            let player = opt_player.ok_or_else(|| UtttError::invariant(
+                "E2503", "finished game without a dominant solution"))?;
+            let solutions = self.candidate_solutions(depth, stack)?;
             best_solutions(player, solutions)
         } else {
-            dom_sols
+            Ok(dom_sols)
         }
     }
 
@@ -75,16 +80,16 @@ impl Game {
     /// one play. Otherwise, this function would not effectively be bounded by
     /// the depth argument.)
     fn candidate_solutions(&self, depth: Count, stack: &Stack)
-                           -> Vec<Solution> {
+                           -> UtttResult<Vec<Solution>> {
         let mut solutions: HashSet<Solution> = HashSet::new();
         for play in self.valid_plays() {
             let mut game = self.clone();
             game.play(play);
-            for solution in game.solve(depth - 1, stack) {
+            for solution in game.solve(depth - 1, stack)? {
                 solutions.insert(solution.futurize(play));
             }
         }
-        solutions.into_iter().collect::<Vec<Solution>>()
+        Ok(solutions.into_iter().collect::<Vec<Solution>>())
     }
 }
 
@@ -119,7 +124,8 @@ impl Solution {
 /// example, if one solution is be 'unknown to 9 turns' and another may be 'lose
 /// in 10 turns', then this function returns the latter in this case, since it
 /// is deeper.
-fn best_solutions(p: Player, ss: Vec<Solution>) -> Vec<Solution> {
+pub fn best_solutions(p: Player, ss: Vec<Solution>)
+                      -> UtttResult<Vec<Solution>> {
     let mut xs = ss.clone();
     xs.sort_by(|a, b| Solution::compare(p, *a, *b));
     match xs.split_first() {
@@ -132,9 +138,9 @@ fn best_solutions(p: Player, ss: Vec<Solution>) -> Vec<Solution> {
                     break;
                 }
             }
-            solutions
+            Ok(solutions)
         },
-        None => panic!("E2504"),
+        None => Err(UtttError::invariant("E2504", "no candidate solutions")),
     }
 }
 
@@ -143,7 +149,7 @@ fn best_solutions(p: Player, ss: Vec<Solution>) -> Vec<Solution> {
 impl Solution {
     /// Returns an 'updated' solution shifted into the future. Sets the play
     /// field and increments the associated count on the outcome field.
-    fn futurize(self, play: Play) -> Solution {
+    pub fn futurize(self, play: Play) -> Solution {
         Solution {
             opt_play: Some(play),
             outcome: self.outcome.inc(),
@@ -1,25 +1,52 @@
 use data::*;
+use error::*;
 use solver::*;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-pub struct Stack<'c> {
-    pub devices: Vec<Device<'c>>,
+pub struct Stack {
+    pub devices: Vec<Device>,
+    /// An optional flag that, once set, stops the solver at the next node; see
+    /// the 'signals' module.
+    pub stop: Option<&'static AtomicBool>,
+    /// The time spent in reads and computes so far, across all devices. A
+    /// compute recurses into the stack, so `get` subtracts the time that the
+    /// nested calls add here from its own; see `Counters::read_time`.
+    read_time: Cell<Duration>,
 }
 
 /// A solver stack, an abstraction that decouples the caching from the solving
 /// algorithm.
-impl<'c> Stack<'c> {
+impl Stack {
+    /// Returns a stack of the given devices, from the top down, without a
+    /// stop flag.
+    pub fn new(devices: Vec<Device>) -> Stack {
+        Stack {
+            devices: devices,
+            stop: None,
+            read_time: Cell::new(Duration::from_secs(0)),
+        }
+    }
+
     /// First, get one or more solutions for the given game and depth. Second,
     /// put the solution(s) back to the appropriate places in the stack.
+    ///
+    /// Fails with an 'interrupted' error if the stop flag is set. Solutions
+    /// found so far stay in the devices, to be flushed.
     pub fn get_and_put(&self, game: &Game, depth: Count)
-                       -> Vec<Solution> {
-        let (solutions, devices) = self.get(game, depth);
+                       -> UtttResult<Vec<Solution>> {
+        if self.is_stopping() {
+            return Err(UtttError::interrupted("E3706"));
+        }
+        let (solutions, devices) = self.get(game, depth)?;
         // Only write solutions with depth greater than 0, since a depth == 0
         // solution can be looked up in a trivial amount of time. To write such
         // a trivial solution to a device would be wasteful.
         if depth > 0 {
-            self.put(game, &solutions, devices);
+            self.put(game, &solutions, devices)?;
         }
-        solutions
+        Ok(solutions)
     }
 
     /// Puts to some layer in the stack, guided by the supplied devices which
@@ -27,14 +54,18 @@ impl<'c> Stack<'c> {
     /// updated, otherwise, they will continue to provide 'false positives' in
     /// the future.
     fn put(&self, game: &Game, solutions: &Vec<Solution>,
-           devices: Vec<&Device>) {
+           devices: Vec<&Device>) -> UtttResult<()> {
         for device in devices.iter() {
             if device.has_write {
-                if !(device.write)(&device, game, solutions) {
-                    panic!("E3701");
-                }
+                let start = Instant::now();
+                let result = (device.write)(&device, game, solutions);
+                let mut counters = device.counters.borrow_mut();
+                counters.writes += 1;
+                counters.write_time += start.elapsed();
+                result?;
             }
         }
+        Ok(())
     }
 
     /// Returns two things:
@@ -50,48 +81,79 @@ impl<'c> Stack<'c> {
     ///
     /// (Naming note: I chose the name 'get' to convey that it is more general
     /// than 'read' or 'compute'.)
-    fn get(&self, game: &Game, depth: Count) -> (Vec<Solution>, Vec<&Device>) {
+    fn get(&self, game: &Game, depth: Count)
+           -> UtttResult<(Vec<Solution>, Vec<&Device>)> {
         let mut devices: Vec<&Device> = Vec::new();
         for device in self.devices.iter() {
+            let start = Instant::now();
+            let before = self.read_time.get();
             let solutions = if device.has_read {
                 // Only read solutions with depth greater than 0, since a
                 // zero-depth solution can be computed in a trivial amount of
                 // time. To read such a trivial solution from a device would be
                 // wasteful.
                 if depth > 0 {
-                    (device.read)(&device, game)
+                    (device.read)(&device, game)?
                 } else {
                     continue;
                 }
             } else if device.has_compute {
-                (device.compute)(game, depth, self)
+                (device.compute)(game, depth, self)?
             } else {
-                panic!("E3704");
+                return Err(UtttError::capability("E3704", "read or compute"));
             };
             let ss = solutions.iter()
                 .filter(|sol| sol.is_deep_enough(depth))
                 .cloned()
                 .collect::<Vec<Solution>>();
+            let elapsed = start.elapsed();
+            let nested = self.read_time.get() - before;
+            self.read_time.set(before + elapsed);
+            let own = elapsed.checked_sub(nested)
+                .unwrap_or(Duration::from_secs(0));
+            device.counters.borrow_mut()
+                .record_read(depth, !ss.is_empty(), own);
             if ss.is_empty() {
                 devices.push(device);
             } else {
-                return (ss, devices);
+                return Ok((ss, devices));
             }
         }
-        panic!("E3705");
+        Err(UtttError::invariant("E3705", "no device had a deep enough solution"))
     }
 
-    pub fn flush(&self) -> (bool, u32) {
-        let mut success = true;
-        let mut count: u32 = 0;
+    /// Flushes every device that supports it, even if an earlier one fails.
+    /// Returns the total number of solutions flushed, or the first error.
+    pub fn flush(&self) -> UtttResult<u32> {
+        let mut result: UtttResult<u32> = Ok(0);
         for device in self.devices.iter() {
             if device.has_flush {
-                let (device_success, device_count) = (device.flush)(&device);
-                if !device_success { success = false; }
-                count += device_count;
+                let start = Instant::now();
+                let flushed = (device.flush)(&device);
+                {
+                    let mut counters = device.counters.borrow_mut();
+                    counters.flushes += 1;
+                    if let Ok(n) = flushed { counters.flushed += n as u64; }
+                    counters.flush_time += start.elapsed();
+                }
+                result = match (result, flushed) {
+                    (Ok(count), Ok(n)) => Ok(count + n),
+                    (Err(e), _) | (_, Err(e)) => Err(e),
+                };
             }
         }
-        (success, count)
+        result
+    }
+
+    /// Returns true if the stop flag is set.
+    pub fn is_stopping(&self) -> bool {
+        self.stop.map_or(false, |stop| stop.load(Ordering::SeqCst))
+    }
+
+    /// Returns a snapshot of the metrics of each device, from the top of the
+    /// stack down.
+    pub fn metrics(&self) -> Vec<DeviceMetrics> {
+        self.devices.iter().map(DeviceMetrics::of).collect()
     }
 }
 
@@ -6,9 +6,7 @@ use solver::*;
 pub struct CPU_Stack {}
 
 impl CPU_Stack {
-    pub fn new<'c>() -> Stack<'c> {
-        Stack {
-            devices: vec![CPU::new()],
-        }
+    pub fn new() -> Stack {
+        Stack::new(vec![CPU::new()])
     }
 }
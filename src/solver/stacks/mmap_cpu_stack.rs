@@ -0,0 +1,13 @@
+/// MMAP + CPU Stack.
+
+use solver::*;
+use solver::file::SolutionFile;
+
+#[allow(non_camel_case_types)]
+pub struct MMAP_CPU_Stack {}
+
+impl MMAP_CPU_Stack {
+    pub fn new(file: SolutionFile) -> Stack {
+        Stack::new(vec![MMAP::new(file), CPU::new()])
+    }
+}
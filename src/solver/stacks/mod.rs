@@ -1,7 +1,11 @@
 /// Stacks.
 
 pub use self::cpu_stack::*;
+pub use self::mmap_cpu_stack::*;
+pub use self::ram_cpu_stack::*;
 pub use self::ssd_cpu_stack::*;
 
 mod cpu_stack;
+mod mmap_cpu_stack;
+mod ram_cpu_stack;
 mod ssd_cpu_stack;
@@ -0,0 +1,16 @@
+/// RAM + CPU Stack.
+
+use solver::*;
+
+#[allow(non_camel_case_types)]
+pub struct RAM_CPU_Stack {}
+
+impl RAM_CPU_Stack {
+    pub fn new() -> Stack {
+        RAM_CPU_Stack::with_capacity(RAM_CACHE_CAP)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Stack {
+        Stack::new(vec![RAM::new(capacity), CPU::new()])
+    }
+}
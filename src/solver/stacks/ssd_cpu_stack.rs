@@ -1,15 +1,18 @@
 /// SSD + CPU Stack.
 
-use postgres::Connection;
+use error::*;
 use solver::*;
 
 #[allow(non_camel_case_types)]
 pub struct SSD_CPU_Stack {}
 
 impl SSD_CPU_Stack {
-    pub fn new<'c>(conn: &'c Connection) -> Stack<'c> {
-        Stack {
-            devices: vec![SSD::new(conn), CPU::new()],
-        }
+    pub fn new(params: &str) -> UtttResult<Stack> {
+        Ok(Stack::new(vec![SSD::new(params)?, CPU::new()]))
+    }
+
+    pub fn with_config(params: &str, config: &SsdConfig)
+                       -> UtttResult<Stack> {
+        Ok(Stack::new(vec![SSD::with_config(params, config)?, CPU::new()]))
     }
 }
@@ -1,6 +1,10 @@
 use constants::*;
 use data::*;
+use quickcheck::QuickCheck;
 use solver::*;
+use solver::ram_cache::*;
+use std::cmp::max;
+use std::time::Instant;
 
 #[test]
 fn test_empty_game() {
@@ -8,10 +12,160 @@ fn test_empty_game() {
     let stack = CPU_Stack::new();
     assert!(
         EMPTY_GAME.solve(depth, &stack) ==
-        vec![
+        Ok(vec![
             Solution {
                 opt_play: None,
                 outcome: Outcome::Unknown{ turns: 0 },
             }
-        ]);
+        ]));
+}
+
+#[test]
+fn test_merge_solutions() {
+    fn prop(old: Vec<Solution>, new: Vec<Solution>) -> bool {
+        let merged = merge_solutions(&old, &new);
+        (merged == old || merged == new) &&
+            knowledge(&merged) == max(knowledge(&old), knowledge(&new))
+    }
+    QuickCheck::new().tests(1000).quickcheck(
+        prop as fn(Vec<Solution>, Vec<Solution>) -> bool
+    );
+}
+
+#[test]
+fn test_merge_never_downgrades() {
+    let win = vec![Solution {
+        opt_play: None,
+        outcome: Outcome::Win { player: Player::X, turns: 5 },
+    }];
+    let unknown_3 = vec![Solution {
+        opt_play: None,
+        outcome: Outcome::Unknown { turns: 3 },
+    }];
+    let unknown_6 = vec![Solution {
+        opt_play: None,
+        outcome: Outcome::Unknown { turns: 6 },
+    }];
+    assert!(merge_solutions(&unknown_6, &unknown_3) == unknown_6);
+    assert!(merge_solutions(&unknown_3, &unknown_6) == unknown_6);
+    assert!(merge_solutions(&win, &unknown_6) == win);
+    assert!(merge_solutions(&unknown_6, &win) == win);
+}
+
+#[test]
+fn test_cache_insert_only_improves() {
+    fn prop(game: Game, writes: Vec<Vec<Solution>>) -> bool {
+        let mut cache = cache_new(4);
+        let mut stored = Knowledge::Nothing;
+        for sols in writes.iter() {
+            cache_insert(&mut cache, &game, sols);
+            let k = knowledge(&cache_get(&mut cache, &game));
+            if k < stored || k < knowledge(sols) { return false; }
+            stored = k;
+        }
+        true
+    }
+    QuickCheck::new().tests(200).quickcheck(
+        prop as fn(Game, Vec<Vec<Solution>>) -> bool
+    );
+}
+
+#[test]
+fn test_admission_policies_admit_decided() {
+    fn prop(game: Game, turns: u8, rate: f64) -> bool {
+        let turns = (turns as usize % MAX_DEPTH) as i16;
+        let stats = [u32::max_value(); MAX_DEPTH];
+        let policies = [
+            AdmissionPolicy::Always,
+            AdmissionPolicy::DecidedOnly,
+            AdmissionPolicy::default_policy(),
+            AdmissionPolicy::Sampling { rate: rate },
+        ];
+        policies.iter().all(|p| p.admit(&game, turns, false, &stats))
+    }
+    QuickCheck::new().tests(200).quickcheck(
+        prop as fn(Game, u8, f64) -> bool
+    );
+}
+
+#[test]
+fn test_admission_counts() {
+    let game = EMPTY_GAME;
+    let mut stats = [0; MAX_DEPTH];
+    let mut admission = Admission::new(AdmissionPolicy::DepthBalanced {
+        low_mark: 1,
+        multiplier: 2,
+    });
+    assert!(admission.admit(&game, 3, true, &stats));
+    stats[0] = 1;
+    stats[3] = 1;
+    assert!(admission.admit(&game, 3, true, &stats));
+    stats[3] = 2;
+    assert!(!admission.admit(&game, 3, true, &stats));
+    assert!(admission.admit(&game, 3, false, &stats));
+    assert!(admission.accepted == 3 && admission.rejected == 1);
+
+    let mut none = Admission::new(AdmissionPolicy::Sampling { rate: 0.0 });
+    assert!(!none.admit(&game, 3, true, &stats));
+    assert!(!AdmissionPolicy::DecidedOnly.admit(&game, 3, true, &stats));
+}
+
+#[test]
+fn test_stack_metrics() {
+    let stack = CPU_Stack::new();
+    stack.get_and_put(&EMPTY_GAME, 1).expect("E3711");
+    let metrics = stack.metrics();
+    assert!(metrics.len() == 1 && metrics[0].name == "CPU");
+    let counters = metrics[0].counters;
+    // The compute at depth 1 asks the stack for shallower solutions, and the
+    // CPU always has them.
+    assert!(counters.hits[1] == 1 && counters.total_hits() > 1);
+    assert!(counters.reads == counters.total_hits());
+    assert!(counters.total_misses() == 0 && counters.writes == 0);
+    assert!(metrics[0].cache_1_len.is_none());
+    assert!(metrics[0].admission.is_none());
+    // Nested computes are timed once, so the total is within the wall time.
+    let stack = CPU_Stack::new();
+    let start = Instant::now();
+    stack.get_and_put(&EMPTY_GAME, 3).expect("E3716");
+    assert!(stack.metrics()[0].counters.read_time <= start.elapsed());
+}
+
+#[test]
+fn test_ram_stack_caches_solutions() {
+    let stack = RAM_CPU_Stack::with_capacity(1);
+    let mut game = EMPTY_GAME;
+    let play = game.valid_plays()[0];
+    assert!(game.play(play));
+    let solutions = stack.get_and_put(&EMPTY_GAME, 1).expect("E3712");
+    let expected = CPU_Stack::new().get_and_put(&EMPTY_GAME, 1)
+        .expect("E3713");
+    // The same solutions as without the cache, though maybe in another order.
+    assert!(solutions.len() == expected.len() &&
+            solutions.iter().all(|sol| expected.contains(sol)));
+    // The second game evicts the first.
+    stack.get_and_put(&game, 1).expect("E3714");
+    let ram = stack.metrics()[0].counters;
+    assert!(ram.writes == 2 && ram.evictions == 1);
+    assert!(stack.metrics()[0].cache_1_len == Some(1));
+    let cpu_reads = stack.metrics()[1].counters.reads;
+    stack.get_and_put(&game, 1).expect("E3715");
+    assert!(stack.metrics()[0].counters.hits[1] == ram.hits[1] + 1);
+    assert!(stack.metrics()[1].counters.reads == cpu_reads);
+}
+
+#[test]
+fn test_stack_stops_on_request() {
+    use error::UtttError;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    static STOP: AtomicBool = AtomicBool::new(false);
+    let mut stack = CPU_Stack::new();
+    stack.stop = Some(&STOP);
+    assert!(stack.get_and_put(&EMPTY_GAME, 1).is_ok());
+    STOP.store(true, Ordering::SeqCst);
+    assert!(stack.is_stopping());
+    match stack.get_and_put(&EMPTY_GAME, 1) {
+        Err(UtttError::Interrupted { code: "E3706" }) => {},
+        other => panic!("unexpected {:?}", other),
+    }
 }
@@ -0,0 +1,9 @@
+/// The 'solver/verify' module.
+
+// Include these submodules into this module.
+pub use self::verify::*;
+
+mod verify;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,94 @@
+use data::*;
+use error::*;
+use solver::*;
+use solver::db::*;
+use solver::verify::*;
+use tests::fixtures::seeded_games;
+
+/// Verifies a row, looking up children by solving them to `depth - 1`.
+fn verify(row: &RawRow, depth: Count) -> Vec<Problem> {
+    let stack = CPU_Stack::new();
+    verify_row(row, |game| game.solve(depth - 1, &stack)).expect("E1911")
+}
+
+#[test]
+fn test_verify_consistent_rows() {
+    let stack = CPU_Stack::new();
+    for game in seeded_games() {
+        let sols = game.solve(3, &stack).expect("E1912");
+        assert!(verify(&raw_row_from(&game, &sols), 3) == vec![]);
+    }
+}
+
+#[test]
+fn test_verify_detects_corrupt_rows() {
+    let stack = CPU_Stack::new();
+    let games = seeded_games();
+    let last = games[0];
+    let sols = last.solve(3, &stack).expect("E1913");
+    let ((game_1, game_2, game_3), plays, solutions) =
+        raw_row_from(&last, &sols);
+    // Swap the last player bits.
+    let row = ((game_1, game_2, game_3 ^ 1 << 30), plays, solutions.clone());
+    match verify(&row, 3).as_slice() {
+        [Problem::CorruptKey(UtttError::Decode { code: "E85097", .. })] => {},
+        problems => panic!("E1919 {:?}", problems),
+    }
+    let row = ((game_1, game_2, game_3), plays + 1, solutions.clone());
+    assert!(verify(&row, 3) == vec![Problem::PlayCount {
+        stored: plays + 1,
+        expected: plays,
+    }]);
+    // A sub-board row of 0b11111 is not a valid encoding.
+    let row = ((game_1 | 0b11111, game_2, game_3), plays, solutions.clone());
+    match verify(&row, 3).as_slice() {
+        [Problem::CorruptKey(_)] => {},
+        problems => panic!("E1914 {:?}", problems),
+    }
+    // The last game is finished; claim that it is a tie (or a win for the
+    // loser).
+    let expected = sols[0].outcome;
+    let wrong = match expected {
+        Outcome::Tie { .. } => Outcome::Win { player: Player::X, turns: 0 },
+        _ => Outcome::Tie { turns: 0 },
+    };
+    let wrong_sol = Solution { opt_play: None, outcome: wrong };
+    let row = raw_row_from(&last, &vec![wrong_sol]);
+    assert!(verify(&row, 3) == vec![Problem::FinalState {
+        stored: wrong,
+        expected: expected,
+    }]);
+}
+
+#[test]
+fn test_verify_detects_inconsistent_outcomes() {
+    let stack = CPU_Stack::new();
+    let game = seeded_games()[1];
+    let sols = game.solve(3, &stack).expect("E1915");
+    let sol = sols[0];
+    let play = sol.opt_play.expect("E1916");
+    // A play into an occupied location.
+    let taken = seeded_games()[2].last_loc.expect("E1917");
+    let illegal = Play { loc: taken, player: play.player };
+    let row = raw_row_from(&game, &vec![Solution {
+        opt_play: Some(illegal),
+        outcome: sol.outcome,
+    }]);
+    assert!(verify(&row, 3) == vec![Problem::IllegalPlay(illegal)]);
+    // The game before the last one is won in one play; claim two.
+    let wrong = match sol.outcome {
+        Outcome::Win { player, turns } =>
+            Outcome::Win { player: player, turns: turns + 1 },
+        outcome => panic!("E1918 {:?}", outcome),
+    };
+    let row = raw_row_from(&game, &vec![Solution {
+        opt_play: Some(play),
+        outcome: wrong,
+    }]);
+    let problems = verify(&row, 3);
+    assert!(problems.contains(&Problem::ChildMismatch {
+        play: play,
+        stored: wrong,
+        child: Outcome::Win { player: play.player, turns: 0 },
+    }));
+}
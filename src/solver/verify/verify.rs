@@ -0,0 +1,251 @@
+/// Integrity checking.
+///
+/// Walks the 'solutions' table (all of it, or a sample) and checks that each
+/// row is consistent: that its key decodes to a game (see
+/// `game_from_columns`) with a matching play count, that its solutions decode
+/// and only use legal plays, and that its outcome agrees with the stored
+/// outcomes of the games one play later (a one-ply minimax).
+///
+/// Rows written by an interrupted or buggy program can be deleted instead of
+/// just reported; the solver recomputes them when needed. When a row and one
+/// of its children disagree, the verifier cannot tell which is wrong, so both
+/// are reported (the child by its own parent, if it disagrees with its own
+/// children too) and repairing deletes the parent.
+
+use data::*;
+use error::*;
+use postgres::Connection;
+use random::hash_fraction;
+use solver::*;
+use solver::codec::*;
+use solver::db::*;
+
+/// The number of rows read from the database at a time.
+const PAGE_SIZE: i64 = 10_000;
+
+/// Options for `verify_db`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VerifyConfig {
+    /// The fraction of rows to check, from 0.0 to 1.0. The choice is made by
+    /// hashing the key (with `seed`), so a given sample is repeatable.
+    pub sample_rate: f64,
+    pub seed: u64,
+    /// If true, delete each row with a problem.
+    pub repair: bool,
+}
+
+impl VerifyConfig {
+    /// Returns a configuration that checks every row, and repairs nothing.
+    pub fn new() -> VerifyConfig {
+        VerifyConfig { sample_rate: 1.0, seed: 0, repair: false }
+    }
+
+    /// Returns a configuration that checks a fraction of the rows.
+    pub fn sampled(sample_rate: f64, seed: u64) -> VerifyConfig {
+        VerifyConfig { sample_rate: sample_rate, seed: seed, repair: false }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// The key does not decode to a game, or its player bits do not match
+    /// the board.
+    CorruptKey(UtttError),
+    /// The 'plays' column does not match the board.
+    PlayCount { stored: i16, expected: i16 },
+    /// The solutions do not decode, or do not agree on their turns.
+    CorruptSolutions(UtttError),
+    /// A solution's play is not valid for the game.
+    IllegalPlay(Play),
+    /// An ongoing game has a win or tie without a play.
+    MissingPlay(Outcome),
+    /// A finished game's outcome does not match its final state.
+    FinalState { stored: Outcome, expected: Outcome },
+    /// The outcome of a play does not follow from the stored outcome of the
+    /// game that the play leads to.
+    ChildMismatch { play: Play, stored: Outcome, child: Outcome },
+    /// The outcome does not match the best outcome among the stored outcomes
+    /// of every game one play later. Only checked if all of them are stored
+    /// and decided.
+    Minimax { stored: Outcome, derived: Outcome },
+}
+
+/// A problem with the row that has the given key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub key: GameColumns,
+    pub problem: Problem,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifyReport {
+    /// The number of rows read.
+    pub scanned: u64,
+    /// The number of rows checked (the sampled ones).
+    pub checked: u64,
+    pub findings: Vec<Finding>,
+    /// The number of rows deleted (if repairing).
+    pub deleted: u64,
+}
+
+// == public API ===============================================================
+
+/// Checks the rows of the 'solutions' table; see `verify_row`. Children are
+/// looked up in the same table. Returns a report of every problem found.
+pub fn verify_db(conn: &Connection, config: &VerifyConfig)
+                 -> UtttResult<VerifyReport> {
+    let mut report = VerifyReport {
+        scanned: 0,
+        checked: 0,
+        findings: vec![],
+        deleted: 0,
+    };
+    let mut after: Option<GameColumns> = None;
+    loop {
+        let rows = db_scan(conn, after, PAGE_SIZE)?;
+        if rows.is_empty() { break; }
+        after = rows.last().map(|&(key, _, _)| key);
+        for row in rows.iter() {
+            report.scanned += 1;
+            if !is_sampled(row.0, config) { continue; }
+            report.checked += 1;
+            let problems = verify_row(row, |game| read_child(conn, game))?;
+            if problems.is_empty() { continue; }
+            if config.repair && db_delete(conn, row.0)? {
+                report.deleted += 1;
+            }
+            for problem in problems {
+                report.findings.push(Finding { key: row.0, problem: problem });
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Checks one row and returns its problems (none, if it is consistent).
+/// `lookup` returns the stored solutions for a game, or none if it is not
+/// stored. Fails only if `lookup` fails.
+pub fn verify_row<F>(row: &RawRow, lookup: F) -> UtttResult<Vec<Problem>>
+    where F: Fn(&Game) -> UtttResult<Vec<Solution>> {
+    let &(key, plays, ref encoded) = row;
+    let game = match game_from_columns(key) {
+        Ok(game) => game,
+        Err(e) => return Ok(vec![Problem::CorruptKey(e)]),
+    };
+    let mut problems: Vec<Problem> = vec![];
+    let expected_plays = game.board.play_count() as i16;
+    if plays != expected_plays {
+        problems.push(Problem::PlayCount {
+            stored: plays,
+            expected: expected_plays,
+        });
+    }
+    let next_player = game.next_player();
+    let decoded = encoded.iter()
+        .map(|sol| solution_from(*sol, next_player))
+        .collect::<UtttResult<Vec<Solution>>>()
+        .and_then(|sols| turns_and_unknown(&sols).map(|_| sols));
+    let sols = match decoded {
+        Ok(sols) => sols,
+        Err(e) => {
+            problems.push(Problem::CorruptSolutions(e));
+            return Ok(problems);
+        },
+    };
+    match game.state() {
+        GameState::Ongoing => {
+            let before = problems.len();
+            for sol in sols.iter() {
+                match sol.opt_play {
+                    Some(play) if !game.is_valid_play(play) =>
+                        problems.push(Problem::IllegalPlay(play)),
+                    None if is_decided(sol.outcome) =>
+                        problems.push(Problem::MissingPlay(sol.outcome)),
+                    _ => {},
+                }
+            }
+            if problems.len() == before {
+                check_children(&game, &sols, lookup, &mut problems)?;
+            }
+        },
+        state => {
+            let expected = match state {
+                GameState::Won(player) =>
+                    Outcome::Win { player: player, turns: 0 },
+                _ => Outcome::Tie { turns: 0 },
+            };
+            for sol in sols.iter().filter(|sol| sol.outcome != expected) {
+                problems.push(Problem::FinalState {
+                    stored: sol.outcome,
+                    expected: expected,
+                });
+            }
+        },
+    }
+    Ok(problems)
+}
+
+// == helpers ==================================================================
+
+/// Compares the solutions of an ongoing game with the stored solutions of the
+/// games one play later.
+fn check_children<F>(game: &Game, sols: &Vec<Solution>, lookup: F,
+                     problems: &mut Vec<Problem>) -> UtttResult<()>
+    where F: Fn(&Game) -> UtttResult<Vec<Solution>> {
+    let player = game.next_player()
+        .ok_or_else(|| UtttError::invariant("E1901", "game is over"))?;
+    let mut candidates: Vec<Solution> = vec![];
+    let mut complete = true;
+    for play in game.valid_plays() {
+        let mut child = game.clone();
+        child.play(play);
+        let child_sol = match lookup(&child)?.first() {
+            Some(child_sol) => *child_sol,
+            None => { complete = false; continue; },
+        };
+        let candidate = child_sol.futurize(play);
+        for sol in sols.iter().filter(|sol| sol.opt_play == Some(play)) {
+            if is_decided(sol.outcome) && is_decided(candidate.outcome) &&
+                sol.outcome != candidate.outcome {
+                problems.push(Problem::ChildMismatch {
+                    play: play,
+                    stored: sol.outcome,
+                    child: child_sol.outcome,
+                });
+            }
+        }
+        candidates.push(candidate);
+    }
+    complete = complete && candidates.iter().all(|c| is_decided(c.outcome));
+    if let (true, Some(sol)) = (complete, sols.first()) {
+        let derived = best_solutions(player, candidates)?[0].outcome;
+        if is_decided(sol.outcome) && sol.outcome != derived {
+            problems.push(Problem::Minimax {
+                stored: sol.outcome,
+                derived: derived,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Reads a child's solutions. A child that does not decode is treated as not
+/// stored; it is reported when its own row is checked.
+fn read_child(conn: &Connection, game: &Game) -> UtttResult<Vec<Solution>> {
+    match db_read(conn, game) {
+        Err(UtttError::Decode { .. }) => Ok(vec![]),
+        result => result,
+    }
+}
+
+fn is_decided(outcome: Outcome) -> bool {
+    match outcome {
+        Outcome::Unknown { .. } => false,
+        _ => true,
+    }
+}
+
+fn is_sampled(key: GameColumns, config: &VerifyConfig) -> bool {
+    config.sample_rate >= 1.0 ||
+        hash_fraction(&(config.seed, key)) < config.sample_rate
+}
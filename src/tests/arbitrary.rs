@@ -1,6 +1,7 @@
 /// Implementations of the Arbitrary trait. Used by quickcheck tests.
 
 use data::*;
+use solver::{Outcome, Solution};
 use quickcheck::{Arbitrary, Gen};
 
 impl Arbitrary for Game {
@@ -56,3 +57,15 @@ impl Arbitrary for Player {
         g.gen::<Player>()
     }
 }
+
+impl Arbitrary for Solution {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        g.gen::<Solution>()
+    }
+}
+
+impl Arbitrary for Outcome {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        g.gen::<Outcome>()
+    }
+}
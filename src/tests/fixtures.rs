@@ -0,0 +1,12 @@
+/// Fixtures shared by the tests of several modules.
+
+use data::*;
+use rand::{SeedableRng, XorShiftRng};
+use random::random_games;
+
+/// Returns the last 8 games of a seeded random game, from the last one back.
+pub fn seeded_games() -> Vec<Game> {
+    let seed: [u32; 4] = [1456198685, 762656086, 844876651, 1745969790];
+    let mut rng: XorShiftRng = SeedableRng::from_seed(seed);
+    random_games(&mut rng).into_iter().rev().take(8).collect::<Vec<Game>>()
+}
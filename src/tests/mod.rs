@@ -3,5 +3,8 @@
 #[cfg(test)]
 pub mod arbitrary;
 
+#[cfg(test)]
+pub mod fixtures;
+
 #[cfg(test)]
 mod various;
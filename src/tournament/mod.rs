@@ -0,0 +1,254 @@
+/// Tournaments between engines (ways of choosing plays).
+///
+/// Every pair of engines plays the same number of games. Each game starts from
+/// an opening: a few random plays, drawn from the tournament's seeded random
+/// number generator. Each opening is played twice per pairing, with the colors
+/// swapped, so neither engine gains from a lucky opening or from moving first.
+///
+/// All engines share one `Stack`, so solutions found by one are reused by the
+/// others. (A solution at a given depth is the same whoever asks for it.)
+///
+/// Ratings are Elo estimates: an engine's score against the engines it played,
+/// converted to a rating difference from their average. The error is the half
+/// width of a 95% confidence interval, from the variance of its game scores.
+
+use constants::*;
+use data::*;
+use error::*;
+use interactive::best_play;
+use random::random_valid_play;
+use rand::Rng;
+use solver::*;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Engine {
+    /// Plays at random.
+    Random,
+    /// Solves at a fixed depth, and picks at random among the best plays.
+    Solver { depth: Count },
+    /// Deepens up to a depth, within a time budget (see 'interactive').
+    Deepening { depth: Count, budget_ms: u64 },
+}
+
+/// A finished game of a tournament.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    /// The engines (indexes into the tournament's engines) playing X and O.
+    pub x: usize,
+    pub o: usize,
+    /// The number of opening plays (at the start of `plays`).
+    pub opening: usize,
+    pub plays: Vec<Play>,
+    pub winner: Option<Player>,
+}
+
+/// An engine's results, and its rating.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub engine: usize,
+    pub games: u32,
+    pub wins: u32,
+    pub ties: u32,
+    pub losses: u32,
+    pub elo: f64,
+    pub error: f64,
+}
+
+impl Engine {
+    /// Parses `random`, `solver:<depth>` or `deepening:<depth>:<ms>`.
+    pub fn parse(s: &str) -> UtttResult<Engine> {
+        let parts = s.split(':').collect::<Vec<&str>>();
+        let number = |x: &str| x.parse::<u64>().ok();
+        // Checked before the cast, so that 'solver:337' is not depth 81.
+        let plays = |x: &str| {
+            number(x).filter(|&d| d <= 81).map(|d| d as Count)
+        };
+        match parts.as_slice() {
+            ["random"] => Some(Engine::Random),
+            ["solver", depth] => plays(depth)
+                .map(|d| Engine::Solver { depth: d }),
+            ["deepening", depth, ms] => plays(depth).and_then(|d| {
+                number(ms).map(|ms| Engine::Deepening {
+                    depth: d,
+                    budget_ms: ms,
+                })
+            }),
+            _ => None,
+        }.ok_or_else(|| {
+            UtttError::usage("E4701", &format!(
+                "unknown engine '{}' (expected random, solver:<depth> or \
+                 deepening:<depth>:<ms>)", s))
+        })
+    }
+
+    /// Returns the engine as `parse` reads it.
+    pub fn name(&self) -> String {
+        match *self {
+            Engine::Random => "random".to_string(),
+            Engine::Solver { depth } => format!("solver:{}", depth),
+            Engine::Deepening { depth, budget_ms } =>
+                format!("deepening:{}:{}", depth, budget_ms),
+        }
+    }
+
+    /// Chooses a play for an ongoing game.
+    pub fn choose<R: Rng>(&self, stack: &Stack, game: &Game, rng: &mut R)
+                          -> UtttResult<Play> {
+        let opt_play = match *self {
+            Engine::Random => None,
+            Engine::Solver { depth } => {
+                let plays = stack.get_and_put(game, depth)?.iter()
+                    .filter_map(|sol| sol.opt_play)
+                    .filter(|&play| game.is_valid_play(play))
+                    .collect::<Vec<Play>>();
+                rng.choose(&plays).cloned()
+            },
+            Engine::Deepening { depth, budget_ms } => {
+                let budget = Duration::from_millis(budget_ms);
+                Some(best_play(stack, game, depth, Some(budget))?.play)
+            },
+        };
+        opt_play.or_else(|| random_valid_play(game, rng)).ok_or_else(|| {
+            UtttError::invariant("E4702", "ongoing game without plays")
+        })
+    }
+}
+
+impl GameRecord {
+    /// Returns an engine's score: 1 for a win, 0.5 for a tie, 0 for a loss;
+    /// None if it did not play.
+    pub fn score(&self, engine: usize) -> Option<f64> {
+        let player = if engine == self.x {
+            Player::X
+        } else if engine == self.o {
+            Player::O
+        } else {
+            return None;
+        };
+        Some(match self.winner {
+            None => 0.5,
+            Some(winner) if winner == player => 1.0,
+            Some(_) => 0.0,
+        })
+    }
+
+    /// Returns the final game.
+    pub fn game(&self) -> Game {
+        let mut game = EMPTY_GAME;
+        for &play in self.plays.iter() { game.play(play); }
+        game
+    }
+}
+
+// == playing ==================================================================
+
+/// Returns `count` openings of `plays` random plays each (fewer, if a game
+/// ends first).
+pub fn openings<R: Rng>(rng: &mut R, count: usize, plays: usize)
+                        -> Vec<Vec<Play>> {
+    (0 .. count).map(|_| {
+        let mut game = EMPTY_GAME;
+        let mut opening = Vec::with_capacity(plays);
+        for _ in 0 .. plays {
+            match random_valid_play(&game, rng) {
+                Some(play) => {
+                    game.play(play);
+                    opening.push(play);
+                },
+                None => break,
+            }
+        }
+        opening
+    }).collect()
+}
+
+/// Plays one game from an opening: engine `x` against engine `o`.
+pub fn play_game<R: Rng>(stack: &Stack, engines: &[Engine], x: usize,
+                         o: usize, opening: &[Play], rng: &mut R)
+                         -> UtttResult<GameRecord> {
+    let mut game = EMPTY_GAME;
+    let mut plays = opening.to_vec();
+    for &play in opening.iter() { game.play(play); }
+    while let Some(player) = game.next_player() {
+        let engine = if player == Player::X { x } else { o };
+        let play = engines[engine].choose(stack, &game, rng)?;
+        if !game.play(play) {
+            return Err(UtttError::invariant("E4703", "invalid engine play"));
+        }
+        plays.push(play);
+    }
+    Ok(GameRecord {
+        x: x,
+        o: o,
+        opening: opening.len(),
+        plays: plays,
+        winner: game.winner(),
+    })
+}
+
+/// Returns the pairings of a round robin: every pair of engines, `games` times,
+/// with the colors swapped every other game. Each item is (X, O, opening).
+pub fn pairings(engines: usize, games: usize) -> Vec<(usize, usize, usize)> {
+    let mut pairings = vec![];
+    for i in 0 .. engines {
+        for j in (i + 1) .. engines {
+            for g in 0 .. games {
+                let (x, o) = if g % 2 == 0 { (i, j) } else { (j, i) };
+                pairings.push((x, o, g / 2));
+            }
+        }
+    }
+    pairings
+}
+
+// == rating ===================================================================
+
+/// Returns each engine's results and rating, from the best to the worst.
+pub fn standings(records: &[GameRecord], engines: usize) -> Vec<Standing> {
+    let mut standings = (0 .. engines).map(|engine| {
+        let scores = records.iter()
+            .filter_map(|record| record.score(engine))
+            .collect::<Vec<f64>>();
+        let count = |score: f64| {
+            scores.iter().filter(|&&s| s == score).count() as u32
+        };
+        let (elo, error) = rating(&scores);
+        Standing {
+            engine: engine,
+            games: scores.len() as u32,
+            wins: count(1.0),
+            ties: count(0.5),
+            losses: count(0.0),
+            elo: elo,
+            error: error,
+        }
+    }).collect::<Vec<Standing>>();
+    standings.sort_by(|a, b| b.elo.partial_cmp(&a.elo).expect("E4704"));
+    standings
+}
+
+/// Returns the Elo difference for a list of game scores, and its error (see
+/// the module documentation).
+pub fn rating(scores: &[f64]) -> (f64, f64) {
+    if scores.is_empty() { return (0.0, 0.0); }
+    let n = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / n;
+    let variance = scores.iter().map(|s| (s - mean) * (s - mean))
+        .sum::<f64>() / n;
+    // A perfect score has no finite rating; count it as half a game less.
+    let clamp = |p: f64| p.max(0.5 / n).min(1.0 - 0.5 / n);
+    // If every score is the same, the variance says nothing; use that of
+    // win-or-lose games at the clamped score instead.
+    let variance = if variance > 0.0 {
+        variance
+    } else {
+        clamp(mean) * (1.0 - clamp(mean))
+    };
+    let margin = 1.96 * (variance / n).sqrt();
+    let elo = |p: f64| -400.0 * (1.0 / clamp(p) - 1.0).log10();
+    (elo(mean), (elo(mean + margin) - elo(mean - margin)) / 2.0)
+}
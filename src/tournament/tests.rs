@@ -0,0 +1,79 @@
+use rand::{SeedableRng, XorShiftRng};
+use tournament::*;
+
+fn record(x: usize, o: usize, winner: Option<Player>) -> GameRecord {
+    GameRecord { x: x, o: o, opening: 0, plays: vec![], winner: winner }
+}
+
+#[test]
+fn test_engine_parse() {
+    for s in ["random", "solver:6", "deepening:12:250"].iter() {
+        assert_eq!(Engine::parse(s).map(|e| e.name()), Ok(s.to_string()));
+    }
+    for s in ["solver", "solver:x", "solver:82", "solver:256", "solver:337",
+              "deepening:256:10", "mcts", ""].iter() {
+        match Engine::parse(s) {
+            Err(UtttError::Usage { code: "E4701", .. }) => {},
+            other => panic!("unexpected {:?} for '{}'", other, s),
+        }
+    }
+}
+
+#[test]
+fn test_pairings_alternate_colors() {
+    assert_eq!(pairings(3, 2), vec![
+        (0, 1, 0), (1, 0, 0),
+        (0, 2, 0), (2, 0, 0),
+        (1, 2, 0), (2, 1, 0),
+    ]);
+    assert_eq!(pairings(2, 3), vec![(0, 1, 0), (1, 0, 0), (0, 1, 1)]);
+}
+
+#[test]
+fn test_rating() {
+    assert_eq!(rating(&[]), (0.0, 0.0));
+    let (elo, error) = rating(&[1.0, 0.0, 0.5, 0.5]);
+    assert!(elo.abs() < 1e-9);
+    assert!(error > 0.0);
+    // 75% is about +191 Elo.
+    let (elo, _) = rating(&[1.0, 1.0, 1.0, 0.0]);
+    assert!((elo - 190.85).abs() < 0.01, "{}", elo);
+    // A perfect score is finite, and uncertain.
+    let (elo, error) = rating(&[1.0, 1.0]);
+    assert!(elo.is_finite() && elo > 0.0);
+    assert!(error > 0.0);
+}
+
+#[test]
+fn test_standings() {
+    let records = vec![
+        record(0, 1, Some(Player::X)),
+        record(1, 0, Some(Player::O)),
+        record(0, 2, None),
+        record(2, 1, Some(Player::X)),
+    ];
+    let standings = standings(&records, 3);
+    assert_eq!(standings.iter().map(|s| s.engine).collect::<Vec<usize>>(),
+               vec![0, 2, 1]);
+    let first = &standings[0];
+    assert_eq!((first.games, first.wins, first.ties, first.losses),
+               (3, 2, 1, 0));
+}
+
+#[test]
+fn test_play_game_from_opening() {
+    let stack = CPU_Stack::new();
+    let mut rng: XorShiftRng = SeedableRng::from_seed([1, 2, 3, 4]);
+    let openings = openings(&mut rng, 1, 4);
+    assert_eq!(openings[0].len(), 4);
+    let engines = [Engine::Random, Engine::Solver { depth: 1 }];
+    let record = play_game(&stack, &engines, 1, 0, &openings[0], &mut rng)
+        .expect("E4711");
+    assert!(record.plays.starts_with(&openings[0]));
+    let game = record.game();
+    assert!(game.is_over());
+    assert_eq!(record.winner, game.winner());
+    assert_eq!(record.score(1).map(|s| s + record.score(0).unwrap()),
+               Some(1.0));
+    assert_eq!(record.score(2), None);
+}
@@ -0,0 +1,238 @@
+/// Training data.
+///
+/// Samples positions from random games, solves them with a `Stack`, and writes
+/// each as a labelled example for machine learning: the board's features, and
+/// the solver's outcome, number of turns and best plays.
+///
+/// Locations and sub-boards are indexed as in the 'features' module: a
+/// location's index is its policy index, `row * 9 + column`.
+///
+/// With augmentation, each example is also written in its 7 other symmetries
+/// (rotations and reflections of the board). A rotation or reflection of the
+/// 9x9 grid maps sub-boards to sub-boards in the same way as it maps slots
+/// within them, so the rules (and the solutions) are the same.
+
+use data::*;
+use error::*;
+use features::*;
+use solver::*;
+use std::io::Write;
+
+#[cfg(test)]
+mod tests;
+
+// == file formats =============================================================
+
+/// The format of a tensor file: a header, then fixed-size records, so that the
+/// file can be read as an array (for example, by `numpy.fromfile` with an
+/// offset).
+///
+/// section   note
+/// -------   ----
+/// header    magic number (TRAINING_MAGIC, 8 bytes), format version
+///           (TRAINING_VERSION, 4 bytes, little-endian), record size
+///           (RECORD_SIZE, 4 bytes, little-endian)
+/// records   one per example, RECORD_SIZE bytes each; see below
+///
+/// Fields of each record, all bytes. A mask field has one byte (0 or 1) per
+/// location or sub-board, by index.
+///
+/// field     size   value
+/// -----     ----   -----
+/// x         81     mask of X's slots
+/// o         81     mask of O's slots
+/// legal     81     mask of valid plays
+/// targets   9      mask of the sub-boards the next play may be in
+/// to_move   1      the next player: 1 for X, 2 for O, 0 if the game is over
+/// outcome   1      0 unknown, 1 X wins, 2 O wins, 3 tie
+/// turns     1      the outcome's number of turns
+/// best      81     mask of the best plays
+pub const TRAINING_MAGIC: &'static [u8; 8] = b"UTTTTRNG";
+
+pub const TRAINING_VERSION: u32 = 1;
+
+pub const RECORD_SIZE: usize = 81 * 4 + 9 + 3;
+
+/// The number of symmetries of the board (including the identity).
+pub const SYMMETRIES: u8 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrainingFormat {
+    /// The tensor format described above.
+    Tensor,
+    /// Comma-separated values, with a header line. 'cells' holds 81
+    /// characters ('X', 'O' or '.'), 'legal' and 'targets' hold a '0' or '1'
+    /// per index, 'to_move' and 'outcome' are 'X', 'O', 'tie', 'unknown' or
+    /// '-', and 'best_plays' holds space-separated indexes.
+    Csv,
+}
+
+const CSV_HEADER: &'static str =
+    "cells,to_move,legal,targets,outcome,turns,best_plays\n";
+
+// == examples =================================================================
+
+/// A labelled example. Masks are indexed as described in the module docs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Example {
+    pub x: [bool; 81],
+    pub o: [bool; 81],
+    pub legal: [bool; 81],
+    pub targets: [bool; 9],
+    pub to_move: Option<Player>,
+    pub outcome: Outcome,
+    pub best: [bool; 81],
+}
+
+impl Example {
+    /// Returns the example for a game and its (best) solutions.
+    pub fn new(game: &Game, solutions: &Vec<Solution>) -> Example {
+        let mut example = Example {
+            x: [false; 81],
+            o: [false; 81],
+            legal: [false; 81],
+            targets: [false; 9],
+            to_move: game.next_player(),
+            outcome: solutions.first().map_or(Outcome::Unknown { turns: 0 },
+                                              |sol| sol.outcome),
+            best: [false; 81],
+        };
+        // The planes are from the mover's point of view; the masks are not.
+        let planes = planes(game);
+        let (x, o) = match side_to_move(game) {
+            Player::X => (OWN, OPPONENT),
+            Player::O => (OPPONENT, OWN),
+        };
+        for i in 0 .. POLICY_SIZE {
+            example.x[i] = planes[x][i] == 1.0;
+            example.o[i] = planes[o][i] == 1.0;
+            example.legal[i] = planes[LEGAL][i] == 1.0;
+            if example.legal[i] { example.targets[sboard_index(i)] = true; }
+        }
+        for play in solutions.iter().filter_map(|sol| sol.opt_play) {
+            example.best[policy_index(play.loc)] = true;
+        }
+        example
+    }
+
+    /// Returns the example under a symmetry (0 is the identity; see
+    /// `transform`).
+    pub fn transformed(&self, symmetry: u8) -> Example {
+        let mut t = self.clone();
+        for i in 0 .. 81 {
+            let j = transform(symmetry, i, 9);
+            t.x[j] = self.x[i];
+            t.o[j] = self.o[i];
+            t.legal[j] = self.legal[i];
+            t.best[j] = self.best[i];
+        }
+        for i in 0 .. 9 {
+            t.targets[transform(symmetry, i, 3)] = self.targets[i];
+        }
+        t
+    }
+
+    /// Returns the tensor record (see `TrainingFormat::Tensor`).
+    pub fn record(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(RECORD_SIZE);
+        for mask in [&self.x, &self.o, &self.legal].iter() {
+            bytes.extend(mask.iter().map(|&b| b as u8));
+        }
+        bytes.extend(self.targets.iter().map(|&b| b as u8));
+        bytes.push(match self.to_move {
+            None => 0,
+            Some(Player::X) => 1,
+            Some(Player::O) => 2,
+        });
+        let (outcome, turns) = match self.outcome {
+            Outcome::Unknown { turns } => (0, turns),
+            Outcome::Win { player: Player::X, turns } => (1, turns),
+            Outcome::Win { player: Player::O, turns } => (2, turns),
+            Outcome::Tie { turns } => (3, turns),
+        };
+        bytes.push(outcome);
+        bytes.push(turns);
+        bytes.extend(self.best.iter().map(|&b| b as u8));
+        bytes
+    }
+
+    /// Returns the CSV line (see `TrainingFormat::Csv`).
+    pub fn csv_line(&self) -> String {
+        let cells = (0 .. 81).map(|i| {
+            if self.x[i] { 'X' } else if self.o[i] { 'O' } else { '.' }
+        }).collect::<String>();
+        let (outcome, turns) = match self.outcome {
+            Outcome::Unknown { turns } => ("unknown".to_string(), turns),
+            Outcome::Win { player, turns } => (format!("{:?}", player), turns),
+            Outcome::Tie { turns } => ("tie".to_string(), turns),
+        };
+        let best = (0 .. 81).filter(|&i| self.best[i])
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>();
+        format!("{},{},{},{},{},{},{}\n", cells,
+                self.to_move.map_or("-".to_string(), |p| format!("{:?}", p)),
+                bits(&self.legal), bits(&self.targets), outcome, turns,
+                best.join(" "))
+    }
+}
+
+/// Writes examples in one of the training formats.
+pub struct TrainingWriter<W: Write> {
+    w: W,
+    format: TrainingFormat,
+    count: u64,
+}
+
+impl<W: Write> TrainingWriter<W> {
+    /// Returns a writer, after writing the header for the format.
+    pub fn new(mut w: W, format: TrainingFormat)
+               -> UtttResult<TrainingWriter<W>> {
+        let header: Vec<u8> = match format {
+            TrainingFormat::Tensor => {
+                let mut bytes = TRAINING_MAGIC.to_vec();
+                bytes.extend_from_slice(&TRAINING_VERSION.to_le_bytes());
+                bytes.extend_from_slice(&(RECORD_SIZE as u32).to_le_bytes());
+                bytes
+            },
+            TrainingFormat::Csv => CSV_HEADER.as_bytes().to_vec(),
+        };
+        w.write_all(&header).map_err(|e| UtttError::from_io("E4801", e))?;
+        Ok(TrainingWriter { w: w, format: format, count: 0 })
+    }
+
+    pub fn write(&mut self, example: &Example) -> UtttResult<()> {
+        let bytes = match self.format {
+            TrainingFormat::Tensor => example.record(),
+            TrainingFormat::Csv => example.csv_line().into_bytes(),
+        };
+        self.w.write_all(&bytes).map_err(|e| UtttError::from_io("E4802", e))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Flushes, and returns the number of examples written.
+    pub fn finish(mut self) -> UtttResult<u64> {
+        self.w.flush().map_err(|e| UtttError::from_io("E4803", e))?;
+        Ok(self.count)
+    }
+}
+
+// == helpers ==================================================================
+
+/// Maps an index of an n x n grid (n is 9 or 3) under a symmetry: symmetries
+/// 0 to 3 rotate by 0, 90, 180 and 270 degrees clockwise, and 4 to 7 do the
+/// same after a reflection across the vertical axis.
+pub fn transform(symmetry: u8, i: usize, n: usize) -> usize {
+    let (mut row, mut col) = (i / n, i % n);
+    if symmetry >= 4 { col = n - 1 - col; }
+    for _ in 0 .. symmetry % 4 {
+        let r = col;
+        col = n - 1 - row;
+        row = r;
+    }
+    row * n + col
+}
+
+fn bits(mask: &[bool]) -> String {
+    mask.iter().map(|&b| if b { '1' } else { '0' }).collect()
+}
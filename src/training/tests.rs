@@ -0,0 +1,92 @@
+use constants::*;
+use training::*;
+
+fn game_from(locs: &[(u8, u8)]) -> Game {
+    let mut game = EMPTY_GAME;
+    for &(row, col) in locs.iter() {
+        let player = game.next_player().expect("E4811");
+        let loc = Loc::from_row_col(RI::from_u8(row), CI::from_u8(col));
+        assert!(game.play(Play { loc: loc, player: player }));
+    }
+    game
+}
+
+#[test]
+fn test_transform() {
+    // A quarter turn clockwise moves the top-left corner to the top right.
+    assert_eq!(transform(1, 0, 9), 8);
+    assert_eq!(transform(1, 0, 3), 2);
+    // The reflection moves it to the top right too, and keeps the row.
+    assert_eq!(transform(4, 9, 9), 17);
+    for symmetry in 0 .. SYMMETRIES {
+        let mut seen = [false; 81];
+        for i in 0 .. 81 { seen[transform(symmetry, i, 9)] = true; }
+        assert!(seen.iter().all(|&b| b), "{}", symmetry);
+        // The center stays put.
+        assert_eq!(transform(symmetry, 40, 9), 40);
+    }
+}
+
+#[test]
+fn test_example_features() {
+    // X plays R0C1, which sends O to the top-middle sub-board.
+    let game = game_from(&[(0, 1)]);
+    let stack = CPU_Stack::new();
+    let solutions = game.solve(1, &stack).expect("E4812");
+    let example = Example::new(&game, &solutions);
+    assert!(example.x[1] && !example.o[1]);
+    assert_eq!(example.to_move, Some(Player::O));
+    assert_eq!(example.targets, [false, true, false, false, false, false,
+                                 false, false, false]);
+    assert_eq!(example.legal.iter().filter(|&&b| b).count(), 9);
+    assert!(example.legal[3] && example.legal[23] && !example.legal[1]);
+    assert!((0 .. 81).all(|i| !example.best[i] || example.legal[i]));
+    let record = example.record();
+    assert_eq!(record.len(), RECORD_SIZE);
+    assert_eq!(record[81 * 3 + 9], 2);
+}
+
+#[test]
+fn test_symmetries_match_the_rules() {
+    // The transformed features equal those of the transformed game.
+    let plays = [(0, 1), (0, 4), (1, 4), (4, 3)];
+    let example = Example::new(&game_from(&plays), &vec![]);
+    for symmetry in 0 .. SYMMETRIES {
+        let moved = plays.iter().map(|&(row, col)| {
+            let i = transform(symmetry, row as usize * 9 + col as usize, 9);
+            ((i / 9) as u8, (i % 9) as u8)
+        }).collect::<Vec<(u8, u8)>>();
+        assert_eq!(example.transformed(symmetry),
+                   Example::new(&game_from(&moved), &vec![]));
+    }
+}
+
+#[test]
+fn test_writer_formats() {
+    let game = game_from(&[(4, 4)]);
+    let example = Example::new(&game, &vec![]);
+    let mut bytes: Vec<u8> = Vec::new();
+    {
+        let mut writer = TrainingWriter::new(&mut bytes, TrainingFormat::Tensor)
+            .expect("E4813");
+        writer.write(&example).expect("E4814");
+        assert_eq!(writer.finish(), Ok(1));
+    }
+    assert_eq!(&bytes[0 .. 8], TRAINING_MAGIC);
+    assert_eq!(bytes.len(), 16 + RECORD_SIZE);
+    let mut csv: Vec<u8> = Vec::new();
+    {
+        let mut writer = TrainingWriter::new(&mut csv, TrainingFormat::Csv)
+            .expect("E4815");
+        writer.write(&example).expect("E4816");
+        writer.finish().expect("E4817");
+    }
+    let text = String::from_utf8(csv).expect("E4818");
+    let line = text.lines().nth(1).expect("E4819");
+    let fields = line.split(',').collect::<Vec<&str>>();
+    assert_eq!(fields.len(), 7);
+    assert_eq!(fields[0].find('X'), Some(40));
+    assert_eq!(fields[1], "O");
+    assert_eq!(fields[3], "000010000");
+    assert_eq!(fields[4], "unknown");
+}